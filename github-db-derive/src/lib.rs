@@ -0,0 +1,127 @@
+//! Derive macros for [github-db](https://github.com/grantshandy/github-db).
+//!
+//! Use them through the `derive` feature of `github-db` rather than depending
+//! on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, LitStr};
+
+/// Implement `github_db::Record` for a struct with named fields.
+///
+/// - `#[id]` marks the field that identifies each document, exactly one is required
+/// - `#[indexed]` fields get an index maintained for them
+/// - `#[unique]` fields are indexed, and no two documents may share a value
+///
+/// Fields are referred to by their serialized name, so `#[serde(rename = "...")]` is respected.
+#[proc_macro_derive(Document, attributes(id, indexed, unique))]
+pub fn derive_document(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Document can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Document can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut id: Option<&Field> = None;
+    let mut indexed = Vec::new();
+    let mut unique = Vec::new();
+
+    for field in fields {
+        for attr in &field.attrs {
+            if attr.path().is_ident("id") {
+                if id.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "only one field can be the #[id]",
+                    ));
+                }
+                id = Some(field);
+            } else if attr.path().is_ident("indexed") {
+                indexed.push(serialized_name(field)?);
+            } else if attr.path().is_ident("unique") {
+                unique.push(serialized_name(field)?);
+            }
+        }
+    }
+
+    let id = match id {
+        Some(id) => id,
+        None => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "a field must be marked #[id]",
+            ))
+        }
+    };
+    let id_ident = &id.ident;
+    let id_ty = &id.ty;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::github_db::Record for #name #ty_generics #where_clause {
+            type Id = #id_ty;
+
+            const INDEXED: &'static [&'static str] = &[#(#indexed),*];
+            const UNIQUE: &'static [&'static str] = &[#(#unique),*];
+
+            fn id(&self) -> &Self::Id {
+                &self.#id_ident
+            }
+        }
+    })
+}
+
+/// The name serde gives a field, taking `#[serde(rename = "...")]` into account.
+fn serialized_name(field: &Field) -> syn::Result<String> {
+    let mut name = match &field.ident {
+        Some(ident) => ident.to_string(),
+        None => return Err(syn::Error::new_spanned(field, "expected a named field")),
+    };
+
+    for attr in &field.attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let rename: LitStr = meta.value()?.parse()?;
+                name = rename.value();
+            } else if meta.input.peek(syn::Token![=]) {
+                // skip the value of any other key-value attribute
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                // and the contents of any list, like `rename(serialize = "...")`
+                let _content;
+                syn::parenthesized!(_content in meta.input);
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(name.trim_start_matches("r#").to_string())
+}
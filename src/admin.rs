@@ -0,0 +1,51 @@
+use crate::{Client, ClientError, CompactOptions, Operation, RepositoryOptions};
+
+impl Client {
+    /// Create everything the database needs that doesn't exist yet, returning whether anything was created.
+    ///
+    /// This creates the repository (initialized with a first commit), the
+    /// client's branch, and the path prefix directory with an empty
+    /// `.gitkeep`, so provisioning a new database takes a single call.
+    /// Running it against an existing database changes nothing.
+    pub async fn ensure_repository(&self, options: RepositoryOptions) -> Result<bool, ClientError> {
+        let mut created = self
+            .inner
+            .provider
+            .ensure_repository(&options, self.inner.branch.as_deref())
+            .await?;
+
+        if let Some(prefix) = &self.inner.path_prefix {
+            let directory = prefix.trim_end_matches('/');
+
+            if !directory.is_empty() && self.list_dir(directory).await?.is_empty() {
+                let path = format!("{directory}/.gitkeep");
+                let message = self.commit_message(&Operation::CreateDatabase, None);
+                self.put_file(&path, &[], None, &message).await?;
+                created = true;
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Squash the branch's history into a single commit, keeping the current content, and return the new head.
+    ///
+    /// Hundreds of single-document commits make clones and history queries
+    /// slow. The most recent [`CompactOptions::retain`] commits are kept.
+    /// The branch is force updated, but only if its head is still the
+    /// commit compacting started from, otherwise this fails with
+    /// [`ClientError::Conflict`] and the branch is left as it is. GitHub has
+    /// no way to make that check and the update atomically, so a commit
+    /// landing in between is still lost; compact while nothing is writing,
+    /// like under a [`lock`](Client::lock).
+    pub async fn compact(&self, mut options: CompactOptions) -> Result<String, ClientError> {
+        if options.message.is_none() {
+            options.message = Some(self.commit_message(&Operation::Compact, None));
+        }
+
+        self.inner
+            .provider
+            .compact(&options, self.inner.branch.as_deref())
+            .await
+    }
+}
@@ -0,0 +1,154 @@
+use std::cmp::Ordering;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{expiry, index::field_value, meta::decode_collection, ClientError, Collection};
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// the number of documents in the collection
+    pub async fn count(&self) -> Result<usize, ClientError> {
+        Ok(self.values().await?.len())
+    }
+
+    /// the number of documents `predicate` returns true for
+    pub async fn count_where<F>(&self, predicate: F) -> Result<usize, ClientError>
+    where
+        F: Fn(&Value) -> bool,
+    {
+        Ok(self.values().await?.iter().filter(|d| predicate(d)).count())
+    }
+
+    /// the sum of `field` across every document, ignoring documents where it isn't a number
+    ///
+    /// nested fields are separated by dots, like `"order.total"`
+    pub async fn sum_by(&self, field: impl AsRef<str>) -> Result<f64, ClientError> {
+        let field = field.as_ref();
+
+        Ok(self
+            .values()
+            .await?
+            .iter()
+            .filter_map(|document| field_value(document, field).and_then(Value::as_f64))
+            .sum())
+    }
+
+    /// the smallest value of `field`, comparing numbers, strings, and booleans with others of the same type
+    pub async fn min_by(&self, field: impl AsRef<str>) -> Result<Option<Value>, ClientError> {
+        self.extreme_by(field.as_ref(), Ordering::Less).await
+    }
+
+    /// the largest value of `field`, comparing numbers, strings, and booleans with others of the same type
+    pub async fn max_by(&self, field: impl AsRef<str>) -> Result<Option<Value>, ClientError> {
+        self.extreme_by(field.as_ref(), Ordering::Greater).await
+    }
+
+    /// the documents grouped by their value of `field`, in the order each value first appears
+    ///
+    /// documents without the field are grouped under [`Value::Null`]
+    pub async fn group_by(
+        &self,
+        field: impl AsRef<str>,
+    ) -> Result<Vec<(Value, Vec<Value>)>, ClientError> {
+        let field = field.as_ref();
+        let mut groups: Vec<(Value, Vec<Value>)> = Vec::new();
+
+        for document in self.values().await? {
+            let key = field_value(&document, field).cloned().unwrap_or_default();
+
+            match groups.iter_mut().find(|(value, _)| *value == key) {
+                Some((_, documents)) => documents.push(document),
+                None => groups.push((key, vec![document])),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    async fn extreme_by(&self, field: &str, want: Ordering) -> Result<Option<Value>, ClientError> {
+        let mut extreme: Option<Value> = None;
+
+        for document in self.values().await? {
+            let value = match field_value(&document, field) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let replace = match &extreme {
+                None => !value.is_null(),
+                Some(current) => compare_values(value, current) == Some(want),
+            };
+            if replace {
+                extreme = Some(value.clone());
+            }
+        }
+
+        Ok(extreme)
+    }
+
+    /// Fetch the documents as JSON without deserializing them into `T`, leaving out expired ones.
+    async fn values(&self) -> Result<Vec<Value>, ClientError> {
+        let file = match self.client.get_file(&self.path).await? {
+            Some(file) => file,
+            None => return Err(ClientError::NoContent),
+        };
+
+        let (meta, mut documents) = decode_collection(self.format.as_ref(), &file.content)?;
+        if let Some(meta) = meta {
+            expiry::retain_unexpired_values(&mut documents, self.key.as_ref(), &meta.expires)?;
+        }
+
+        Ok(documents)
+    }
+}
+
+/// Order two values of the same type, [`None`] if they can't be compared.
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{mock::MockClient, Record};
+
+    #[derive(Serialize, Deserialize)]
+    struct Session {
+        id: u64,
+    }
+
+    impl Record for Session {
+        type Id = u64;
+
+        const INDEXED: &'static [&'static str] = &[];
+        const UNIQUE: &'static [&'static str] = &[];
+
+        fn id(&self) -> &u64 {
+            &self.id
+        }
+    }
+
+    #[tokio::test]
+    async fn expired_documents_arent_counted() {
+        let mock = MockClient::new();
+        let mut sessions = mock.record_collection::<Session>("sessions").await.unwrap();
+
+        sessions.save(Session { id: 1 }).await.unwrap();
+        sessions
+            .save_expiring(Session { id: 2 }, Utc::now() - Duration::hours(1))
+            .await
+            .unwrap();
+
+        assert_eq!(sessions.count().await.unwrap(), 1);
+        assert_eq!(sessions.count_where(|_| true).await.unwrap(), 1);
+        assert_eq!(sessions.stats().await.unwrap().documents, 1);
+    }
+}
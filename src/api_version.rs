@@ -0,0 +1,73 @@
+use reqwest::{header::ACCEPT, RequestBuilder};
+
+/// The media type github documents for its REST API.
+const GITHUB_MEDIA_TYPE: &str = "application/vnd.github+json";
+
+/// The header github reads the requested API version from.
+const VERSION_HEADER: &str = "X-GitHub-Api-Version";
+
+/// The version of github's REST API a client asks for, see [`ClientBuilder::api_version`](crate::ClientBuilder::api_version).
+///
+/// Requests carry an `X-GitHub-Api-Version` header, so the responses a
+/// client gets don't change when github releases a new version. The default
+/// is [`ApiVersion::CURRENT`], the version this release of the library is
+/// written against, which moves forward with library releases rather than
+/// with github's.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiVersion {
+    version: Option<String>,
+    media_type: String,
+}
+
+impl ApiVersion {
+    /// the version of github's REST API this release of the library is written against
+    pub const CURRENT: &'static str = "2022-11-28";
+
+    /// Pin the API to a dated `version`, like `"2022-11-28"`.
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: Some(version.into()),
+            media_type: GITHUB_MEDIA_TYPE.to_string(),
+        }
+    }
+
+    /// Send no version header, so github answers with whatever its default version is.
+    pub fn unversioned() -> Self {
+        Self {
+            version: None,
+            media_type: GITHUB_MEDIA_TYPE.to_string(),
+        }
+    }
+
+    /// ask for `media_type` instead of `application/vnd.github+json`, like a preview of an unreleased API feature
+    pub fn with_media_type(mut self, media_type: impl Into<String>) -> Self {
+        self.media_type = media_type.into();
+        self
+    }
+
+    /// the version sent in the `X-GitHub-Api-Version` header, [`None`] if there isn't one
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// the media type sent in the `Accept` header
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    /// Add the `Accept` and version headers to a request.
+    pub(crate) fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = request.header(ACCEPT, &self.media_type);
+
+        match &self.version {
+            Some(version) => request.header(VERSION_HEADER, version),
+            None => request,
+        }
+    }
+}
+
+impl Default for ApiVersion {
+    fn default() -> Self {
+        Self::new(Self::CURRENT)
+    }
+}
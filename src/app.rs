@@ -0,0 +1,136 @@
+//! Github app authentication, enabled by the default `github-app` feature.
+
+use std::fmt::{self, Debug};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::lock::Mutex;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::header::USER_AGENT;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{http::join_url, ApiVersion, ClientError, CredentialProvider};
+
+/// Installation tokens are refreshed when they're this close to expiring.
+const REFRESH_MARGIN_MINUTES: i64 = 5;
+
+/// Exchanges github app JWTs for installation tokens, caching them until they're close to expiring.
+pub(crate) struct AppInstallation {
+    app_id: u64,
+    installation_id: u64,
+    private_key: String,
+    client: reqwest::Client,
+    host: Url,
+    user_agent: String,
+    api_version: ApiVersion,
+    cached: Mutex<Option<InstallationToken>>,
+}
+
+impl AppInstallation {
+    pub(crate) fn new(
+        app_id: u64,
+        installation_id: u64,
+        private_key: String,
+        client: reqwest::Client,
+        host: Url,
+        user_agent: String,
+        api_version: ApiVersion,
+    ) -> Self {
+        Self {
+            app_id,
+            installation_id,
+            private_key,
+            client,
+            host,
+            user_agent,
+            api_version,
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl Debug for AppInstallation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppInstallation")
+            .field("app_id", &self.app_id)
+            .field("installation_id", &self.installation_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+struct Claims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl CredentialProvider for AppInstallation {
+    async fn token(&self) -> Result<String, ClientError> {
+        let mut cached = self.cached.lock().await;
+
+        if let Some(token) = cached.as_ref() {
+            if token.expires_at - Utc::now() > Duration::minutes(REFRESH_MARGIN_MINUTES) {
+                return Ok(token.token.clone());
+            }
+        }
+
+        let jwt = sign_app_jwt(self.app_id, &self.private_key)?;
+
+        let installation_id = self.installation_id.to_string();
+        let url = join_url(
+            &self.host,
+            ["app", "installations", &installation_id, "access_tokens"],
+        );
+
+        let request = self
+            .api_version
+            .apply(self.client.post(url))
+            .header(USER_AGENT, &self.user_agent)
+            .bearer_auth(jwt);
+
+        let token: InstallationToken = match request.send().await {
+            Ok(response) => match crate::http::check_status(response)?.json().await {
+                Ok(token) => token,
+                Err(e) => return Err(ClientError::Http(e)),
+            },
+            Err(e) => return Err(ClientError::Http(e)),
+        };
+
+        let result = token.token.clone();
+        *cached = Some(token);
+
+        Ok(result)
+    }
+}
+
+/// Sign the short lived JWT github apps use to request installation tokens.
+fn sign_app_jwt(app_id: u64, private_key: &str) -> Result<String, ClientError> {
+    let now = Utc::now().timestamp();
+
+    // backdate a minute to allow for clock drift, github allows at most ten minutes
+    let claims = Claims {
+        iat: now - 60,
+        exp: now + 9 * 60,
+        iss: app_id.to_string(),
+    };
+
+    let key = match EncodingKey::from_rsa_pem(private_key.as_bytes()) {
+        Ok(key) => key,
+        Err(err) => return Err(ClientError::Jwt(err)),
+    };
+
+    match jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key) {
+        Ok(jwt) => Ok(jwt),
+        Err(err) => Err(ClientError::Jwt(err)),
+    }
+}
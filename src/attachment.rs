@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "lfs")]
+use crate::lfs::Pointer;
+use crate::{
+    meta::encode_collection, provider::blob_sha, ClientError, Collection, FileChange, Operation,
+    Record, MAX_CONFLICT_RETRIES,
+};
+
+/// A file attached to a document, see [`Collection::attach`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    pub name: String,
+    /// the MIME type given when it was attached, like `image/png`
+    pub content_type: String,
+    pub size: u64,
+    /// the git blob sha of the file in the repository
+    pub sha: String,
+    /// the sha256 of the content if it's stored in Git LFS, in which case the file is only a pointer to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lfs_oid: Option<String>,
+}
+
+impl<T: Record> Collection<T> {
+    /// store `content` as a file named `name` attached to the document with the primary key `id`
+    ///
+    /// attachments are stored as `<name>.attachments/<id>/<file name>` and
+    /// listed in the collection's [metadata](Collection::meta), and are
+    /// written in the same commit as the listing. attaching a file with an
+    /// existing name replaces it. on github files are uploaded through the
+    /// Git Data API, so they can be up to 100 MB. with the `lfs` feature,
+    /// files over the client's LFS threshold are stored in Git LFS instead.
+    pub async fn attach(
+        &mut self,
+        id: &T::Id,
+        name: impl AsRef<str>,
+        content: impl Into<Vec<u8>>,
+        content_type: impl AsRef<str>,
+    ) -> Result<Attachment, ClientError> {
+        let name = name.as_ref();
+        let content = content.into();
+        let key = attachment_key(id)?;
+        let path = self.attachment_path(&key, name)?;
+
+        let size = content.len() as u64;
+
+        // large files are uploaded to git lfs and replaced with a pointer to them
+        #[cfg(feature = "lfs")]
+        let (content, lfs_oid) = match self.client.inner.lfs_threshold {
+            Some(threshold) if size >= threshold => {
+                let pointer = Pointer::new(&content);
+                self.client
+                    .inner
+                    .provider
+                    .upload_lfs(&pointer.oid, &content)
+                    .await?;
+
+                (pointer.encode(), Some(pointer.oid))
+            }
+            _ => (content, None),
+        };
+        #[cfg(not(feature = "lfs"))]
+        let lfs_oid = None;
+
+        let attachment = Attachment {
+            name: name.to_string(),
+            content_type: content_type.as_ref().to_string(),
+            size,
+            sha: blob_sha(&content),
+            lfs_oid,
+        };
+
+        let mut retries = 0;
+        loop {
+            self.update().await?;
+            if !self.inner.iter().any(|document| document.id() == id) {
+                return Err(ClientError::NotFound);
+            }
+
+            let attachments = self.meta.attachments.entry(key.clone()).or_default();
+            let expected_sha = attachments
+                .iter()
+                .position(|existing| existing.name == name)
+                .map(|position| attachments.remove(position).sha);
+            attachments.push(attachment.clone());
+
+            let file = FileChange {
+                path: path.clone(),
+                content: Some(content.clone()),
+                expected_sha,
+            };
+            let message = self.message(&Operation::Attach {
+                name: name.to_string(),
+            });
+
+            match self.write_attachment(file, &message).await {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(attachment),
+            }
+        }
+    }
+
+    /// the content of the file named `name` attached to the document with the primary key `id`, [`None`] if there isn't one
+    ///
+    /// attachments stored in Git LFS are downloaded from the LFS server
+    pub async fn get_attachment(
+        &self,
+        id: &T::Id,
+        name: impl AsRef<str>,
+    ) -> Result<Option<Vec<u8>>, ClientError> {
+        let name = name.as_ref();
+        let path = self.attachment_path(&attachment_key(id)?, name)?;
+
+        let lfs = self
+            .attachments(id)
+            .iter()
+            .find(|attachment| attachment.name == name)
+            .and_then(|attachment| Some((attachment.lfs_oid.as_ref()?, attachment.size)));
+        if let Some((oid, size)) = lfs {
+            return self
+                .client
+                .inner
+                .provider
+                .download_lfs(oid, size)
+                .await
+                .map(Some);
+        }
+
+        match self.client.get_file(&path).await? {
+            Some(file) => Ok(Some(file.content)),
+            None => Ok(None),
+        }
+    }
+
+    /// delete the file named `name` attached to the document with the primary key `id`
+    ///
+    /// returns whether there was one
+    pub async fn delete_attachment(
+        &mut self,
+        id: &T::Id,
+        name: impl AsRef<str>,
+    ) -> Result<bool, ClientError> {
+        let name = name.as_ref();
+        let key = attachment_key(id)?;
+        let path = self.attachment_path(&key, name)?;
+
+        let mut retries = 0;
+        loop {
+            self.update().await?;
+
+            let attachments = match self.meta.attachments.get_mut(&key) {
+                Some(attachments) => attachments,
+                None => return Ok(false),
+            };
+            let removed = match attachments
+                .iter()
+                .position(|existing| existing.name == name)
+            {
+                Some(position) => attachments.remove(position),
+                None => return Ok(false),
+            };
+            if attachments.is_empty() {
+                self.meta.attachments.remove(&key);
+            }
+
+            let file = FileChange {
+                path: path.clone(),
+                content: None,
+                expected_sha: Some(removed.sha),
+            };
+            let message = self.message(&Operation::DeleteAttachment {
+                name: name.to_string(),
+            });
+
+            match self.write_attachment(file, &message).await {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(true),
+            }
+        }
+    }
+
+    /// the files attached to the document with the primary key `id`, as of the last sync
+    pub fn attachments(&self, id: &T::Id) -> &[Attachment] {
+        match attachment_key(id) {
+            Ok(key) => self.meta.attachments.get(&key).map_or(&[], Vec::as_slice),
+            Err(_) => &[],
+        }
+    }
+
+    fn attachment_path(&self, key: &str, name: &str) -> Result<String, ClientError> {
+        if name.is_empty() || name.contains('/') || name.starts_with('.') {
+            return Err(ClientError::InvalidKey(name.to_string()));
+        }
+
+        Ok(self
+            .client
+            .file_path(&format!("{}.attachments/{key}/{name}", self.name)))
+    }
+
+    /// Commit an attachment change together with the collection's updated metadata.
+    async fn write_attachment(
+        &mut self,
+        file: FileChange,
+        message: &str,
+    ) -> Result<(), ClientError> {
+        let changes = [
+            file,
+            FileChange {
+                path: self.path.clone(),
+                content: Some(encode_collection(
+                    self.format.as_ref(),
+                    &self.meta,
+                    &self.inner,
+                )?),
+                expected_sha: Some(self.sha.clone()),
+            },
+        ];
+
+        self.client
+            .inner
+            .provider
+            .commit_files(&changes, &self.client.commit_options(message))
+            .await?;
+
+        // the commit doesn't return the new sha of the collection file
+        self.update().await
+    }
+}
+
+/// The directory name for a document's attachments.
+fn attachment_key<I: Serialize>(id: &I) -> Result<String, ClientError> {
+    let key = match serde_json::to_value(id) {
+        Ok(Value::String(key)) => key,
+        Ok(value) => value.to_string(),
+        Err(err) => return Err(ClientError::Json(err)),
+    };
+
+    if key.is_empty() || key.contains('/') || key.starts_with('.') {
+        return Err(ClientError::InvalidKey(key));
+    }
+
+    Ok(key)
+}
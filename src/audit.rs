@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+#[cfg(not(target_arch = "wasm32"))]
+use futures::stream::BoxStream;
+#[cfg(target_arch = "wasm32")]
+use futures::stream::LocalBoxStream;
+use futures::{stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    message::{find_trailer, OPERATION_TRAILER},
+    ChangedFile, Client, ClientError, HistoryEntry,
+};
+
+/// How many commits are listed at a time while walking the history.
+const PAGE_SIZE: usize = 100;
+
+/// A commit that changed the database, see [`Client::audit_log`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sha: String,
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+    /// the [`Operation::name`](crate::Operation::name) from the message's `Operation` trailer, like `"insert"` or `"rollback"`
+    ///
+    /// [`None`] for commits with an explicitly given message, or made by something else.
+    pub operation: Option<String>,
+    /// the collections, key-value stores, and other named files the commit changed, sorted
+    pub collections: Vec<String>,
+    /// the database files the commit changed, relative to the path prefix
+    pub files: Vec<ChangedFile>,
+}
+
+/// The commits that changed the database, newest first, see [`Client::audit_log`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type AuditLog<'a> = BoxStream<'a, Result<AuditEntry, ClientError>>;
+/// The commits that changed the database, newest first, see [`Client::audit_log`].
+#[cfg(target_arch = "wasm32")]
+pub type AuditLog<'a> = LocalBoxStream<'a, Result<AuditEntry, ClientError>>;
+
+/// Where the walk through the history is.
+struct Cursor {
+    until: Option<DateTime<Utc>>,
+    /// commits already yielded with the timestamp of `until`, which the next page starts at
+    seen: BTreeSet<String>,
+    done: bool,
+}
+
+impl Client {
+    /// Every commit since `since` that changed a file under the path prefix, newest first.
+    ///
+    /// Each entry says who made the commit and when, which collections it
+    /// changed, the operation from its message, and which files changed by
+    /// how many lines, so changes can be reported on without a clone of the
+    /// repository. Commits are listed a page at a time as the stream is
+    /// polled, with a request per commit for its files.
+    pub fn audit_log(&self, since: DateTime<Utc>) -> AuditLog<'_> {
+        let cursor = Cursor {
+            until: None,
+            seen: BTreeSet::new(),
+            done: false,
+        };
+
+        Box::pin(
+            stream::try_unfold(cursor, move |cursor| async move {
+                if cursor.done {
+                    return Ok(None);
+                }
+
+                let (commits, cursor) = self.audit_page(since, cursor).await?;
+
+                let mut entries = Vec::with_capacity(commits.len());
+                for commit in commits {
+                    entries.push(self.audit_entry(commit).await?);
+                }
+
+                Ok(Some((stream::iter(entries.into_iter().map(Ok)), cursor)))
+            })
+            .try_flatten(),
+        )
+    }
+
+    /// The next page of commits since `since`, and where the page after it starts.
+    async fn audit_page(
+        &self,
+        since: DateTime<Utc>,
+        mut cursor: Cursor,
+    ) -> Result<(Vec<HistoryEntry>, Cursor), ClientError> {
+        let root = self.file_path("");
+        let page = self
+            .inner
+            .provider
+            .list_commits(
+                root.trim_end_matches('/'),
+                PAGE_SIZE,
+                cursor.until,
+                self.inner.branch.as_deref(),
+            )
+            .await?;
+
+        // a page has fewer commits than asked for once the history runs out
+        let exhausted = page.len() < PAGE_SIZE;
+
+        // the next page starts at the oldest timestamp on this one, so it lists some commits again
+        let commits: Vec<HistoryEntry> = page
+            .into_iter()
+            .filter(|commit| !cursor.seen.contains(&commit.sha))
+            .collect();
+        let older = commits.iter().any(|commit| commit.timestamp < since);
+        let commits: Vec<HistoryEntry> = commits
+            .into_iter()
+            .take_while(|commit| commit.timestamp >= since)
+            .collect();
+
+        match commits.last() {
+            Some(last) if !exhausted && !older => {
+                if cursor.until != Some(last.timestamp) {
+                    cursor.seen.clear();
+                }
+                cursor.until = Some(last.timestamp);
+                cursor.seen.extend(
+                    commits
+                        .iter()
+                        .filter(|commit| commit.timestamp == last.timestamp)
+                        .map(|commit| commit.sha.clone()),
+                );
+            }
+            _ => cursor.done = true,
+        }
+
+        Ok((commits, cursor))
+    }
+
+    /// The files a commit changed and what they say about it.
+    async fn audit_entry(&self, commit: HistoryEntry) -> Result<AuditEntry, ClientError> {
+        let root = self.file_path("");
+
+        let files: Vec<ChangedFile> = self
+            .inner
+            .provider
+            .changed_files(&commit.sha)
+            .await?
+            .into_iter()
+            .filter_map(|file| {
+                let path = file.path.strip_prefix(&root)?.to_string();
+                Some(ChangedFile { path, ..file })
+            })
+            .collect();
+
+        let collections: BTreeSet<String> = files
+            .iter()
+            .filter_map(|file| collection_name(&file.path))
+            .collect();
+
+        Ok(AuditEntry {
+            operation: find_trailer(&commit.message, OPERATION_TRAILER).map(str::to_string),
+            collections: collections.into_iter().collect(),
+            files,
+            sha: commit.sha,
+            author: commit.author,
+            timestamp: commit.timestamp,
+            message: commit.message,
+        })
+    }
+}
+
+/// The name of what a file belongs to, like `reviews` for `reviews.json`,
+/// `reviews.index.json`, `reviews.attachments/1/cover.png`, or the
+/// key-value store file `reviews/key.json`.
+fn collection_name(path: &str) -> Option<String> {
+    let name = path.split(['/', '.']).next()?;
+
+    match name.is_empty() {
+        true => None,
+        false => Some(name.to_string()),
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use url::Url;
+
+#[cfg(feature = "github-app")]
+use crate::app::AppInstallation;
+use crate::{ApiVersion, ClientError};
+
+/// How the [`Client`](crate::Client) authenticates with github.
+#[derive(Clone)]
+pub enum Auth {
+    /// A classic personal access token.
+    PersonalAccessToken(String),
+    /// A fine-grained personal access token.
+    FineGrainedToken(String),
+    /// No token, for reading public repositories.
+    ///
+    /// Anonymous clients are [read only](crate::ClientBuilder::read_only)
+    /// and get the forge's much lower rate limit for unauthenticated
+    /// requests (60 an hour on github).
+    Anonymous,
+    /// A github app installation, enabled by the default `github-app` feature.
+    ///
+    /// A JWT signed with the app's private key is exchanged for an
+    /// installation token, which is refreshed automatically before it expires.
+    #[cfg(feature = "github-app")]
+    App {
+        app_id: u64,
+        installation_id: u64,
+        /// the app's PEM encoded RSA private key
+        private_key: String,
+    },
+}
+
+impl Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print secrets
+        match self {
+            Auth::PersonalAccessToken(_) => write!(f, "PersonalAccessToken(..)"),
+            Auth::FineGrainedToken(_) => write!(f, "FineGrainedToken(..)"),
+            Auth::Anonymous => write!(f, "Anonymous"),
+            #[cfg(feature = "github-app")]
+            Auth::App {
+                app_id,
+                installation_id,
+                ..
+            } => f
+                .debug_struct("App")
+                .field("app_id", app_id)
+                .field("installation_id", installation_id)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Supplies the bearer token used to authenticate each request.
+///
+/// The [`Client`](crate::Client) asks for a token before every request
+/// instead of baking one into its headers, so implementations can rotate
+/// tokens without the client being rebuilt.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait CredentialProvider: Debug + Send + Sync {
+    /// the token to send with the next request
+    async fn token(&self) -> Result<String, ClientError>;
+}
+
+impl Auth {
+    #[cfg_attr(not(feature = "github-app"), allow(unused_variables))]
+    pub(crate) fn into_provider(
+        self,
+        client: &reqwest::Client,
+        host: &Url,
+        user_agent: &str,
+        api_version: &ApiVersion,
+    ) -> Arc<dyn CredentialProvider> {
+        match self {
+            Auth::PersonalAccessToken(token) | Auth::FineGrainedToken(token) => {
+                Arc::new(StaticToken(token))
+            }
+            Auth::Anonymous => Arc::new(StaticToken(String::new())),
+            #[cfg(feature = "github-app")]
+            Auth::App {
+                app_id,
+                installation_id,
+                private_key,
+            } => Arc::new(AppInstallation::new(
+                app_id,
+                installation_id,
+                private_key,
+                client.clone(),
+                host.clone(),
+                user_agent.to_string(),
+                api_version.clone(),
+            )),
+        }
+    }
+}
+
+/// A token that never changes.
+struct StaticToken(String);
+
+impl Debug for StaticToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StaticToken(..)")
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl CredentialProvider for StaticToken {
+    async fn token(&self) -> Result<String, ClientError> {
+        Ok(self.0.clone())
+    }
+}
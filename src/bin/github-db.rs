@@ -0,0 +1,327 @@
+//! `github-db`, a command line tool for inspecting and editing a database.
+//!
+//! Built with the `cli` feature. Every command goes through the same
+//! [`Client`] and [`Collection`](github_db::Collection) code as a program
+//! using the library would.
+
+use std::{
+    env,
+    error::Error,
+    fmt::{self, Display},
+    io::{self, Write},
+    process::ExitCode,
+};
+
+use futures::TryStreamExt;
+use github_db::{Auth, Client, ClientError, Collection, Forge, LocalBackend};
+use serde_json::Value;
+
+const USAGE: &str = "\
+github-db: inspect and edit a github-db database
+
+usage: github-db [options] <command>
+
+commands:
+  list                                list every collection
+  get <collection>                    print a collection's documents as JSON
+  insert <collection> --json <doc>    insert a document, `--json -` reads it from stdin
+  history <collection> [--limit <n>]  list the commits that changed a collection, 20 by default
+  export                              print every file in the database as a JSON bundle
+
+options:
+  --repo <owner/repo>  the repository, or the GITHUB_DB_REPO environment variable
+  --token <token>      a token with access to it, or GITHUB_TOKEN, anonymous if neither is set
+  --forge <forge>      github, gitlab, or gitea, github by default
+  --host <url>         the forge's API, for self-hosted instances
+  --enterprise         the host is a GitHub Enterprise Server, serving its API from /api/v3
+  --branch <branch>    the branch to use instead of the default branch
+  --path-prefix <dir>  the directory in the repository collections are kept in
+  --local <dir>        use the database in a local directory instead of a forge
+  --help               print this message";
+
+/// How many commits `history` lists without `--limit`.
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// A mistake in the arguments, which prints the usage.
+#[derive(Debug)]
+struct UsageError(String);
+
+impl Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+#[derive(Debug)]
+enum Command {
+    List,
+    Get(String),
+    Insert(String, String),
+    History(String, usize),
+    Export,
+}
+
+#[derive(Debug, Default)]
+struct Options {
+    repo: Option<String>,
+    token: Option<String>,
+    forge: Forge,
+    host: Option<String>,
+    enterprise: bool,
+    branch: Option<String>,
+    path_prefix: Option<String>,
+    local: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    let (options, command) = match parse(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match client(options) {
+        Ok(client) => run(&client, command).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // the output was piped into something that stopped reading, like `head`
+        Err(err) if is_broken_pipe(err.as_ref()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn is_broken_pipe(err: &(dyn Error + 'static)) -> bool {
+    let kind = match (
+        err.downcast_ref::<io::Error>(),
+        err.downcast_ref::<serde_json::Error>(),
+    ) {
+        (Some(err), _) => Some(err.kind()),
+        (_, Some(err)) => err.io_error_kind(),
+        _ => None,
+    };
+
+    kind == Some(io::ErrorKind::BrokenPipe)
+}
+
+/// Split the arguments into the options and the command to run.
+fn parse(args: Vec<String>) -> Result<(Options, Command), UsageError> {
+    let mut options = Options::default();
+    let mut json = None;
+    let mut limit = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if arg.starts_with("--") => (name.to_string(), Some(value.into())),
+            _ => (arg.clone(), None),
+        };
+        if !name.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        if name == "--enterprise" && inline.is_none() {
+            options.enterprise = true;
+            continue;
+        }
+
+        let value = match inline.or_else(|| args.next()) {
+            Some(value) => value,
+            None => return Err(UsageError(format!("{name} needs a value"))),
+        };
+
+        match name.as_str() {
+            "--repo" => options.repo = Some(value),
+            "--token" => options.token = Some(value),
+            "--forge" => options.forge = parse_forge(&value)?,
+            "--host" => options.host = Some(value),
+            "--branch" => options.branch = Some(value),
+            "--path-prefix" => options.path_prefix = Some(value),
+            "--local" => options.local = Some(value),
+            "--json" => json = Some(value),
+            "--limit" => match value.parse() {
+                Ok(value) => limit = Some(value),
+                Err(_) => {
+                    return Err(UsageError(format!(
+                        "--limit must be a number, not '{value}'"
+                    )))
+                }
+            },
+            _ => return Err(UsageError(format!("unknown option {name}"))),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        Some("list") => Command::List,
+        Some("get") => Command::Get(collection_arg(&mut positional)?),
+        Some("insert") => match json.take() {
+            Some(json) => Command::Insert(collection_arg(&mut positional)?, json),
+            None => {
+                return Err(UsageError(
+                    "insert needs a document with --json".to_string(),
+                ))
+            }
+        },
+        Some("history") => Command::History(
+            collection_arg(&mut positional)?,
+            limit.take().unwrap_or(DEFAULT_HISTORY_LIMIT),
+        ),
+        Some("export") => Command::Export,
+        Some(other) => return Err(UsageError(format!("unknown command '{other}'"))),
+        None => return Err(UsageError("no command given".to_string())),
+    };
+
+    if let Some(extra) = positional.next() {
+        return Err(UsageError(format!("unexpected argument '{extra}'")));
+    }
+    if json.is_some() {
+        return Err(UsageError("--json is only used by insert".to_string()));
+    }
+    if limit.is_some() {
+        return Err(UsageError("--limit is only used by history".to_string()));
+    }
+
+    Ok((options, command))
+}
+
+fn collection_arg(positional: &mut impl Iterator<Item = String>) -> Result<String, UsageError> {
+    match positional.next() {
+        Some(collection) => Ok(collection),
+        None => Err(UsageError("no collection given".to_string())),
+    }
+}
+
+fn parse_forge(forge: &str) -> Result<Forge, UsageError> {
+    match forge {
+        "github" => Ok(Forge::GitHub),
+        "gitlab" => Ok(Forge::GitLab),
+        "gitea" => Ok(Forge::Gitea),
+        _ => Err(UsageError(format!(
+            "unknown forge '{forge}', expected github, gitlab, or gitea"
+        ))),
+    }
+}
+
+/// Build the client the options describe, reading what they leave out from the environment.
+fn client(options: Options) -> Result<Client, Box<dyn Error>> {
+    if let Some(dir) = options.local {
+        return Ok(Client::with_provider(
+            LocalBackend::new(dir),
+            options.path_prefix,
+        ));
+    }
+
+    let repo = match options.repo.or_else(|| env::var("GITHUB_DB_REPO").ok()) {
+        Some(repo) => repo,
+        None => {
+            return Err(UsageError("no repository, set --repo or GITHUB_DB_REPO".into()).into())
+        }
+    };
+    let (owner, repo) = match repo.split_once('/') {
+        Some(parts) => parts,
+        None => return Err(UsageError(format!("repository '{repo}' isn't <owner>/<repo>")).into()),
+    };
+
+    let auth = match options.token.or_else(|| env::var("GITHUB_TOKEN").ok()) {
+        Some(token) => Auth::PersonalAccessToken(token),
+        None => Auth::Anonymous,
+    };
+
+    let mut builder = Client::builder(owner, repo).auth(auth).forge(options.forge);
+    if let Some(host) = options.host {
+        builder = builder.host(host);
+    }
+    if options.enterprise {
+        builder = builder.enterprise();
+    }
+    if let Some(branch) = options.branch {
+        builder = builder.branch(branch);
+    }
+    if let Some(path_prefix) = options.path_prefix {
+        builder = builder.path_prefix(path_prefix);
+    }
+
+    Ok(builder.build()?)
+}
+
+async fn run(client: &Client, command: Command) -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout().lock();
+
+    match command {
+        Command::List => {
+            let mut names = client.list_collections();
+            while let Some(name) = names.try_next().await? {
+                writeln!(stdout, "{name}")?;
+            }
+        }
+        Command::Get(name) => {
+            let mut collection = open_existing(client, &name).await?;
+            serde_json::to_writer_pretty(&mut stdout, collection.data().await?)?;
+            writeln!(stdout)?;
+        }
+        Command::Insert(name, json) => {
+            let json = match json.as_str() {
+                "-" => io::read_to_string(io::stdin())?,
+                _ => json,
+            };
+            let document: Value = serde_json::from_str(&json)?;
+
+            let mut collection = client.collection::<Value>(&name).await?;
+            let commit = collection.insert(document).await?;
+
+            match commit.html_url.or(commit.commit) {
+                Some(commit) => writeln!(stdout, "inserted into '{name}' in {commit}")?,
+                None => writeln!(stdout, "inserted into '{name}'")?,
+            }
+        }
+        Command::History(name, limit) => {
+            let collection = open_existing(client, &name).await?;
+            for entry in collection.history(limit).await? {
+                let summary = entry.message.lines().next().unwrap_or_default();
+                writeln!(
+                    stdout,
+                    "{} {} {} {summary}",
+                    &entry.sha[..entry.sha.len().min(7)],
+                    entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                    entry.author,
+                )?;
+            }
+        }
+        Command::Export => {
+            serde_json::to_writer_pretty(&mut stdout, &client.export().await?)?;
+            writeln!(stdout)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Open a collection without creating it if it doesn't exist, since reading shouldn't change the database.
+async fn open_existing(client: &Client, name: &str) -> Result<Collection<Value>, Box<dyn Error>> {
+    // a read only client fails where it would have created the collection
+    match client.read_only().collection::<Value>(name).await {
+        Err(err) if matches!(err.without_context(), ClientError::ReadOnly) => {
+            Err(format!("there's no collection named '{name}'").into())
+        }
+        Err(err) => Err(err.into()),
+        Ok(collection) => Ok(collection),
+    }
+}
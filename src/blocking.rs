@@ -0,0 +1,181 @@
+//! A synchronous API for applications that don't use async, enabled by the `blocking` feature.
+//!
+//! Every call runs the async [`Client`](crate::Client) to completion on a
+//! small runtime owned by the blocking client. Don't use this from inside an
+//! async runtime, it will panic.
+
+use std::{future::Future, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::runtime::Runtime;
+
+use crate::{Auth, ClientError, CollectionMeta, CommitInfo, Format, HistoryEntry};
+
+/// A synchronous [`Client`](crate::Client).
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Client {
+    /// Create a new [`Client`].
+    pub fn new(
+        auth_token: impl AsRef<str>,
+        owner: impl AsRef<str>,
+        repo: impl AsRef<str>,
+        host: Option<String>,
+        path_prefix: Option<String>,
+    ) -> Result<Self, ClientError> {
+        Self::from_async(crate::Client::new(
+            auth_token,
+            owner,
+            repo,
+            host,
+            path_prefix,
+        )?)
+    }
+
+    /// Create a new [`Client`] using any supported [`Auth`] method.
+    pub fn with_auth(
+        auth: Auth,
+        owner: impl AsRef<str>,
+        repo: impl AsRef<str>,
+        host: Option<String>,
+        path_prefix: Option<String>,
+    ) -> Result<Self, ClientError> {
+        Self::from_async(crate::Client::with_auth(
+            auth,
+            owner,
+            repo,
+            host,
+            path_prefix,
+        )?)
+    }
+
+    /// Wrap an async client, for example one made with [`Client::builder`](crate::Client::builder).
+    pub fn from_async(client: crate::Client) -> Result<Self, ClientError> {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(err) => return Err(ClientError::Io(err)),
+        };
+
+        Ok(Self {
+            inner: client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Return a reference to a collection in the database.
+    ///
+    /// If it doesn't exist in the repository it'll be created automatically
+    pub fn collection<T: Serialize + DeserializeOwned>(
+        &self,
+        name: impl AsRef<str>,
+    ) -> Result<Collection<T>, ClientError> {
+        let inner = self.block_on(self.inner.collection(name))?;
+
+        Ok(Collection {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    /// Return a reference to a collection stored in the given [`Format`].
+    pub fn collection_with_format<T: Serialize + DeserializeOwned>(
+        &self,
+        name: impl AsRef<str>,
+        format: impl Format + 'static,
+    ) -> Result<Collection<T>, ClientError> {
+        let inner = self.block_on(self.inner.collection_with_format(name, format))?;
+
+        Ok(Collection {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+}
+
+/// A synchronous [`Collection`](crate::Collection).
+pub struct Collection<T> {
+    inner: crate::Collection<T>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// update client state to be in line with the database
+    pub fn update(&mut self) -> Result<(), ClientError> {
+        self.runtime.block_on(self.inner.update())
+    }
+
+    /// push document to the database
+    pub fn insert(&mut self, data: T) -> Result<CommitInfo, ClientError> {
+        self.runtime.block_on(self.inner.insert(data))
+    }
+
+    /// push document to the database unless another document has the same key
+    pub fn insert_unique<K, F>(&mut self, data: T, key: F) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        self.runtime.block_on(self.inner.insert_unique(data, key))
+    }
+
+    /// replace the document with the same key as `data`, or push it if there isn't one
+    pub fn upsert<K, F>(&mut self, data: T, key: F) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        self.runtime.block_on(self.inner.upsert(data, key))
+    }
+
+    /// overwrite the entire collection
+    pub fn set_as(&mut self, value: Vec<T>) -> Result<CommitInfo, ClientError> {
+        self.runtime.block_on(self.inner.set_as(value))
+    }
+
+    /// syncs and returns all documents
+    pub fn data(&mut self) -> Result<&Vec<T>, ClientError> {
+        self.runtime.block_on(self.inner.data())
+    }
+
+    /// syncs and returns `limit` documents starting at `offset`
+    pub fn page(&mut self, offset: usize, limit: usize) -> Result<&[T], ClientError> {
+        self.runtime.block_on(self.inner.page(offset, limit))
+    }
+
+    /// the most recent commits that changed this collection, newest first
+    pub fn history(&self, limit: usize) -> Result<Vec<HistoryEntry>, ClientError> {
+        self.runtime.block_on(self.inner.history(limit))
+    }
+
+    /// the documents in this collection as of a specific commit
+    pub fn at_commit(&self, sha: impl AsRef<str>) -> Result<Vec<T>, ClientError> {
+        self.runtime.block_on(self.inner.at_commit(sha))
+    }
+
+    /// the documents in this collection as they were at a point in time
+    pub fn at_time(&self, time: DateTime<Utc>) -> Result<Vec<T>, ClientError> {
+        self.runtime.block_on(self.inner.at_time(time))
+    }
+
+    /// restore the collection to how it was at a specific commit
+    pub fn rollback_to(&mut self, commit_sha: impl AsRef<str>) -> Result<CommitInfo, ClientError> {
+        self.runtime.block_on(self.inner.rollback_to(commit_sha))
+    }
+
+    /// the metadata header as of the last sync
+    pub fn meta(&self) -> &CollectionMeta {
+        self.inner.meta()
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::{meta, ClientError, Collection, Format};
+
+/// A collection file held in memory so documents can borrow from it, see [`Collection::data_borrowed`].
+pub struct BorrowedData {
+    content: Vec<u8>,
+    format: Arc<dyn Format>,
+}
+
+impl<T> Collection<T> {
+    /// fetch the collection file so its documents can be deserialized borrowing from it
+    ///
+    /// for large collections of string-heavy documents this avoids copying
+    /// every string into an owned `String`. it reads the file as stored,
+    /// without syncing the collection, so expired documents that haven't been
+    /// purged are included. only collections stored as plain JSON can be
+    /// borrowed from.
+    pub async fn data_borrowed(&self) -> Result<BorrowedData, ClientError> {
+        // only check the format, so a file that can't be borrowed from isn't fetched for nothing
+        if self.format.as_json(&[]).is_none() {
+            return Err(ClientError::Unsupported("borrowed deserialization"));
+        }
+
+        match self.client.get_file(&self.path).await? {
+            Some(file) => Ok(BorrowedData {
+                content: file.content,
+                format: self.format.clone(),
+            }),
+            None => Err(ClientError::NoContent),
+        }
+    }
+}
+
+impl BorrowedData {
+    /// the documents, borrowing from the file wherever `B` does
+    ///
+    /// `B` is usually a borrowed version of the collection's document type,
+    /// using `&'a str` or, for strings that may contain escapes, `Cow<'a, str>`
+    /// with `#[serde(borrow)]`.
+    pub fn documents<'a, B: Deserialize<'a>>(&'a self) -> Result<Vec<B>, ClientError> {
+        match self.format.as_json(&self.content) {
+            Some(json) => meta::decode_json_collection(json).map(|(_, documents)| documents),
+            None => Err(ClientError::Unsupported("borrowed deserialization")),
+        }
+    }
+
+    /// the raw content of the collection file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.content
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_timer::Delay;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{ClientError, Collection, Operation, MAX_CONFLICT_RETRIES};
+
+/// When a [`BufferedCollection`] flushes on its own.
+#[derive(Clone, Debug)]
+pub struct BufferOptions {
+    /// how often [`BufferedCollection::flusher`] flushes, 10 seconds by default
+    pub interval: Duration,
+    /// flush as soon as this many mutations are pending, 100 by default
+    pub max_pending: usize,
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            max_pending: 100,
+        }
+    }
+}
+
+type SameKey<T> = Box<dyn Fn(&T, &T) -> bool + Send + Sync>;
+
+/// A change waiting to be flushed, kept so it can be applied again on top of newer data.
+enum Mutation<T> {
+    Insert(T),
+    Upsert(T, SameKey<T>),
+    SetAs(Vec<T>),
+}
+
+impl<T: Clone> Mutation<T> {
+    fn apply(&self, documents: &mut Vec<T>) {
+        match self {
+            Mutation::Insert(data) => documents.push(data.clone()),
+            Mutation::Upsert(data, same_key) => {
+                match documents
+                    .iter()
+                    .position(|document| same_key(document, data))
+                {
+                    Some(position) => documents[position] = data.clone(),
+                    None => documents.push(data.clone()),
+                }
+            }
+            Mutation::SetAs(value) => *documents = value.clone(),
+        }
+    }
+}
+
+struct State<T> {
+    collection: Collection<T>,
+    pending: Vec<Mutation<T>>,
+}
+
+/// A write-behind [`Collection`], see [`Collection::buffered`].
+///
+/// Mutations are applied to the local copy immediately and queued, then
+/// written together in a single commit by [`flush`](Self::flush), when
+/// [`BufferOptions::max_pending`] mutations are waiting, or on an interval by
+/// the [`flusher`](Self::flusher). A flush that fails when a mutation is
+/// queued is logged and leaves it pending, rather than failing the mutation.
+/// Pending mutations are lost if the process exits without flushing, so call
+/// [`shutdown`](Self::shutdown) before exiting.
+pub struct BufferedCollection<T> {
+    state: Arc<Mutex<State<T>>>,
+    options: BufferOptions,
+    closed: Arc<AtomicBool>,
+}
+
+impl<T> Clone for BufferedCollection<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            options: self.options.clone(),
+            closed: self.closed.clone(),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Collection<T> {
+    /// buffer writes and flush them in batches, see [`BufferedCollection`]
+    pub fn buffered(self, options: BufferOptions) -> BufferedCollection<T> {
+        BufferedCollection {
+            state: Arc::new(Mutex::new(State {
+                collection: self,
+                pending: Vec::new(),
+            })),
+            options,
+            closed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> BufferedCollection<T> {
+    /// queue pushing a document
+    pub async fn insert(&self, data: T) -> Result<(), ClientError> {
+        self.push(Mutation::Insert(data)).await
+    }
+
+    /// queue replacing the document with the same key as `data`, or pushing it if there isn't one
+    pub async fn upsert<K, F>(&self, data: T, key: F) -> Result<(), ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K + Send + Sync + 'static,
+    {
+        let same_key = Box::new(move |a: &T, b: &T| key(a) == key(b));
+        self.push(Mutation::Upsert(data, same_key)).await
+    }
+
+    /// queue overwriting the entire collection, replacing any mutations still pending
+    pub async fn set_as(&self, value: Vec<T>) -> Result<(), ClientError> {
+        self.push(Mutation::SetAs(value)).await
+    }
+
+    /// the documents with every pending mutation applied, without fetching
+    pub async fn data(&self) -> Vec<T> {
+        self.state.lock().await.collection.inner.clone()
+    }
+
+    /// how many mutations are waiting to be flushed
+    pub async fn pending(&self) -> usize {
+        self.state.lock().await.pending.len()
+    }
+
+    /// write every pending mutation in a single commit
+    ///
+    /// the collection is re-read first and the mutations applied on top of
+    /// it, so changes made by other writers in the meantime are kept. if
+    /// the write fails the mutations stay pending for the next flush.
+    pub async fn flush(&self) -> Result<(), ClientError> {
+        let mut state = self.state.lock().await;
+        Self::flush_locked(&mut state).await
+    }
+
+    /// a future that flushes every [`BufferOptions::interval`] until [`shutdown`](Self::shutdown), to spawn on your runtime
+    ///
+    /// failed flushes are logged and retried at the next interval.
+    pub fn flusher(&self) -> impl Future<Output = ()> {
+        let buffered = self.clone();
+
+        async move {
+            while !buffered.closed.load(Ordering::Acquire) {
+                Delay::new(buffered.options.interval).await;
+
+                if let Err(err) = buffered.flush().await {
+                    log::warn!("flushing buffered writes failed: {err}");
+                }
+            }
+        }
+    }
+
+    /// stop the [`flusher`](Self::flusher) and flush everything still pending
+    pub async fn shutdown(&self) -> Result<(), ClientError> {
+        self.closed.store(true, Ordering::Release);
+        self.flush().await
+    }
+
+    async fn push(&self, mutation: Mutation<T>) -> Result<(), ClientError> {
+        let mut state = self.state.lock().await;
+
+        // an overwrite makes everything before it irrelevant
+        if let Mutation::SetAs(_) = mutation {
+            state.pending.clear();
+        }
+
+        mutation.apply(&mut state.collection.inner);
+        state.pending.push(mutation);
+
+        // the mutation is queued either way, so a failed flush isn't the
+        // caller's to retry, it's tried again by the next flush
+        if state.pending.len() >= self.options.max_pending {
+            if let Err(err) = Self::flush_locked(&mut state).await {
+                log::warn!("flushing buffered writes failed: {err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn flush_locked(state: &mut State<T>) -> Result<(), ClientError> {
+        if state.pending.is_empty() {
+            return Ok(());
+        }
+
+        let operation = Operation::Flush {
+            changes: state.pending.len(),
+        };
+        let message = state.collection.message(&operation);
+        let mut retries = 0;
+
+        loop {
+            state.collection.update().await?;
+            for mutation in &state.pending {
+                mutation.apply(&mut state.collection.inner);
+            }
+
+            match state.collection.write(&operation, &message).await {
+                Ok(_) => {
+                    state.pending.clear();
+                    return Ok(());
+                }
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
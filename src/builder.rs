@@ -0,0 +1,451 @@
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{path::PathBuf, time::Duration};
+
+use url::Url;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::offline::OfflineCache;
+use crate::{
+    http::Http,
+    provider::{GitHub, GitLab, Gitea},
+    pull_request::PullRequestWriter,
+    read_only::ReadOnly,
+    ApiVersion, Auth, Client, ClientError, ClientInner, CommitAuthor, CommitSigner,
+    CredentialProvider, DefaultFormatter, Forge, Limits, MessageFormatter, Metrics, Middleware,
+    Provider, RetryPolicy, WriteMode,
+};
+
+/// Configures and creates a [`Client`], see [`Client::builder`].
+#[derive(Debug)]
+pub struct ClientBuilder {
+    pub(crate) owner: String,
+    pub(crate) repo: String,
+    pub(crate) credentials: Option<Credentials>,
+    pub(crate) host: Option<String>,
+    api_root: Option<String>,
+    enterprise: bool,
+    pub(crate) path_prefix: Option<String>,
+    forge: Forge,
+    branch: Option<String>,
+    author: Option<CommitAuthor>,
+    #[cfg(not(target_arch = "wasm32"))]
+    timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    connect_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pool_max_idle_per_host: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pool_idle_timeout: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<String>,
+    retry: RetryPolicy,
+    http_client: Option<reqwest::Client>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    read_only: bool,
+    write_mode: WriteMode,
+    signer: Option<Arc<dyn CommitSigner>>,
+    #[cfg(feature = "lfs")]
+    lfs_threshold: Option<u64>,
+    limits: Limits,
+    api_version: ApiVersion,
+    messages: Arc<dyn MessageFormatter>,
+    #[cfg(not(target_arch = "wasm32"))]
+    offline_cache: Option<PathBuf>,
+}
+
+/// Where the client gets its token from.
+#[derive(Debug)]
+pub(crate) enum Credentials {
+    Auth(Auth),
+    Provider(Arc<dyn CredentialProvider>),
+}
+
+impl ClientBuilder {
+    pub(crate) fn new(owner: String, repo: String) -> Self {
+        Self {
+            owner,
+            repo,
+            credentials: None,
+            host: None,
+            api_root: None,
+            enterprise: false,
+            path_prefix: None,
+            forge: Forge::default(),
+            branch: None,
+            author: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            connect_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pool_max_idle_per_host: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pool_idle_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
+            retry: RetryPolicy::default(),
+            http_client: None,
+            middleware: Vec::new(),
+            metrics: None,
+            read_only: false,
+            write_mode: WriteMode::Direct,
+            signer: None,
+            #[cfg(feature = "lfs")]
+            lfs_threshold: None,
+            limits: Limits::default(),
+            api_version: ApiVersion::default(),
+            messages: Arc::new(DefaultFormatter),
+            #[cfg(not(target_arch = "wasm32"))]
+            offline_cache: None,
+        }
+    }
+
+    /// authenticate with any supported [`Auth`] method
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.credentials = Some(Credentials::Auth(auth));
+        self
+    }
+
+    /// ask a [`CredentialProvider`] for a token before every request
+    pub fn credentials(mut self, credentials: impl CredentialProvider + 'static) -> Self {
+        self.credentials = Some(Credentials::Provider(Arc::new(credentials)));
+        self
+    }
+
+    /// the kind of forge the repository is hosted on, defaults to [`Forge::GitHub`]
+    pub fn forge(mut self, forge: Forge) -> Self {
+        self.forge = forge;
+        self
+    }
+
+    /// an alternate api location, like a self-hosted gitlab or gitea instance
+    ///
+    /// defaults to the forge's public instance, see [`Forge::default_host`]
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// the path github's REST API is served from on the [`host`](Self::host), like `"/api/v3"`
+    ///
+    /// only needed when the host isn't the API's full url, replaces any
+    /// path in the host. only supported on github.
+    pub fn api_root(mut self, api_root: impl Into<String>) -> Self {
+        self.api_root = Some(api_root.into());
+        self
+    }
+
+    /// the [`host`](Self::host) is a GitHub Enterprise Server, which serves its REST API from `/api/v3`
+    ///
+    /// `/api/v3` is added to hosts given without a path, so the server's own
+    /// url can be used, other hosts and an [`api_root`](Self::api_root) are
+    /// used as they are. only supported on github.
+    pub fn enterprise(mut self) -> Self {
+        self.enterprise = true;
+        self
+    }
+
+    /// a directory in the repository to keep every collection in, like `"data/"`
+    pub fn path_prefix(mut self, path_prefix: impl Into<String>) -> Self {
+        self.path_prefix = Some(path_prefix.into());
+        self
+    }
+
+    /// read from and commit to `branch` instead of the repository's default branch
+    pub fn branch(mut self, branch: impl Into<String>) -> Self {
+        self.branch = Some(branch.into());
+        self
+    }
+
+    /// attribute every commit to this name and email instead of the token's owner
+    pub fn author(mut self, name: impl Into<String>, email: impl Into<String>) -> Self {
+        self.author = Some(CommitAuthor {
+            name: name.into(),
+            email: email.into(),
+        });
+        self
+    }
+
+    /// the longest a single request may take, from connecting until the body is read
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// the longest connecting to the forge may take
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// the most idle connections kept open to the forge
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// how long idle connections are kept open
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// send every request through an HTTP proxy, like `"http://proxy.example.com:8080"`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// the version of github's REST API to ask for, defaults to [`ApiVersion::CURRENT`]
+    ///
+    /// only github reads it, other forges are sent their usual headers.
+    pub fn api_version(mut self, api_version: ApiVersion) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    /// how transient failures are retried
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// send requests through an existing [`reqwest::Client`]
+    ///
+    /// the timeout, connection pool, and proxy settings on this builder are
+    /// ignored in favor of however `client` was configured
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// run [`Middleware`] around every request, in the order it was added
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// report request counts, latencies, retries, and conflicts to [`Metrics`]
+    pub fn metrics(mut self, metrics: impl Metrics + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// commit directly or through pull requests, see [`WriteMode`]
+    pub fn write_mode(mut self, mode: WriteMode) -> Self {
+        self.write_mode = mode;
+        self
+    }
+
+    /// sign every commit with `signer`, only supported on github
+    pub fn signer(mut self, signer: impl CommitSigner + 'static) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// store attachments of at least `threshold` bytes in Git LFS instead of the repository
+    #[cfg(feature = "lfs")]
+    pub fn lfs(mut self, threshold: u64) -> Self {
+        self.lfs_threshold = Some(threshold);
+        self
+    }
+
+    /// refuse writes breaking `limits` before sending them, see [`Limits`]
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// write the message of every commit with `formatter` instead of [`DefaultFormatter`], see [`MessageFormatter`]
+    pub fn message_formatter(mut self, formatter: impl MessageFormatter + 'static) -> Self {
+        self.messages = Arc::new(formatter);
+        self
+    }
+
+    /// keep a snapshot of every file read in `dir`, and read from it when the forge can't be reached
+    ///
+    /// reads that fall back to a snapshot succeed with the data as it was
+    /// when the snapshot was taken, see [`Collection::stale_since`](crate::Collection::stale_since).
+    /// snapshots are kept apart by repository and branch, so one directory
+    /// can be shared by several clients.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn offline_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.offline_cache = Some(dir.into());
+        self
+    }
+
+    /// make every write fail with [`ClientError::ReadOnly`], so the client can't change the repository
+    ///
+    /// clients using [`Auth::Anonymous`] are always read only
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// create the [`Client`]
+    pub fn build(self) -> Result<Client, ClientError> {
+        let host = self.host.as_deref().unwrap_or(self.forge.default_host());
+        let host: Url = match Url::parse(host) {
+            Ok(host) => host,
+            Err(err) => return Err(ClientError::Parse(err)),
+        };
+        check_host(&host)?;
+        let host = match (self.forge, self.api_root.as_deref()) {
+            (Forge::GitHub, api_root) => GitHub::api_base(&host, api_root, self.enterprise)?,
+            (_, None) if !self.enterprise => host,
+            (_, None) => return Err(ClientError::Unsupported("github enterprise")),
+            (_, Some(_)) => return Err(ClientError::Unsupported("custom api roots")),
+        };
+
+        let user_agent = format!("{}-{}", &self.owner, &self.repo);
+        #[cfg(not(target_arch = "wasm32"))]
+        let repository = Some((self.owner.clone(), self.repo.clone()));
+
+        let client = match &self.http_client {
+            Some(client) => client.clone(),
+            None => self.build_http_client()?,
+        };
+
+        let api_version = match self.forge {
+            Forge::GitHub => self.api_version,
+            _ => ApiVersion::unversioned(),
+        };
+
+        let read_only =
+            self.read_only || matches!(self.credentials, Some(Credentials::Auth(Auth::Anonymous)));
+
+        let credentials = match self.credentials {
+            Some(Credentials::Auth(auth)) => {
+                auth.into_provider(&client, &host, &user_agent, &api_version)
+            }
+            Some(Credentials::Provider(provider)) => provider,
+            None => return Err(ClientError::NoCredentials),
+        };
+
+        let http = Http {
+            client,
+            user_agent,
+            api_version,
+            credentials,
+            retry: self.retry,
+            middleware: self.middleware,
+            metrics: self.metrics.clone(),
+            etags: Default::default(),
+        };
+
+        let provider: Arc<dyn Provider> = match self.forge {
+            Forge::GitHub => {
+                let github = GitHub::new(http, host, self.owner, self.repo);
+                match self.signer {
+                    Some(signer) => Arc::new(github.with_signer(signer)),
+                    None => Arc::new(github),
+                }
+            }
+            // only github accepts signatures through its API
+            _ if self.signer.is_some() => return Err(ClientError::Unsupported("commit signing")),
+            Forge::GitLab => Arc::new(GitLab::new(http, host, self.owner, self.repo)),
+            Forge::Gitea => Arc::new(Gitea::new(http, host, self.owner, self.repo)),
+        };
+        let pull_request = match self.write_mode {
+            WriteMode::Direct => None,
+            WriteMode::PullRequest(options) => Some((options, Arc::new(Default::default()))),
+        };
+        let provider: Arc<dyn Provider> = match &pull_request {
+            Some((options, state)) => Arc::new(PullRequestWriter::new(
+                provider,
+                self.branch.clone(),
+                options.clone(),
+                Arc::clone(state),
+            )),
+            None => provider,
+        };
+        let provider: Arc<dyn Provider> = match read_only {
+            true => Arc::new(ReadOnly(provider)),
+            false => provider,
+        };
+
+        Ok(Client {
+            inner: Arc::new(ClientInner {
+                provider,
+                path_prefix: self.path_prefix,
+                branch: self.branch,
+                author: self.author,
+                metrics: self.metrics,
+                dry_run: None,
+                pull_request: pull_request.map(|(_, state)| state),
+                #[cfg(feature = "lfs")]
+                lfs_threshold: self.lfs_threshold,
+                limits: self.limits,
+                messages: self.messages,
+                #[cfg(not(target_arch = "wasm32"))]
+                offline: self
+                    .offline_cache
+                    .map(|dir| Arc::new(OfflineCache::new(dir))),
+                #[cfg(not(target_arch = "wasm32"))]
+                repository,
+            }),
+        })
+    }
+
+    fn build_http_client(&self) -> Result<reqwest::Client, ClientError> {
+        #[allow(unused_mut)]
+        let mut builder = reqwest::Client::builder();
+
+        // browsers manage connections themselves
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(timeout) = self.timeout {
+                builder = builder.timeout(timeout);
+            }
+            if let Some(timeout) = self.connect_timeout {
+                builder = builder.connect_timeout(timeout);
+            }
+            if let Some(max) = self.pool_max_idle_per_host {
+                builder = builder.pool_max_idle_per_host(max);
+            }
+            if let Some(timeout) = self.pool_idle_timeout {
+                builder = builder.pool_idle_timeout(timeout);
+            }
+            if let Some(proxy) = &self.proxy {
+                builder = match reqwest::Proxy::all(proxy) {
+                    Ok(proxy) => builder.proxy(proxy),
+                    Err(err) => return Err(ClientError::Http(err)),
+                };
+            }
+        }
+
+        match builder.build() {
+            Ok(client) => Ok(client),
+            Err(err) => Err(ClientError::Http(err)),
+        }
+    }
+}
+
+/// Check that `host` is somewhere requests can be sent.
+fn check_host(host: &Url) -> Result<(), ClientError> {
+    let problem = if !matches!(host.scheme(), "http" | "https") {
+        "must be an http or https url"
+    } else if host.host_str().is_none() {
+        "has no host name"
+    } else if host.query().is_some() || host.fragment().is_some() {
+        "can't have a query or fragment"
+    } else if !host.username().is_empty() || host.password().is_some() {
+        "can't have credentials, use the builder's auth"
+    } else {
+        return Ok(());
+    };
+
+    // never echo a password back in an error
+    let mut shown = host.clone();
+    let _ = shown.set_username("");
+    let _ = shown.set_password(None);
+
+    Err(ClientError::InvalidHost(format!("'{shown}' {problem}")))
+}
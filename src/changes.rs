@@ -0,0 +1,97 @@
+use std::{collections::HashMap, hash::Hash};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{ClientError, Collection};
+
+/// The documents that differ between two revisions of a collection.
+#[derive(Clone, Debug)]
+pub struct ChangeSet<T> {
+    /// documents that only exist in the newer revision
+    pub added: Vec<T>,
+    /// documents that only exist in the older revision
+    pub removed: Vec<T>,
+    /// documents that exist in both revisions but with different content
+    pub modified: Vec<Modified<T>>,
+}
+
+/// A document that changed between two revisions.
+#[derive(Clone, Debug)]
+pub struct Modified<T> {
+    pub before: T,
+    pub after: T,
+}
+
+impl<T> ChangeSet<T> {
+    /// true if the two revisions contain the same documents
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Collection<T> {
+    /// the documents added, removed, or modified between `commit_sha` and now
+    ///
+    /// documents are matched between the two revisions by the key returned from `id`
+    pub async fn changes_since<K, F>(
+        &mut self,
+        commit_sha: impl AsRef<str>,
+        id: F,
+    ) -> Result<ChangeSet<T>, ClientError>
+    where
+        K: Eq + Hash,
+        F: Fn(&T) -> K,
+    {
+        let before = self.at_commit(commit_sha).await?;
+
+        self.update().await?;
+
+        Ok(diff_documents(before, self.inner.clone(), id))
+    }
+}
+
+/// Diff two lists of documents by key, comparing their serialized form.
+pub(crate) fn diff_documents<T, K, F>(before: Vec<T>, after: Vec<T>, id: F) -> ChangeSet<T>
+where
+    T: Serialize,
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    let index: HashMap<K, usize> = before
+        .iter()
+        .enumerate()
+        .map(|(i, doc)| (id(doc), i))
+        .collect();
+    let mut before: Vec<Option<T>> = before.into_iter().map(Some).collect();
+
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+
+    for doc in after {
+        let old = match index.get(&id(&doc)) {
+            Some(&i) => before[i].take(),
+            None => None,
+        };
+
+        match old {
+            Some(old) => {
+                if serde_json::to_value(&old).ok() != serde_json::to_value(&doc).ok() {
+                    modified.push(Modified {
+                        before: old,
+                        after: doc,
+                    });
+                }
+            }
+            None => added.push(doc),
+        }
+    }
+
+    // anything left over wasn't in the newer revision
+    let removed = before.into_iter().flatten().collect();
+
+    ChangeSet {
+        added,
+        removed,
+        modified,
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    merge::{self, KeyFn},
+    ClientError, Collection, MAX_CONFLICT_RETRIES,
+};
+
+type Merge<T> = Arc<dyn Fn(Vec<T>, Vec<T>) -> Vec<T> + Send + Sync>;
+
+/// What a [`Collection`] does when another writer changed it between reading and writing.
+///
+/// Every strategy gives up with [`ClientError::Conflict`](crate::ClientError::Conflict)
+/// after [`MAX_CONFLICT_RETRIES`] attempts.
+#[derive(Default)]
+pub enum ConflictStrategy<T> {
+    /// return [`ClientError::Conflict`](crate::ClientError::Conflict) immediately
+    FailFast,
+    /// re-read the collection and apply the operation again, the default
+    ///
+    /// if the collection has a [key](Collection::with_key), our changes and
+    /// theirs are merged document by document instead, and only documents
+    /// both writers changed are a [`ClientError::MergeConflict`](crate::ClientError::MergeConflict)
+    #[default]
+    RetryRebase,
+    /// overwrite the other writer's changes with ours
+    LastWriteWins,
+    /// combine our documents and the other writer's, see [`merge`](Self::merge)
+    Merge(Merge<T>),
+}
+
+impl<T> ConflictStrategy<T> {
+    /// resolve conflicts with a callback that receives our documents and then theirs, and returns the documents to write
+    pub fn merge<F>(merge: F) -> Self
+    where
+        F: Fn(Vec<T>, Vec<T>) -> Vec<T> + Send + Sync + 'static,
+    {
+        Self::Merge(Arc::new(merge))
+    }
+}
+
+impl<T> Clone for ConflictStrategy<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::FailFast => Self::FailFast,
+            Self::RetryRebase => Self::RetryRebase,
+            Self::LastWriteWins => Self::LastWriteWins,
+            Self::Merge(merge) => Self::Merge(merge.clone()),
+        }
+    }
+}
+
+impl<T> Debug for ConflictStrategy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FailFast => f.write_str("FailFast"),
+            Self::RetryRebase => f.write_str("RetryRebase"),
+            Self::LastWriteWins => f.write_str("LastWriteWins"),
+            Self::Merge(_) => f.write_str("Merge(..)"),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// use `strategy` when a write from this collection conflicts with another writer
+    pub fn with_conflict_strategy(mut self, strategy: ConflictStrategy<T>) -> Self {
+        self.conflict = strategy;
+        self
+    }
+
+    /// match documents by the key returned from `key` when merging concurrent changes
+    ///
+    /// collections from [`Client::record_collection`](crate::Client::record_collection)
+    /// are keyed by their [`Record::id`](crate::Record::id).
+    ///
+    /// writes and merges fail with [`ClientError::Json`](crate::ClientError::Json)
+    /// if a document's key doesn't serialize.
+    pub fn with_key<K, F>(self, key: F) -> Self
+    where
+        K: Serialize,
+        F: Fn(&T) -> K + Send + Sync + 'static,
+    {
+        self.keyed_by(Arc::new(move |document| {
+            match serde_json::to_value(key(document)) {
+                Ok(key) => Ok(key),
+                Err(err) => Err(ClientError::Json(err)),
+            }
+        }))
+    }
+
+    /// Match documents by `key`, see [`with_key`](Self::with_key).
+    pub(crate) fn keyed_by(mut self, key: KeyFn<T>) -> Self {
+        // a key that doesn't serialize fails the next write or merge with the same error
+        self.base = merge::keyed(&self.inner, &key).unwrap_or_default();
+        self.key = Some(key);
+        self
+    }
+
+    /// the strategy used when a write conflicts with another writer
+    pub fn conflict_strategy(&self) -> &ConflictStrategy<T> {
+        &self.conflict
+    }
+
+    /// Whether an operation should be applied again after its write conflicted.
+    pub(crate) fn rebases(&self, retries: usize) -> bool {
+        matches!(self.conflict, ConflictStrategy::RetryRebase) && retries < MAX_CONFLICT_RETRIES
+    }
+}
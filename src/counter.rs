@@ -0,0 +1,87 @@
+use serde_json::{json, Value};
+
+use crate::{Client, ClientError, Format, Json, Operation, MAX_CONFLICT_RETRIES};
+
+/// A number stored in its own file that can be changed atomically, such as an id sequence.
+///
+/// Every change reads the current value and writes the new one only if
+/// nobody else wrote in between, retrying if they did, so concurrent
+/// increments are never lost.
+pub struct Counter {
+    pub name: String,
+    path: String,
+    client: Client,
+}
+
+impl Counter {
+    pub(crate) fn new(client: Client, name: String) -> Self {
+        let path = client.file_path(&format!("{name}.counter.json"));
+
+        Self { name, path, client }
+    }
+
+    /// the current value, 0 if the counter hasn't been changed yet
+    pub async fn get(&self) -> Result<i64, ClientError> {
+        match self.client.get_file(&self.path).await? {
+            Some(file) => self.decode(&file.content),
+            None => Ok(0),
+        }
+    }
+
+    /// add `n` to the counter, returning the new value
+    pub async fn increment(&self, n: i64) -> Result<i64, ClientError> {
+        self.add(n).await
+    }
+
+    /// subtract `n` from the counter, returning the new value
+    pub async fn decrement(&self, n: i64) -> Result<i64, ClientError> {
+        match n.checked_neg() {
+            Some(n) => self.add(n).await,
+            None => Err(self.overflow()),
+        }
+    }
+
+    async fn add(&self, n: i64) -> Result<i64, ClientError> {
+        let mut retries = 0;
+
+        loop {
+            let (value, sha) = match self.client.get_file(&self.path).await? {
+                Some(file) => (self.decode(&file.content)?, Some(file.sha)),
+                None => (0, None),
+            };
+
+            let value = match value.checked_add(n) {
+                Some(value) => value,
+                None => return Err(self.overflow()),
+            };
+
+            let content = Json.serialize(&json!(value))?;
+            let message = self
+                .client
+                .commit_message(&Operation::UpdateCounter, Some(&self.name));
+            let result = self
+                .client
+                .put_file(&self.path, &content, sha.as_deref(), &message)
+                .await;
+
+            match result {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(_) => return Ok(value),
+            }
+        }
+    }
+
+    fn decode(&self, content: &[u8]) -> Result<i64, ClientError> {
+        let value: Value = Json.deserialize(content)?;
+
+        match serde_json::from_value(value) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(ClientError::Json(err)),
+        }
+    }
+
+    fn overflow(&self) -> ClientError {
+        ClientError::Validation(format!("counter '{}' overflowed", self.name))
+    }
+}
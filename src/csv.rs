@@ -0,0 +1,75 @@
+use std::io::{Read, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Client, ClientError, Collection, Operation, MAX_CONFLICT_RETRIES};
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// sync and write every document to `writer` as CSV, returning how many rows were written
+    ///
+    /// the header row is taken from the field names of the documents, which
+    /// can be renamed with `#[serde(rename)]`. nested structs, maps, and
+    /// lists can't be written as CSV columns.
+    pub async fn export_csv(&mut self, writer: impl Write) -> Result<usize, ClientError> {
+        self.update().await?;
+
+        let mut writer = csv::Writer::from_writer(writer);
+        for document in &self.inner {
+            if let Err(err) = writer.serialize(document) {
+                return Err(ClientError::Csv(err));
+            }
+        }
+
+        match writer.flush() {
+            Ok(()) => Ok(self.inner.len()),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+}
+
+impl Client {
+    /// Append every row of the CSV in `reader` to the collection called `name` in a single commit.
+    ///
+    /// The first row is the header, and columns are matched to the fields
+    /// of `T` by name, in any order. Nothing is written if any row doesn't
+    /// deserialize.
+    pub async fn import_csv<T: Serialize + DeserializeOwned>(
+        &self,
+        name: impl AsRef<str>,
+        reader: impl Read,
+    ) -> Result<Collection<T>, ClientError> {
+        let mut documents = Vec::new();
+        for document in csv::Reader::from_reader(reader).deserialize() {
+            match document {
+                Ok(document) => documents.push(document),
+                Err(err) => return Err(ClientError::Csv(err)),
+            }
+        }
+
+        let mut collection = self.collection::<T>(name).await?;
+        if documents.is_empty() {
+            return Ok(collection);
+        }
+
+        let operation = Operation::Import;
+        let message = collection.message(&operation);
+        let mut retries = 0;
+
+        loop {
+            let length = collection.inner.len();
+            collection.inner.append(&mut documents);
+
+            match collection.write(&operation, &message).await {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => {
+                    retries += 1;
+                    documents = collection.inner.split_off(length);
+                    collection.update().await?;
+                }
+                Err(err) => return Err(err),
+                Ok(_) => break,
+            }
+        }
+
+        Ok(collection)
+    }
+}
@@ -0,0 +1,87 @@
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Client, ClientError, CommitInfo, Format, Json, Operation};
+
+/// A single document stored in its own file, such as a configuration struct.
+///
+/// Unlike a [`Collection`](crate::Collection) the file contains just the
+/// serialized `T` rather than an array. It's stored in `<name>.document.json`,
+/// so it isn't listed by [`Client::list_collections`](crate::Client::list_collections).
+pub struct Document<T> {
+    pub name: String,
+    path: String,
+    client: Client,
+    format: Json,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> Document<T> {
+    pub(crate) fn new(client: Client, name: String) -> Self {
+        let format = Json;
+        let path = client.file_path(&format!("{name}.document.{}", format.extension()));
+
+        Self {
+            name,
+            path,
+            client,
+            format,
+            _marker: PhantomData,
+        }
+    }
+
+    /// read the document, returning [`None`] if it hasn't been written yet
+    pub async fn read(&self) -> Result<Option<T>, ClientError> {
+        match self.client.get_file(&self.path).await? {
+            Some(file) => self.decode(&file.content).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// overwrite the document, creating it if it doesn't exist
+    pub async fn write(&self, value: T) -> Result<CommitInfo, ClientError> {
+        let sha = self.client.get_file(&self.path).await?.map(|file| file.sha);
+
+        self.put(&value, sha.as_deref()).await
+    }
+
+    /// read the document, apply `f` to it and write it back, returning the new value
+    pub async fn modify<F: FnOnce(&mut T)>(&self, f: F) -> Result<T, ClientError> {
+        let file = match self.client.get_file(&self.path).await? {
+            Some(file) => file,
+            None => return Err(ClientError::NotFound),
+        };
+
+        let mut value = self.decode(&file.content)?;
+        f(&mut value);
+
+        self.put(&value, Some(&file.sha)).await?;
+
+        Ok(value)
+    }
+
+    async fn put(&self, value: &T, sha: Option<&str>) -> Result<CommitInfo, ClientError> {
+        let value = match serde_json::to_value(value) {
+            Ok(value) => value,
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+        let content = self.format.serialize(&value)?;
+
+        let message = self
+            .client
+            .commit_message(&Operation::WriteDocument, Some(&self.name));
+        self.client
+            .put_file(&self.path, &content, sha, &message)
+            .await
+    }
+
+    fn decode(&self, content: &[u8]) -> Result<T, ClientError> {
+        let value = self.format.deserialize(content)?;
+
+        match serde_json::from_value(value) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(ClientError::Json(err)),
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    provider::blob_sha, ChangedFile, Client, ClientError, CommitInfo, CommitOptions,
+    CompactOptions, DirEntry, EntryKind, FileChange, HistoryEntry, Polled, Provider,
+    PullRequestOptions, RemoteFile, RepositoryOptions,
+};
+
+/// A commit a dry run client would have made, see [`Client::dry_run`].
+///
+/// Changes to the repository that aren't commits, like creating a branch or
+/// opening a pull request, are recorded as commits without any changes,
+/// with a message saying what would have been done.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DryRunCommit {
+    pub message: String,
+    pub changes: Vec<DryRunChange>,
+}
+
+/// A file a [`DryRunCommit`] would have written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DryRunChange {
+    pub path: String,
+    /// the new content, or [`None`] if the file would have been deleted
+    pub content: Option<Vec<u8>>,
+}
+
+impl DryRunCommit {
+    /// the total size of the content the commit would have written
+    pub fn size(&self) -> usize {
+        self.changes.iter().map(DryRunChange::size).sum()
+    }
+}
+
+impl DryRunChange {
+    /// the size of the content that would have been written, 0 for deletes
+    pub fn size(&self) -> usize {
+        self.content.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// The commits made by a dry run client so far.
+pub(crate) type DryRunLog = Arc<Mutex<Vec<DryRunCommit>>>;
+
+impl Client {
+    /// A copy of this client whose writes are checked but never committed.
+    ///
+    /// Writes are serialized and checked for conflicts against the real
+    /// repository as usual, then recorded in [`dry_run_commits`](Self::dry_run_commits)
+    /// instead of being sent. Reads through the copy see its own writes, so
+    /// multi-step operations like migrations behave as they would for real.
+    /// Compacting, creating the repository or a branch, and opening a pull
+    /// request are recorded too, without changing anything.
+    pub fn dry_run(&self) -> Client {
+        let commits = DryRunLog::default();
+
+        self.derive(|inner| {
+            inner.provider = Arc::new(DryRun {
+                inner: inner.provider.clone(),
+                branch: inner.branch.clone(),
+                files: Mutex::new(BTreeMap::new()),
+                lfs: Mutex::new(BTreeMap::new()),
+                created: AtomicBool::new(false),
+                commits: commits.clone(),
+            });
+            inner.dry_run = Some(commits);
+        })
+    }
+
+    /// every commit a [`dry_run`](Self::dry_run) client would have made, empty for normal clients
+    pub fn dry_run_commits(&self) -> Vec<DryRunCommit> {
+        match &self.inner.dry_run {
+            Some(commits) => lock(commits).clone(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Records writes in memory on top of another provider.
+#[derive(Debug)]
+struct DryRun {
+    inner: Arc<dyn Provider>,
+    /// the branch the client reads and writes, only reads from it see dry run writes
+    branch: Option<String>,
+    /// files written during the dry run, [`None`] if deleted
+    files: Mutex<BTreeMap<String, Option<RemoteFile>>>,
+    /// git lfs objects uploaded during the dry run, by their sha256
+    lfs: Mutex<BTreeMap<String, Vec<u8>>>,
+    /// whether the dry run would have created the repository, which reads then find empty
+    created: AtomicBool,
+    commits: DryRunLog,
+}
+
+impl DryRun {
+    /// Check a file's version token like a real write would.
+    async fn check_sha(&self, path: &str, expected: Option<&str>) -> Result<(), ClientError> {
+        let current = self.get_file(path, self.branch.as_deref()).await?;
+
+        if current.as_ref().map(|file| file.sha.as_str()) == expected {
+            Ok(())
+        } else {
+            Err(ClientError::Conflict)
+        }
+    }
+
+    /// Record a commit, returning a stand-in for its sha.
+    fn commit(&self, changes: Vec<DryRunChange>, message: &str) -> String {
+        let mut files = lock(&self.files);
+        let mut id = Vec::new();
+
+        for change in &changes {
+            let file = change.content.as_ref().map(|content| RemoteFile {
+                content: content.clone(),
+                sha: blob_sha(content),
+            });
+            files.insert(change.path.clone(), file);
+
+            id.extend_from_slice(change.path.as_bytes());
+            id.extend(change.content.iter().flatten());
+        }
+
+        lock(&self.commits).push(DryRunCommit {
+            message: message.to_string(),
+            changes,
+        });
+
+        blob_sha(&id)
+    }
+
+    /// Record something done to the repository that changes no files, returning a stand-in for its sha.
+    fn plan(&self, message: String) -> String {
+        self.commit(Vec::new(), &message)
+    }
+
+    fn reads_overlay(&self, reference: Option<&str>) -> bool {
+        reference == self.branch.as_deref()
+    }
+
+    /// Whether a read failed only because the repository the dry run would have created doesn't exist.
+    fn is_uncreated(&self, err: &ClientError) -> bool {
+        self.created.load(Ordering::Acquire) && err.is_not_found()
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Provider for DryRun {
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        if self.reads_overlay(reference) {
+            if let Some(file) = lock(&self.files).get(path) {
+                return Ok(file.clone());
+            }
+        }
+
+        match self.inner.get_file(path, reference).await {
+            Err(err) if self.is_uncreated(&err) => Ok(None),
+            result => result,
+        }
+    }
+
+    async fn poll_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+        sha: &str,
+    ) -> Result<Polled, ClientError> {
+        if self.reads_overlay(reference) {
+            if let Some(file) = lock(&self.files).get(path) {
+                return match file {
+                    Some(file) if file.sha == sha => Ok(Polled::Unchanged),
+                    file => Ok(Polled::Changed(file.clone())),
+                };
+            }
+        }
+
+        self.inner.poll_file(path, reference, sha).await
+    }
+
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError> {
+        self.check_sha(path, sha).await?;
+
+        let change = DryRunChange {
+            path: path.to_string(),
+            content: Some(content.to_vec()),
+        };
+        let commit = self.commit(vec![change], &options.message);
+
+        Ok(CommitInfo {
+            commit: Some(commit),
+            ..CommitInfo::new(blob_sha(content))
+        })
+    }
+
+    async fn delete_file(
+        &self,
+        path: &str,
+        sha: &str,
+        options: &CommitOptions,
+    ) -> Result<(), ClientError> {
+        self.check_sha(path, Some(sha)).await?;
+
+        let change = DryRunChange {
+            path: path.to_string(),
+            content: None,
+        };
+        self.commit(vec![change], &options.message);
+
+        Ok(())
+    }
+
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError> {
+        let mut entries = match self.inner.list_dir(path, reference).await {
+            Err(err) if self.is_uncreated(&err) => Vec::new(),
+            result => result?,
+        };
+
+        if !self.reads_overlay(reference) {
+            return Ok(entries);
+        }
+
+        let prefix = match path.trim_end_matches('/') {
+            "" => String::new(),
+            path => format!("{path}/"),
+        };
+
+        for (file_path, file) in lock(&self.files).iter() {
+            let relative = match file_path.strip_prefix(&prefix) {
+                Some(relative) => relative,
+                None => continue,
+            };
+
+            let (name, kind) = match relative.split_once('/') {
+                Some((directory, _)) => (directory, EntryKind::Dir),
+                None => (relative, EntryKind::File),
+            };
+            let position = entries.iter().position(|entry| entry.name == name);
+
+            match (file, kind, position) {
+                // deleting one file doesn't remove the directory it was in
+                (None, EntryKind::File, Some(position)) => {
+                    entries.remove(position);
+                }
+                (Some(_), _, None) => entries.push(DirEntry {
+                    name: name.to_string(),
+                    kind,
+                }),
+                _ => {}
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        self.inner.list_commits(path, limit, until, reference).await
+    }
+
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        self.inner.changed_files(sha).await
+    }
+
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        for change in changes {
+            self.check_sha(&change.path, change.expected_sha.as_deref())
+                .await?;
+        }
+
+        let changes = changes
+            .iter()
+            .map(|change| DryRunChange {
+                path: change.path.clone(),
+                content: change.content.clone(),
+            })
+            .collect();
+
+        Ok(self.commit(changes, &options.message))
+    }
+
+    async fn ensure_repository(
+        &self,
+        options: &RepositoryOptions,
+        branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        let _ = options;
+
+        // a repository without the branch has no commits on it to list
+        match self.inner.list_commits("", 1, None, branch).await {
+            Ok(commits) if !commits.is_empty() => return Ok(false),
+            Ok(_) => {}
+            Err(err) if err.is_not_found() => {}
+            Err(err) => return Err(err),
+        }
+
+        match branch {
+            Some(branch) => self.plan(format!("Create Repository With Branch '{branch}'")),
+            None => self.plan("Create Repository".to_string()),
+        };
+        self.created.store(true, Ordering::Release);
+
+        Ok(true)
+    }
+
+    /// the content doesn't change, so only the squashed commit is recorded
+    async fn compact(
+        &self,
+        options: &CompactOptions,
+        branch: Option<&str>,
+    ) -> Result<String, ClientError> {
+        let _ = branch;
+
+        Ok(self.plan(options.message().to_string()))
+    }
+
+    async fn create_branch(&self, branch: &str, from: Option<&str>) -> Result<(), ClientError> {
+        match from {
+            Some(from) => self.plan(format!("Create Branch '{branch}' From '{from}'")),
+            None => self.plan(format!("Create Branch '{branch}'")),
+        };
+
+        Ok(())
+    }
+
+    /// returns 0, since no pull request is opened to number
+    async fn open_pull_request(
+        &self,
+        options: &PullRequestOptions,
+        head: &str,
+        base: Option<&str>,
+    ) -> Result<u64, ClientError> {
+        let base = match base {
+            Some(base) => format!("'{base}'"),
+            None => "the Default Branch".to_string(),
+        };
+        self.plan(format!(
+            "Open Pull Request '{}' Merging '{head}' Into {base}",
+            options.title
+        ));
+
+        Ok(0)
+    }
+
+    async fn upload_lfs(&self, oid: &str, content: &[u8]) -> Result<(), ClientError> {
+        lock(&self.lfs).insert(oid.to_string(), content.to_vec());
+
+        Ok(())
+    }
+
+    async fn download_lfs(&self, oid: &str, size: u64) -> Result<Vec<u8>, ClientError> {
+        if let Some(content) = lock(&self.lfs).get(oid) {
+            return Ok(content.clone());
+        }
+
+        self.inner.download_lfs(oid, size).await
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
@@ -3,10 +3,18 @@ use std::{
     fmt::{self, Display},
 };
 
-use url::ParseError;
+use reqwest::StatusCode;
+use url::{ParseError, Url};
 
 /// Any error this library can return.
+///
+/// New variants may be added, so matches need a wildcard arm. The
+/// [`is_not_found`](Self::is_not_found), [`is_conflict`](Self::is_conflict)
+/// and [`is_rate_limited`](Self::is_rate_limited) helpers check for the
+/// common categories without caring which variant or [`Context`](Self::Context)
+/// an error came in.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ClientError {
     Parse(ParseError),
     Http(reqwest::Error),
@@ -15,6 +23,127 @@ pub enum ClientError {
     BadEncoding(base64::DecodeError),
     NotUtf8,
     NoSha,
+    Format(Box<dyn Error + Send + Sync>),
+    Status(StatusCode),
+    InvalidKey(String),
+    NotFound,
+    Conflict,
+    MissingIndex(String),
+    DuplicateKey,
+    #[cfg(feature = "github-app")]
+    Jwt(jsonwebtoken::errors::Error),
+    NoCredentials,
+    Io(std::io::Error),
+    Unsupported(&'static str),
+    Timeout,
+    SchemaVersion(u32),
+    Encryption,
+    InvalidSignature,
+    MergeConflict(Vec<String>),
+    ReadOnly,
+    Validation(String),
+    InvalidSchema(String),
+    Signing(String),
+    Lfs(String),
+    Locked(String),
+    TooLarge(String),
+    InvalidHost(String),
+    #[cfg(feature = "git")]
+    Git(git2::Error),
+    #[cfg(feature = "csv")]
+    Csv(csv::Error),
+    /// another error, with what the client was doing when it happened
+    Context(ErrorContext, Box<ClientError>),
+}
+
+/// What the client was doing when an error happened, see [`ClientError::Context`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorContext {
+    /// the collection operation, like `"open"` or `"update"`
+    pub operation: Option<String>,
+    pub collection: Option<String>,
+    /// the url of the request that failed
+    pub url: Option<String>,
+}
+
+impl ClientError {
+    /// whether the file or document wasn't found, including a 404 from the forge
+    pub fn is_not_found(&self) -> bool {
+        match self.without_context() {
+            ClientError::NotFound => true,
+            ClientError::Status(status) => *status == StatusCode::NOT_FOUND,
+            _ => false,
+        }
+    }
+
+    /// whether the repository changed during a write, including conflicting merges
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self.without_context(),
+            ClientError::Conflict | ClientError::MergeConflict(_)
+        )
+    }
+
+    /// whether the forge rejected the request for exceeding its rate limit
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// the unexpected status the forge responded with, if that's what the error is
+    pub fn status(&self) -> Option<StatusCode> {
+        match self.without_context() {
+            ClientError::Status(status) => Some(*status),
+            ClientError::Http(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// what the client was doing when the error happened, if known
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ClientError::Context(context, _) => Some(context),
+            _ => None,
+        }
+    }
+
+    /// the error without any [`Context`](Self::Context) around it
+    pub fn without_context(&self) -> &ClientError {
+        match self {
+            ClientError::Context(_, err) => err.without_context(),
+            err => err,
+        }
+    }
+
+    /// Record the url of the request that caused the error.
+    pub(crate) fn in_request(self, url: &Url) -> Self {
+        self.with_context(|context| {
+            context.url.get_or_insert_with(|| url.to_string());
+        })
+    }
+
+    /// Record the collection operation that caused the error.
+    pub(crate) fn in_collection(self, collection: &str, operation: &str) -> Self {
+        self.with_context(|context| {
+            context
+                .collection
+                .get_or_insert_with(|| collection.to_string());
+            context
+                .operation
+                .get_or_insert_with(|| operation.to_string());
+        })
+    }
+
+    /// Fill in the context, adding to an existing one rather than nesting them.
+    fn with_context(self, fill: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (mut context, err) = match self {
+            ClientError::Context(context, err) => (context, err),
+            err => (ErrorContext::default(), Box::new(err)),
+        };
+        fill(&mut context);
+
+        ClientError::Context(context, err)
+    }
 }
 
 impl Display for ClientError {
@@ -27,8 +156,75 @@ impl Display for ClientError {
             ClientError::BadEncoding(e) => write!(f, "Base64 Decode Error: {e}"),
             ClientError::NotUtf8 => write!(f, "Content Not Encoded in Utf8"),
             ClientError::NoSha => write!(f, "No Sha Returned From Github"),
+            ClientError::Format(e) => write!(f, "Format Error: {e}"),
+            ClientError::Status(s) => write!(f, "Unexpected Status From Github: {s}"),
+            ClientError::InvalidKey(k) => write!(f, "Invalid Key: '{k}'"),
+            ClientError::NotFound => write!(f, "File Not Found In Repository"),
+            ClientError::Conflict => write!(f, "Repository Changed During Write"),
+            ClientError::MissingIndex(i) => write!(f, "No Index On Field '{i}'"),
+            ClientError::DuplicateKey => write!(f, "Document With Same Key Already Exists"),
+            #[cfg(feature = "github-app")]
+            ClientError::Jwt(e) => write!(f, "Github App JWT Error: {e}"),
+            ClientError::NoCredentials => write!(f, "No Credentials Given To Client Builder"),
+            ClientError::Io(e) => write!(f, "IO Error: {e}"),
+            ClientError::Unsupported(o) => write!(f, "Provider Does Not Support {o}"),
+            ClientError::Timeout => write!(f, "Request Timed Out"),
+            ClientError::SchemaVersion(v) => write!(f, "Collection Has Newer Schema Version {v}"),
+            ClientError::Encryption => write!(f, "Could Not Encrypt Or Decrypt Content"),
+            ClientError::InvalidSignature => write!(f, "Webhook Signature Does Not Match"),
+            ClientError::MergeConflict(k) => {
+                write!(f, "Conflicting Changes To Documents: {}", k.join(", "))
+            }
+            ClientError::ReadOnly => write!(f, "Client Is Read Only"),
+            ClientError::Validation(e) => write!(f, "Invalid Document: {e}"),
+            ClientError::InvalidSchema(e) => write!(f, "Invalid JSON Schema: {e}"),
+            ClientError::Signing(e) => write!(f, "Could Not Sign Commit: {e}"),
+            ClientError::Lfs(e) => write!(f, "Git LFS Error: {e}"),
+            ClientError::Locked(l) => write!(f, "Lock '{l}' Is Held By Another Client"),
+            ClientError::TooLarge(e) => write!(f, "Write Exceeds Limit: {e}"),
+            ClientError::InvalidHost(e) => write!(f, "Invalid Host: {e}"),
+            #[cfg(feature = "git")]
+            ClientError::Git(e) => write!(f, "Git Error: {e}"),
+            #[cfg(feature = "csv")]
+            ClientError::Csv(e) => write!(f, "CSV Error: {e}"),
+            ClientError::Context(context, e) => {
+                write!(f, "{e}")?;
+
+                let details: Vec<String> = [
+                    ("Operation", &context.operation),
+                    ("Collection", &context.collection),
+                    ("Url", &context.url),
+                ]
+                .into_iter()
+                .filter_map(|(name, value)| value.as_ref().map(|value| format!("{name}: {value}")))
+                .collect();
+
+                match details.is_empty() {
+                    true => Ok(()),
+                    false => write!(f, " ({})", details.join(", ")),
+                }
+            }
         }
     }
 }
 
-impl Error for ClientError {}
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Parse(e) => Some(e),
+            ClientError::Http(e) => Some(e),
+            ClientError::Json(e) => Some(e),
+            ClientError::BadEncoding(e) => Some(e),
+            ClientError::Format(e) => Some(e.as_ref()),
+            #[cfg(feature = "github-app")]
+            ClientError::Jwt(e) => Some(e),
+            ClientError::Io(e) => Some(e),
+            #[cfg(feature = "git")]
+            ClientError::Git(e) => Some(e),
+            #[cfg(feature = "csv")]
+            ClientError::Csv(e) => Some(e),
+            ClientError::Context(_, e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    merge::KeyFn, ClientError, Collection, CommitInfo, Operation, Record, MAX_CONFLICT_RETRIES,
+};
+
+impl<T: Record> Collection<T> {
+    /// like [`save`](Self::save), and remove the document once `expires_at` has passed
+    ///
+    /// saving the document again without an expiry keeps the one it has.
+    pub async fn save_expiring(
+        &mut self,
+        data: T,
+        expires_at: DateTime<Utc>,
+    ) -> Result<CommitInfo, ClientError> {
+        let message = self.message(&Operation::Save);
+        self.save_inner(data, &message, Some(expires_at)).await
+    }
+
+    /// like [`save_expiring`](Self::save_expiring), expiring `ttl` from now
+    pub async fn save_with_ttl(
+        &mut self,
+        data: T,
+        ttl: Duration,
+    ) -> Result<CommitInfo, ClientError> {
+        let expires_at = match chrono::Duration::from_std(ttl) {
+            Ok(ttl) => Utc::now().checked_add_signed(ttl),
+            Err(_) => None,
+        };
+
+        let message = self.message(&Operation::Save);
+        self.save_inner(
+            data,
+            &message,
+            Some(expires_at.unwrap_or(DateTime::<Utc>::MAX_UTC)),
+        )
+        .await
+    }
+
+    /// when the document with the primary key `id` expires, as of the last sync
+    pub fn expires_at(&self, id: &T::Id) -> Result<Option<DateTime<Utc>>, ClientError> {
+        let key = match serde_json::to_value(id) {
+            Ok(key) => expiry_key(&key),
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+
+        Ok(self.meta.expires.get(&key).copied())
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// remove expired documents from the stored collection, returning how many were removed
+    ///
+    /// expired documents are already hidden from reads and dropped by the
+    /// next write, this removes them without waiting for one.
+    pub async fn purge_expired(&mut self) -> Result<usize, ClientError> {
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+
+            let key = match &self.key {
+                Some(key) => key,
+                None => return Ok(0),
+            };
+
+            // expired documents were dropped when syncing, but their expiry is still stored
+            let keys = document_keys(&self.inner, key)?;
+            let purged = self
+                .meta
+                .expires
+                .keys()
+                .filter(|expiring| !keys.contains(*expiring))
+                .count();
+            if purged == 0 {
+                return Ok(0);
+            }
+
+            let message = self.message(&Operation::PurgeExpired);
+            match self.write(&Operation::PurgeExpired, &message).await {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(_) => return Ok(purged),
+            }
+        }
+    }
+
+    /// Drop the expiry of documents that are no longer in the collection, before writing it.
+    pub(crate) fn prune_expiries(&mut self) -> Result<(), ClientError> {
+        if let Some(key) = &self.key {
+            let keys = document_keys(&self.inner, key)?;
+            self.meta
+                .expires
+                .retain(|expiring, _| keys.contains(expiring));
+        }
+
+        Ok(())
+    }
+
+    /// Whether any document had expired when the collection was last synced, so positions in the index file are off.
+    pub(crate) fn has_expired(&self) -> bool {
+        let now = Utc::now();
+        self.meta
+            .expires
+            .values()
+            .any(|expires_at| *expires_at <= now)
+    }
+}
+
+/// Remove the documents that have expired.
+///
+/// Expiries are stored by key, so documents in collections without one never expire.
+pub(crate) fn retain_unexpired<T>(
+    documents: &mut Vec<T>,
+    key: Option<&KeyFn<T>>,
+    expires: &BTreeMap<String, DateTime<Utc>>,
+) -> Result<(), ClientError> {
+    match key {
+        Some(key) if !expires.is_empty() => {
+            retain_by_key(documents, expires, |document| key(document))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Like [`retain_unexpired`], for documents decoded as JSON rather than as `T`.
+pub(crate) fn retain_unexpired_values<T: DeserializeOwned>(
+    documents: &mut Vec<Value>,
+    key: Option<&KeyFn<T>>,
+    expires: &BTreeMap<String, DateTime<Utc>>,
+) -> Result<(), ClientError> {
+    let key = match key {
+        Some(key) if !expires.is_empty() => key,
+        _ => return Ok(()),
+    };
+
+    // keys are taken from `T`, so each document is decoded to find its key
+    retain_by_key(
+        documents,
+        expires,
+        |document| match serde_json::from_value::<T>(document.clone()) {
+            Ok(document) => key(&document),
+            Err(err) => Err(ClientError::Json(err)),
+        },
+    )
+}
+
+fn retain_by_key<D>(
+    documents: &mut Vec<D>,
+    expires: &BTreeMap<String, DateTime<Utc>>,
+    key: impl Fn(&D) -> Result<Value, ClientError>,
+) -> Result<(), ClientError> {
+    let now = Utc::now();
+    let mut expired = Vec::with_capacity(documents.len());
+    for document in documents.iter() {
+        let expires_at = expires.get(&expiry_key(&key(document)?));
+        expired.push(expires_at.is_some_and(|expires_at| *expires_at <= now));
+    }
+
+    let mut expired = expired.into_iter();
+    documents.retain(|_| !expired.next().unwrap_or_default());
+
+    Ok(())
+}
+
+fn document_keys<T>(documents: &[T], key: &KeyFn<T>) -> Result<BTreeSet<String>, ClientError> {
+    documents
+        .iter()
+        .map(|document| key(document).map(|key| expiry_key(&key)))
+        .collect()
+}
+
+/// A document's key as it's stored in [`CollectionMeta::expires`](crate::CollectionMeta::expires).
+pub(crate) fn expiry_key(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        key => key.to_string(),
+    }
+}
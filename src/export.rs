@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Client, ClientError, EntryKind, FileChange, Operation};
+
+/// Every file in a database, produced by [`Client::export`].
+///
+/// Bundles are serializable, so they can be saved as JSON for backups or
+/// [imported](Client::import) into another repository or provider.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bundle {
+    pub exported_at: DateTime<Utc>,
+    /// every file, by its path relative to the client's path prefix
+    pub files: BTreeMap<String, BundleFile>,
+}
+
+/// The content of a file in a [`Bundle`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "encoding", content = "content", rename_all = "lowercase")]
+pub enum BundleFile {
+    /// json files are stored as-is so bundles stay readable
+    Json(Value),
+    /// files in any other format
+    Base64(String),
+}
+
+impl BundleFile {
+    fn new(content: &[u8]) -> Self {
+        match serde_json::from_slice(content) {
+            Ok(value) => BundleFile::Json(value),
+            Err(_) => BundleFile::Base64(base64::encode(content)),
+        }
+    }
+
+    /// the raw content of the file
+    pub fn content(&self) -> Result<Vec<u8>, ClientError> {
+        match self {
+            BundleFile::Json(value) => match serde_json::to_vec(value) {
+                Ok(content) => Ok(content),
+                Err(err) => Err(ClientError::Json(err)),
+            },
+            BundleFile::Base64(content) => match base64::decode(content) {
+                Ok(content) => Ok(content),
+                Err(err) => Err(ClientError::BadEncoding(err)),
+            },
+        }
+    }
+}
+
+/// How [`Client::import`] treats files already in the database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImportMode {
+    /// write the bundle's files over existing ones, keeping files the bundle doesn't have
+    #[default]
+    Merge,
+    /// make the database exactly match the bundle, deleting files it doesn't have
+    Replace,
+}
+
+impl Client {
+    /// Read every file under the path prefix (or the whole repository without one) into a [`Bundle`].
+    pub async fn export(&self) -> Result<Bundle, ClientError> {
+        let mut files = BTreeMap::new();
+
+        for path in self.list_files().await? {
+            if let Some(file) = self.get_file(&self.file_path(&path)).await? {
+                files.insert(path, BundleFile::new(&file.content));
+            }
+        }
+
+        Ok(Bundle {
+            exported_at: Utc::now(),
+            files,
+        })
+    }
+
+    /// Write every file in `bundle` to the database in a single commit, returning the commit's sha.
+    pub async fn import(&self, bundle: &Bundle, mode: ImportMode) -> Result<String, ClientError> {
+        let mut changes = Vec::new();
+
+        for (path, file) in &bundle.files {
+            let path = self.file_path(path);
+            let expected_sha = self.get_file(&path).await?.map(|file| file.sha);
+
+            changes.push(FileChange {
+                path,
+                content: Some(file.content()?),
+                expected_sha,
+            });
+        }
+
+        if mode == ImportMode::Replace {
+            for path in self.list_files().await? {
+                if bundle.files.contains_key(&path) {
+                    continue;
+                }
+
+                let path = self.file_path(&path);
+                if let Some(file) = self.get_file(&path).await? {
+                    changes.push(FileChange {
+                        path,
+                        content: None,
+                        expected_sha: Some(file.sha),
+                    });
+                }
+            }
+        }
+
+        let message = self.commit_message(&Operation::Import, None);
+        self.inner
+            .provider
+            .commit_files(&changes, &self.commit_options(&message))
+            .await
+    }
+
+    /// The path of every file under the path prefix, relative to it.
+    pub(crate) async fn list_files(&self) -> Result<Vec<String>, ClientError> {
+        let root = self.file_path("");
+        let root = root.trim_end_matches('/');
+
+        let mut files = Vec::new();
+        let mut directories = vec![String::new()];
+
+        while let Some(directory) = directories.pop() {
+            let path = match (root.is_empty(), directory.is_empty()) {
+                (_, true) => root.to_string(),
+                (true, false) => directory.clone(),
+                (false, false) => format!("{root}/{directory}"),
+            };
+
+            for entry in self.list_dir(&path).await? {
+                // a local git repository's own files aren't part of the database
+                if entry.name == ".git" {
+                    continue;
+                }
+
+                let relative = match directory.is_empty() {
+                    true => entry.name,
+                    false => format!("{directory}/{}", entry.name),
+                };
+
+                match entry.kind {
+                    EntryKind::File => files.push(relative),
+                    EntryKind::Dir => directories.push(relative),
+                    EntryKind::Other => {}
+                }
+            }
+        }
+
+        files.sort();
+
+        Ok(files)
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{index, Client, ClientError, Collection, Format, Json, RemoteFile};
+
+/// How many collections [`Client::warm`] opens at once.
+const WARM_CONCURRENCY: usize = 4;
+
+/// The files a collection is opened from.
+pub(crate) struct CollectionFiles {
+    pub(crate) collection: Option<RemoteFile>,
+    pub(crate) index: Option<RemoteFile>,
+    #[cfg(feature = "schema")]
+    pub(crate) schema: Option<RemoteFile>,
+}
+
+impl Client {
+    /// Return several collections at once, fetching all of their files together.
+    ///
+    /// On GitHub every file is read in a single GraphQL query instead of a
+    /// request per file, falling back to the REST API when GraphQL isn't
+    /// available. Collections that don't exist are created like
+    /// [`collection`](Self::collection) does.
+    pub async fn fetch_collections<T: Serialize + DeserializeOwned>(
+        &self,
+        names: &[&str],
+    ) -> Result<Vec<Collection<T>>, ClientError> {
+        let format: Arc<dyn Format> = Arc::new(Json);
+
+        let paths: Vec<String> = names
+            .iter()
+            .flat_map(|name| self.collection_file_paths(name, &format))
+            .collect();
+        let mut files = self.get_files(&paths).await?.into_iter();
+
+        let mut collections = Vec::with_capacity(names.len());
+        for name in names {
+            let files = CollectionFiles {
+                collection: files.next().flatten(),
+                index: files.next().flatten(),
+                #[cfg(feature = "schema")]
+                schema: files.next().flatten(),
+            };
+
+            match self
+                .open_collection(name.to_string(), format.clone(), files)
+                .await
+            {
+                Ok(collection) => collections.push(collection),
+                Err(err) => return Err(err.in_collection(name, "open")),
+            }
+        }
+
+        Ok(collections)
+    }
+
+    /// Open several collections concurrently, for loading everything a service needs at startup.
+    ///
+    /// Up to four collections are fetched at a time, see
+    /// [`warm_with_concurrency`](Self::warm_with_concurrency). Every fetch
+    /// shares the client's connections, retry policy, and cache of file
+    /// versions, so later syncs of the returned collections only download
+    /// files that changed. The first error, like running out of rate limit,
+    /// stops any fetches that haven't started yet. Collections are returned
+    /// in the order of `names`, and those that don't exist are created like
+    /// [`collection`](Self::collection) does.
+    pub async fn warm<T: Serialize + DeserializeOwned>(
+        &self,
+        names: &[&str],
+    ) -> Result<Vec<Collection<T>>, ClientError> {
+        self.warm_with_concurrency(names, WARM_CONCURRENCY).await
+    }
+
+    /// like [`warm`](Self::warm), fetching up to `concurrency` collections at a time
+    pub async fn warm_with_concurrency<T: Serialize + DeserializeOwned>(
+        &self,
+        names: &[&str],
+        concurrency: usize,
+    ) -> Result<Vec<Collection<T>>, ClientError> {
+        stream::iter(names)
+            .map(|name| self.collection::<T>(name))
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// The paths of the files in [`CollectionFiles`], in order.
+    fn collection_file_paths(&self, name: &str, format: &Arc<dyn Format>) -> Vec<String> {
+        vec![
+            self.collection_path(name, format),
+            index::index_path(self, name),
+            #[cfg(feature = "schema")]
+            crate::schema::schema_path(self, name),
+        ]
+    }
+
+    /// Fetch several files, returning [`None`] for those that don't exist.
+    pub(crate) async fn get_files(
+        &self,
+        paths: &[String],
+    ) -> Result<Vec<Option<RemoteFile>>, ClientError> {
+        self.inner
+            .provider
+            .get_files(paths, self.inner.branch.as_deref())
+            .await
+    }
+}
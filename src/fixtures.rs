@@ -0,0 +1,163 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    meta::{self, CollectionMeta},
+    Client, ClientError, FileChange, Format, Json, Operation,
+};
+
+/// Documents to seed collections with, see [`Client::apply_fixtures`].
+///
+/// Fixtures are read from files holding a list of documents, named after
+/// the collection they fill: `users.json`, or `users.yaml` with the `yaml`
+/// feature. They can come from a directory, or be embedded in the binary
+/// with `include_str!` so tests don't depend on the working directory.
+#[derive(Clone, Debug, Default)]
+pub struct Fixtures {
+    collections: BTreeMap<String, Vec<Value>>,
+}
+
+impl Fixtures {
+    /// no fixtures, add them with [`with_file`](Self::with_file) or [`with_documents`](Self::with_documents)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read every fixture file in `dir`, skipping files in other formats.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_dir(dir: impl AsRef<std::path::Path>) -> Result<Self, ClientError> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => return Err(ClientError::Io(err)),
+        };
+
+        let mut fixtures = Self::new();
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(err) => return Err(ClientError::Io(err)),
+            };
+
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !path.is_file() || fixture_format(&file_name).is_none() {
+                continue;
+            }
+
+            let content = match std::fs::read(&path) {
+                Ok(content) => content,
+                Err(err) => return Err(ClientError::Io(err)),
+            };
+            fixtures = fixtures.with_file(&file_name, content)?;
+        }
+
+        Ok(fixtures)
+    }
+
+    /// add the fixture file called `file_name`, like `("users.json", include_str!("fixtures/users.json"))`
+    pub fn with_file(
+        mut self,
+        file_name: &str,
+        content: impl AsRef<[u8]>,
+    ) -> Result<Self, ClientError> {
+        let (name, format) = match fixture_format(file_name) {
+            Some(fixture) => fixture,
+            None => return Err(ClientError::InvalidKey(file_name.to_string())),
+        };
+
+        let documents = match format.deserialize(content.as_ref())? {
+            Value::Array(documents) => documents,
+            _ => {
+                return Err(ClientError::Validation(format!(
+                    "fixture '{file_name}' isn't a list of documents"
+                )))
+            }
+        };
+
+        self.collections.insert(name.to_string(), documents);
+        Ok(self)
+    }
+
+    /// add `documents` as the fixture for the collection called `name`
+    pub fn with_documents<T: Serialize>(
+        mut self,
+        name: impl Into<String>,
+        documents: &[T],
+    ) -> Result<Self, ClientError> {
+        let documents = match serde_json::to_value(documents) {
+            Ok(Value::Array(documents)) => documents,
+            Ok(_) => Vec::new(),
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+
+        self.collections.insert(name.into(), documents);
+        Ok(self)
+    }
+
+    /// the names of the collections the fixtures fill
+    pub fn collections(&self) -> impl Iterator<Item = &str> {
+        self.collections.keys().map(String::as_str)
+    }
+}
+
+impl Client {
+    /// Create or overwrite every collection in `fixtures` in a single commit, returning the commit's sha.
+    ///
+    /// Collections are written as JSON. An existing collection keeps its
+    /// schema version and indexes, so migrations aren't run again, but its
+    /// documents are replaced by the fixture's.
+    pub async fn apply_fixtures(&self, fixtures: &Fixtures) -> Result<String, ClientError> {
+        let format: Arc<dyn Format> = Arc::new(Json);
+
+        let paths: Vec<String> = fixtures
+            .collections()
+            .map(|name| self.collection_path(name, &format))
+            .collect();
+        let existing = self.get_files(&paths).await?;
+
+        let mut changes = Vec::with_capacity(paths.len());
+        for ((path, file), documents) in paths
+            .into_iter()
+            .zip(existing)
+            .zip(fixtures.collections.values())
+        {
+            let mut meta = CollectionMeta::new(format.as_ref(), 0);
+            if let Some(file) = &file {
+                if let Ok((Some(existing), _)) =
+                    meta::decode_collection::<Value>(format.as_ref(), &file.content)
+                {
+                    meta.version = existing.version;
+                    meta.indexes = existing.indexes;
+                }
+            }
+
+            changes.push(FileChange {
+                path,
+                content: Some(meta::encode_collection(format.as_ref(), &meta, documents)?),
+                expected_sha: file.map(|file| file.sha),
+            });
+        }
+
+        let message = self.commit_message(&Operation::ApplyFixtures, None);
+        self.inner
+            .provider
+            .commit_files(&changes, &self.commit_options(&message))
+            .await
+    }
+}
+
+/// The collection a fixture file fills and the format it's in, if it's a fixture file.
+fn fixture_format(file_name: &str) -> Option<(&str, Box<dyn Format>)> {
+    let (name, extension) = file_name.rsplit_once('.')?;
+    if name.is_empty() {
+        return None;
+    }
+
+    match extension {
+        "json" => Some((name, Box::new(Json))),
+        #[cfg(feature = "yaml")]
+        "yaml" | "yml" => Some((name, Box::new(crate::Yaml))),
+        _ => None,
+    }
+}
@@ -0,0 +1,364 @@
+use std::fmt::Debug;
+
+use serde_json::Value;
+
+use crate::ClientError;
+
+/// A serialization format used to store a collection in the repository.
+///
+/// Formats convert between the raw file content and a [`serde_json::Value`],
+/// which the collection then maps to and from your document type.
+pub trait Format: Debug + Send + Sync {
+    /// The file extension used for collections in this format, without the dot.
+    fn extension(&self) -> &str;
+
+    /// Serialize a value into the bytes that get written to the repository.
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError>;
+
+    /// Deserialize the bytes read from the repository.
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError>;
+
+    /// The bytes read from the repository if they're plain JSON, so documents
+    /// can be parsed from them directly instead of through a [`Value`].
+    fn as_json<'a>(&self, bytes: &'a [u8]) -> Option<&'a [u8]> {
+        let _ = bytes;
+        None
+    }
+}
+
+/// The default JSON format (`.json`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Format for Json {
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        match serde_json::to_vec(value) {
+            Ok(bytes) => Ok(bytes),
+            Err(err) => Err(ClientError::Json(err)),
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        match serde_json::from_slice(bytes) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(ClientError::Json(err)),
+        }
+    }
+
+    fn as_json<'a>(&self, bytes: &'a [u8]) -> Option<&'a [u8]> {
+        Some(bytes)
+    }
+}
+
+/// YAML (`.yaml`), enabled by the `yaml` feature.
+#[cfg(feature = "yaml")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Yaml;
+
+#[cfg(feature = "yaml")]
+impl Format for Yaml {
+    fn extension(&self) -> &str {
+        "yaml"
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        match serde_yaml::to_string(value) {
+            Ok(string) => Ok(string.into_bytes()),
+            Err(err) => Err(ClientError::Format(Box::new(err))),
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        match serde_yaml::from_slice(bytes) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(ClientError::Format(Box::new(err))),
+        }
+    }
+}
+
+/// TOML (`.toml`), enabled by the `toml` feature.
+///
+/// TOML documents must be a table at the root, so arrays are stored
+/// under a top level `documents` key.
+#[cfg(feature = "toml")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Toml;
+
+#[cfg(feature = "toml")]
+impl Format for Toml {
+    fn extension(&self) -> &str {
+        "toml"
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        let result = match value {
+            Value::Object(_) => toml::to_string(value),
+            _ => toml::to_string(&serde_json::json!({ "documents": value })),
+        };
+
+        match result {
+            Ok(string) => Ok(string.into_bytes()),
+            Err(err) => Err(ClientError::Format(Box::new(err))),
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        let string = match std::str::from_utf8(bytes) {
+            Ok(string) => string,
+            Err(_) => return Err(ClientError::NotUtf8),
+        };
+
+        let value: Value = match toml::from_str(string) {
+            Ok(value) => value,
+            Err(err) => return Err(ClientError::Format(Box::new(err))),
+        };
+
+        // unwrap arrays that were wrapped in `serialize`
+        match value {
+            Value::Object(mut table) if table.len() == 1 && table.contains_key("documents") => {
+                Ok(table.remove("documents").unwrap_or_default())
+            }
+            value => Ok(value),
+        }
+    }
+}
+
+/// MessagePack (`.msgpack`), enabled by the `msgpack` feature.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Format for MessagePack {
+    fn extension(&self) -> &str {
+        "msgpack"
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        match rmp_serde::to_vec_named(value) {
+            Ok(bytes) => Ok(bytes),
+            Err(err) => Err(ClientError::Format(Box::new(err))),
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        match rmp_serde::from_slice(bytes) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(ClientError::Format(Box::new(err))),
+        }
+    }
+}
+
+/// CBOR (`.cbor`), enabled by the `cbor` feature.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Format for Cbor {
+    fn extension(&self) -> &str {
+        "cbor"
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        let mut bytes = Vec::new();
+
+        match ciborium::into_writer(value, &mut bytes) {
+            Ok(()) => Ok(bytes),
+            Err(err) => Err(ClientError::Format(Box::new(err))),
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        match ciborium::from_reader(bytes) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(ClientError::Format(Box::new(err))),
+        }
+    }
+}
+
+/// Encrypts another format with AES-256-GCM, enabled by the `encryption` feature.
+///
+/// Content is encrypted before it's written and decrypted after it's read,
+/// so the repository (and the github UI) only ever sees ciphertext. Each
+/// write uses a fresh random nonce, stored in front of the ciphertext. The
+/// extension is the inner format's with `.enc` appended, like `.json.enc`.
+///
+/// Losing the key means losing the data, there's no way to recover it.
+#[cfg(feature = "encryption")]
+#[derive(Clone)]
+pub struct Encrypted<F> {
+    inner: F,
+    cipher: aes_gcm::Aes256Gcm,
+    extension: String,
+}
+
+#[cfg(feature = "encryption")]
+impl<F: Format> Encrypted<F> {
+    /// encrypt `inner` with a 256 bit key
+    pub fn new(inner: F, key: [u8; 32]) -> Self {
+        use aes_gcm::KeyInit;
+
+        let extension = format!("{}.enc", inner.extension());
+
+        Self {
+            inner,
+            cipher: aes_gcm::Aes256Gcm::new(&key.into()),
+            extension,
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl<F: Debug> Debug for Encrypted<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never print the key
+        f.debug_struct("Encrypted")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The length of the nonce stored in front of encrypted content.
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 12;
+
+#[cfg(feature = "encryption")]
+impl<F: Format> Format for Encrypted<F> {
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        use aes_gcm::aead::{Aead, AeadCore, OsRng};
+
+        let plaintext = self.inner.serialize(value)?;
+        let nonce = aes_gcm::Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let ciphertext = match self.cipher.encrypt(&nonce, plaintext.as_slice()) {
+            Ok(ciphertext) => ciphertext,
+            Err(_) => return Err(ClientError::Encryption),
+        };
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+
+        Ok(bytes)
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        use aes_gcm::aead::Aead;
+
+        if bytes.len() < NONCE_LEN {
+            return Err(ClientError::Encryption);
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+
+        match self.cipher.decrypt(nonce.into(), ciphertext) {
+            Ok(plaintext) => self.inner.deserialize(&plaintext),
+            Err(_) => Err(ClientError::Encryption),
+        }
+    }
+}
+
+/// Compresses another format with gzip, enabled by the `gzip` feature.
+///
+/// The extension is the inner format's with `.gz` appended, like `.json.gz`.
+#[cfg(feature = "gzip")]
+#[derive(Clone, Debug)]
+pub struct Gzip<F> {
+    inner: F,
+    extension: String,
+}
+
+#[cfg(feature = "gzip")]
+impl<F: Format> Gzip<F> {
+    /// compress `inner` with gzip
+    pub fn new(inner: F) -> Self {
+        let extension = format!("{}.gz", inner.extension());
+
+        Self { inner, extension }
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<F: Format> Format for Gzip<F> {
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        use std::io::Write;
+
+        let bytes = self.inner.serialize(value)?;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        if let Err(err) = encoder.write_all(&bytes) {
+            return Err(ClientError::Io(err));
+        }
+
+        match encoder.finish() {
+            Ok(compressed) => Ok(compressed),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        use std::io::Read;
+
+        let mut decompressed = Vec::new();
+        if let Err(err) = flate2::read::GzDecoder::new(bytes).read_to_end(&mut decompressed) {
+            return Err(ClientError::Io(err));
+        }
+
+        self.inner.deserialize(&decompressed)
+    }
+}
+
+/// Compresses another format with zstd, enabled by the `zstd` feature.
+///
+/// Compresses better and faster than [`Gzip`], but fewer tools can open it.
+/// The extension is the inner format's with `.zst` appended, like `.json.zst`.
+#[cfg(feature = "zstd")]
+#[derive(Clone, Debug)]
+pub struct Zstd<F> {
+    inner: F,
+    extension: String,
+}
+
+#[cfg(feature = "zstd")]
+impl<F: Format> Zstd<F> {
+    /// compress `inner` with zstd
+    pub fn new(inner: F) -> Self {
+        let extension = format!("{}.zst", inner.extension());
+
+        Self { inner, extension }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<F: Format> Format for Zstd<F> {
+    fn extension(&self) -> &str {
+        &self.extension
+    }
+
+    fn serialize(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        let bytes = self.inner.serialize(value)?;
+
+        match zstd::encode_all(bytes.as_slice(), 0) {
+            Ok(compressed) => Ok(compressed),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Value, ClientError> {
+        match zstd::decode_all(bytes) {
+            Ok(decompressed) => self.inner.deserialize(&decompressed),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+//! Checking a collection's files for problems and repairing them.
+//!
+//! Collections are plain files that anyone with access to the repository
+//! can edit, so they can end up in states the library never writes: a
+//! document with the wrong shape, two documents with the same key, or an
+//! index file that no longer matches. [`Collection::fsck`] finds these and
+//! [`Collection::repair`] fixes what it can in a single commit.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+#[cfg(feature = "schema")]
+use crate::schema::SchemaViolation;
+use crate::{
+    expiry::expiry_key,
+    index,
+    meta::{self, CollectionMeta},
+    provider::blob_sha,
+    ClientError, Collection, FileChange, Operation, RemoteFile, MAX_CONFLICT_RETRIES,
+};
+
+/// A problem found by [`Collection::fsck`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// the collection file can't be decoded at all, so nothing else was checked
+    Unreadable(String),
+    /// the document at `position` doesn't deserialize into the collection's type
+    InvalidDocument { position: usize, message: String },
+    /// documents at `positions` share the same [key](Collection::with_key)
+    DuplicateKey { key: Value, positions: Vec<usize> },
+    /// a document doesn't conform to the collection's schema
+    #[cfg(feature = "schema")]
+    SchemaViolation(SchemaViolation),
+    /// the index file was built from a different version of the collection
+    StaleIndex,
+    /// an index lists a document under a value it doesn't have
+    DanglingIndexEntry {
+        field: String,
+        value: String,
+        /// the document's key, or its position in collections without one
+        document: String,
+    },
+    /// the collection is indexed on a field that's missing from the index file
+    MissingIndex(String),
+}
+
+/// What [`Collection::repair`] fixes.
+#[derive(Clone, Debug)]
+pub struct RepairOptions {
+    /// remove documents that don't deserialize, true by default
+    pub remove_invalid: bool,
+    /// keep only the first of the documents that share a key, true by default
+    pub remove_duplicates: bool,
+    /// remove documents that don't conform to the collection's schema, false by default
+    #[cfg(feature = "schema")]
+    pub remove_schema_violations: bool,
+    /// rebuild the index file if it doesn't match the collection, true by default
+    pub rebuild_indexes: bool,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            remove_invalid: true,
+            remove_duplicates: true,
+            #[cfg(feature = "schema")]
+            remove_schema_violations: false,
+            rebuild_indexes: true,
+        }
+    }
+}
+
+/// What a [`Collection::repair`] changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RepairReport {
+    pub fixed: Vec<Problem>,
+    /// problems the options didn't allow fixing, or that can't be fixed
+    pub remaining: Vec<Problem>,
+    /// the sha of the commit with the fixes, [`None`] if nothing was written
+    pub commit: Option<String>,
+}
+
+/// A collection file decoded as far as it could be.
+struct Inspection {
+    meta: Option<CollectionMeta>,
+    documents: Vec<Value>,
+    /// the key of each document in a keyed collection, [`None`] for those that didn't decode
+    document_keys: Option<Vec<Option<String>>>,
+    problems: Vec<Problem>,
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// check the stored collection for problems, without changing anything
+    ///
+    /// this reads the files directly, so it works on collections that are
+    /// too broken for [`data`](Self::data) to read.
+    pub async fn fsck(&self) -> Result<Vec<Problem>, ClientError> {
+        let file = self.collection_file().await?;
+
+        Ok(self.inspect(&file).await?.problems)
+    }
+
+    /// fix the problems [`fsck`](Self::fsck) finds that `options` allow, in a single commit
+    ///
+    /// documents are removed rather than changed, to fix them instead use
+    /// [`repair_with`](Self::repair_with).
+    pub async fn repair(&mut self, options: RepairOptions) -> Result<RepairReport, ClientError> {
+        let mut retries = 0;
+
+        loop {
+            let file = self.collection_file().await?;
+            let inspection = self.inspect(&file).await?;
+
+            let mut report = RepairReport::default();
+            let mut remove = BTreeSet::new();
+            let mut rebuild_indexes = false;
+
+            for problem in inspection.problems {
+                match &problem {
+                    Problem::InvalidDocument { position, .. } if options.remove_invalid => {
+                        remove.insert(*position);
+                    }
+                    Problem::DuplicateKey { positions, .. } if options.remove_duplicates => {
+                        remove.extend(positions.iter().skip(1));
+                    }
+                    #[cfg(feature = "schema")]
+                    Problem::SchemaViolation(violation) if options.remove_schema_violations => {
+                        remove.insert(violation.document);
+                    }
+                    Problem::StaleIndex
+                    | Problem::DanglingIndexEntry { .. }
+                    | Problem::MissingIndex(_)
+                        if options.rebuild_indexes =>
+                    {
+                        rebuild_indexes = true;
+                    }
+                    _ => {
+                        report.remaining.push(problem);
+                        continue;
+                    }
+                }
+
+                report.fixed.push(problem);
+            }
+
+            if remove.is_empty() && !rebuild_indexes {
+                return Ok(report);
+            }
+
+            // documents that are left invalid are written back as they were
+            let documents: Vec<Value> = inspection
+                .documents
+                .into_iter()
+                .enumerate()
+                .filter(|(position, _)| !remove.contains(position))
+                .map(|(_, document)| document)
+                .collect();
+            let document_keys: Option<Vec<Option<String>>> = inspection.document_keys.map(|keys| {
+                keys.into_iter()
+                    .enumerate()
+                    .filter(|(position, _)| !remove.contains(position))
+                    .map(|(_, key)| key)
+                    .collect()
+            });
+
+            let mut changes = Vec::new();
+            let sha = match remove.is_empty() {
+                true => file.sha.clone(),
+                false => {
+                    let meta = inspection
+                        .meta
+                        .unwrap_or_else(|| CollectionMeta::legacy(self.format.as_ref()));
+                    let content = meta::encode_collection(self.format.as_ref(), &meta, &documents)?;
+                    // the version token github and gitea will give the new file
+                    let sha = blob_sha(&content);
+
+                    changes.push(FileChange {
+                        path: self.path.clone(),
+                        content: Some(content),
+                        expected_sha: Some(file.sha.clone()),
+                    });
+
+                    sha
+                }
+            };
+
+            // positions change when documents are removed, so the indexes always need rebuilding then
+            if !self.indexes.is_empty() || self.search.is_some() {
+                let path = index::index_path(&self.client, &self.name);
+                let expected_sha = self.client.get_file(&path).await?.map(|file| file.sha);
+
+                changes.push(FileChange {
+                    path,
+                    content: Some(index::encode_index_file(
+                        &index::with_ids(&documents, document_keys.as_deref()),
+                        &self.indexes,
+                        self.search.as_deref(),
+                        &sha,
+                    )?),
+                    expected_sha,
+                });
+            }
+
+            if changes.is_empty() {
+                return Ok(report);
+            }
+
+            let message = self.message(&Operation::Repair);
+            let result = self
+                .client
+                .inner
+                .provider
+                .commit_files(&changes, &self.client.commit_options(&message))
+                .await;
+
+            match result {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(commit) => {
+                    report.commit = Some(commit);
+
+                    // documents left invalid keep the collection unreadable
+                    match self.update().await {
+                        Ok(()) => return Ok(report),
+                        Err(err) => match err.without_context() {
+                            ClientError::Json(_) => return Ok(report),
+                            _ => return Err(err),
+                        },
+                    }
+                }
+            }
+        }
+    }
+
+    async fn collection_file(&self) -> Result<RemoteFile, ClientError> {
+        match self.client.get_file(&self.path).await? {
+            Some(file) => Ok(file),
+            None => Err(ClientError::NoContent),
+        }
+    }
+
+    /// Decode a collection file and check it for problems.
+    async fn inspect(&self, file: &RemoteFile) -> Result<Inspection, ClientError> {
+        let (meta, decoded) =
+            match meta::decode_collection_lenient::<T>(self.format.as_ref(), &file.content) {
+                Ok(decoded) => decoded,
+                Err(err) => {
+                    return Ok(Inspection {
+                        meta: None,
+                        documents: Vec::new(),
+                        document_keys: None,
+                        problems: vec![Problem::Unreadable(err.to_string())],
+                    })
+                }
+            };
+
+        let mut problems = Vec::new();
+        let mut documents = Vec::with_capacity(decoded.len());
+        let mut document_keys = self.key.as_ref().map(|_| Vec::with_capacity(decoded.len()));
+        let mut keys: BTreeMap<String, (Value, Vec<usize>)> = BTreeMap::new();
+
+        for (position, document) in decoded.into_iter().enumerate() {
+            let document = match document {
+                Ok(document) => document,
+                Err((err, value)) => {
+                    problems.push(Problem::InvalidDocument {
+                        position,
+                        message: err.to_string(),
+                    });
+                    documents.push(value);
+                    if let Some(document_keys) = &mut document_keys {
+                        document_keys.push(None);
+                    }
+                    continue;
+                }
+            };
+
+            if let (Some(key), Some(document_keys)) = (&self.key, &mut document_keys) {
+                let key = key(&document)?;
+                document_keys.push(Some(expiry_key(&key)));
+                keys.entry(key.to_string())
+                    .or_insert_with(|| (key, Vec::new()))
+                    .1
+                    .push(position);
+            }
+
+            match serde_json::to_value(&document) {
+                Ok(value) => documents.push(value),
+                Err(err) => return Err(ClientError::Json(err)),
+            }
+        }
+
+        for (key, positions) in keys.into_values() {
+            if positions.len() > 1 {
+                problems.push(Problem::DuplicateKey { key, positions });
+            }
+        }
+
+        #[cfg(feature = "schema")]
+        if let Some(schema) = &self.schema {
+            for violation in schema.violations(&documents)? {
+                problems.push(Problem::SchemaViolation(violation));
+            }
+        }
+
+        problems.extend(
+            index::check_indexes(
+                &self.client,
+                &self.name,
+                &self.indexes,
+                &file.sha,
+                &index::with_ids(&documents, document_keys.as_deref()),
+            )
+            .await?,
+        );
+
+        Ok(Inspection {
+            meta,
+            documents,
+            document_keys,
+            problems,
+        })
+    }
+}
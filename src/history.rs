@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    decode_documents, expiry, merge, meta::decode_collection, ClientError, Collection, CommitInfo,
+    Operation,
+};
+
+/// A commit that changed a collection.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub sha: String,
+    pub message: String,
+    pub author: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// the most recent commits that changed this collection, newest first
+    pub async fn history(&self, limit: usize) -> Result<Vec<HistoryEntry>, ClientError> {
+        self.client
+            .inner
+            .provider
+            .list_commits(&self.path, limit, None, self.client.inner.branch.as_deref())
+            .await
+    }
+
+    /// the documents in this collection as of a specific commit
+    pub async fn at_commit(&self, sha: impl AsRef<str>) -> Result<Vec<T>, ClientError> {
+        match self.client.get_file_at(&self.path, sha.as_ref()).await? {
+            Some(file) => decode_documents(self.format.as_ref(), &file.content),
+            None => Err(ClientError::NotFound),
+        }
+    }
+
+    /// restore the collection to how it was at a specific commit
+    ///
+    /// the old documents are written back as a new commit, so nothing in the
+    /// history is lost and the rollback can itself be rolled back. they're
+    /// written like any other change, so validators, hooks, and limits apply
+    pub async fn rollback_to(
+        &mut self,
+        commit_sha: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError> {
+        let commit_sha = commit_sha.as_ref();
+
+        let file = match self.client.get_file_at(&self.path, commit_sha).await? {
+            Some(file) => file,
+            None => return Err(ClientError::NotFound),
+        };
+
+        let (meta, mut inner) = decode_collection(self.format.as_ref(), &file.content)?;
+
+        self.update().await?;
+
+        // the documents come back with the deadlines they had, but the
+        // collection keeps its current version, indexes, and attachments
+        let expires = meta.map(|meta| meta.expires).unwrap_or_default();
+        expiry::retain_unexpired(&mut inner, self.key.as_ref(), &expires)?;
+
+        let operation = Operation::Rollback {
+            commit: commit_sha.to_string(),
+        };
+        let message = self.message(&operation);
+
+        let current = std::mem::replace(&mut self.inner, inner);
+        let current_expires = std::mem::replace(&mut self.meta.expires, expires);
+        let commit = match self.write(&operation, &message).await {
+            Ok(commit) => commit,
+            Err(err) => {
+                self.inner = current;
+                self.meta.expires = current_expires;
+                return Err(err);
+            }
+        };
+
+        if let Some(key) = &self.key {
+            self.base = merge::keyed(&self.inner, key)?;
+        }
+
+        Ok(commit)
+    }
+
+    /// the documents in this collection as they were at a point in time
+    pub async fn at_time(&self, time: DateTime<Utc>) -> Result<Vec<T>, ClientError> {
+        match self
+            .client
+            .inner
+            .provider
+            .list_commits(
+                &self.path,
+                1,
+                Some(time),
+                self.client.inner.branch.as_deref(),
+            )
+            .await?
+            .first()
+        {
+            Some(entry) => self.at_commit(&entry.sha).await,
+            None => Err(ClientError::NotFound),
+        }
+    }
+}
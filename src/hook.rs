@@ -0,0 +1,149 @@
+use std::{fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{ClientError, Collection, CommitInfo, Operation};
+
+/// Callbacks run around a [`Collection`]'s reads and writes, see [`Collection::with_hook`].
+///
+/// Hooks can keep an audit trail, invalidate caches, send notifications, or
+/// reject writes that break an application's rules, without wrapping every
+/// call site. Documents are passed as JSON, so a single hook can be shared
+/// by collections of different types. Hooks run in the order they were added.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Hook: Debug + Send + Sync {
+    /// called before every attempt to write the collection, returning an error cancels the write
+    ///
+    /// a write that conflicted is attempted again with the merged documents,
+    /// so this can be called more than once for a single operation.
+    async fn on_before_write(&self, event: &WriteEvent<'_>) -> Result<(), ClientError> {
+        let _ = event;
+        Ok(())
+    }
+
+    /// called once a write has been committed
+    async fn on_after_write(&self, event: &WriteEvent<'_>, commit: &CommitInfo) {
+        let _ = (event, commit);
+    }
+
+    /// called whenever the collection is synced from the repository, which every read and write starts with
+    async fn on_read(&self, event: &ReadEvent<'_>) {
+        let _ = event;
+    }
+}
+
+/// A write a [`Hook`] is told about.
+#[derive(Clone, Debug)]
+pub struct WriteEvent<'a> {
+    pub collection: &'a str,
+    pub operation: &'a Operation,
+    pub message: &'a str,
+    /// every document the collection holds after the write
+    pub documents: &'a [Value],
+}
+
+/// A sync a [`Hook`] is told about.
+#[derive(Clone, Debug)]
+pub struct ReadEvent<'a> {
+    pub collection: &'a str,
+    /// every document the collection holds
+    pub documents: &'a [Value],
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// run `hook` around every read and write of this collection, see [`Hook`]
+    pub fn with_hook(mut self, hook: impl Hook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Run every hook's [`on_before_write`](Hook::on_before_write), stopping at the first to reject the write.
+    pub(crate) async fn before_write_hooks(
+        &self,
+        operation: &Operation,
+        message: &str,
+    ) -> Result<(), ClientError> {
+        if self.hooks.is_empty() {
+            return Ok(());
+        }
+
+        let documents = self.document_values()?;
+        let event = self.write_event(operation, message, &documents);
+
+        for hook in &self.hooks {
+            hook.on_before_write(&event).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Run every hook's [`on_after_write`](Hook::on_after_write).
+    pub(crate) async fn after_write_hooks(
+        &self,
+        operation: &Operation,
+        message: &str,
+        commit: &CommitInfo,
+    ) -> Result<(), ClientError> {
+        if self.hooks.is_empty() {
+            return Ok(());
+        }
+
+        let documents = self.document_values()?;
+        let event = self.write_event(operation, message, &documents);
+
+        for hook in &self.hooks {
+            hook.on_after_write(&event, commit).await;
+        }
+
+        Ok(())
+    }
+
+    /// Run every hook's [`on_read`](Hook::on_read).
+    pub(crate) async fn read_hooks(&self) -> Result<(), ClientError> {
+        if self.hooks.is_empty() {
+            return Ok(());
+        }
+
+        let documents = self.document_values()?;
+        let event = ReadEvent {
+            collection: &self.name,
+            documents: &documents,
+        };
+
+        for hook in &self.hooks {
+            hook.on_read(&event).await;
+        }
+
+        Ok(())
+    }
+
+    fn write_event<'a>(
+        &'a self,
+        operation: &'a Operation,
+        message: &'a str,
+        documents: &'a [Value],
+    ) -> WriteEvent<'a> {
+        WriteEvent {
+            collection: &self.name,
+            operation,
+            message,
+            documents,
+        }
+    }
+
+    fn document_values(&self) -> Result<Vec<Value>, ClientError> {
+        let mut documents = Vec::with_capacity(self.inner.len());
+
+        for document in &self.inner {
+            match serde_json::to_value(document) {
+                Ok(document) => documents.push(document),
+                Err(err) => return Err(ClientError::Json(err)),
+            }
+        }
+
+        Ok(documents)
+    }
+}
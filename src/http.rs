@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures::{stream, Stream, TryStreamExt};
+use futures_timer::Delay;
+use reqwest::{
+    header::{ETAG, IF_NONE_MATCH, LINK, USER_AGENT},
+    Method, Response, StatusCode,
+};
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    trace, ApiVersion, ClientError, CredentialProvider, Metrics, Middleware, RequestMetrics,
+    RetryPolicy,
+};
+
+/// The authenticated HTTP transport shared by the built-in forge providers.
+#[derive(Clone, Debug)]
+pub(crate) struct Http {
+    pub(crate) client: reqwest::Client,
+    pub(crate) user_agent: String,
+    /// the `Accept` and `X-GitHub-Api-Version` headers to send
+    pub(crate) api_version: ApiVersion,
+    pub(crate) credentials: Arc<dyn CredentialProvider>,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) middleware: Vec<Arc<dyn Middleware>>,
+    pub(crate) metrics: Option<Arc<dyn Metrics>>,
+    /// the last `ETag` of each polled url and the version of the file it returned, see [`poll_json`](Self::poll_json)
+    pub(crate) etags: Arc<Mutex<HashMap<Url, (String, String)>>>,
+}
+
+impl Http {
+    /// Send an authenticated request, retrying transient failures according to the [`RetryPolicy`].
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&Value>,
+    ) -> Result<Response, ClientError> {
+        self.send_conditional(method, url, body, None).await
+    }
+
+    /// Like [`send`](Self::send), but with an `If-None-Match` header when `etag` is set.
+    async fn send_conditional(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&Value>,
+        etag: Option<&str>,
+    ) -> Result<Response, ClientError> {
+        let request = self.send_with_retries(method.clone(), url.clone(), body, etag);
+
+        trace::request(&method, &url, request).await
+    }
+
+    async fn send_with_retries(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&Value>,
+        etag: Option<&str>,
+    ) -> Result<Response, ClientError> {
+        let mut attempt = 1;
+
+        loop {
+            // fetch the token every attempt in case it was rotated
+            let token = self.credentials.token().await?;
+
+            let mut request = self
+                .api_version
+                .apply(self.client.request(method.clone(), url.clone()))
+                .header(USER_AGENT, &self.user_agent);
+            // anonymous clients don't send an Authorization header at all
+            if !token.is_empty() {
+                request = request.bearer_auth(token);
+            }
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+
+            let mut request = match request.build() {
+                Ok(request) => request,
+                Err(e) => return Err(ClientError::Http(e)),
+            };
+
+            for middleware in &self.middleware {
+                middleware.on_request(&mut request).await?;
+            }
+
+            if let Some(metrics) = &self.metrics {
+                metrics.request_started(&method, &url);
+            }
+            let bytes_sent = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map_or(0, |body| body.len() as u64);
+            // `Instant` isn't available in the browser
+            let started = Utc::now();
+
+            let result = self.client.execute(request).await;
+
+            if let Some(metrics) = &self.metrics {
+                let response = result.as_ref().ok();
+                metrics.request_finished(&RequestMetrics {
+                    method: method.clone(),
+                    url: url.clone(),
+                    status: response.map(Response::status),
+                    elapsed: (Utc::now() - started).to_std().unwrap_or_default(),
+                    bytes_sent,
+                    bytes_received: response.and_then(Response::content_length),
+                    attempt,
+                });
+            }
+
+            match &result {
+                Ok(response) => {
+                    trace::response(response, attempt - 1);
+                    for middleware in &self.middleware {
+                        middleware.on_response(response).await;
+                    }
+                }
+                Err(e) => trace::error(e, attempt - 1),
+            }
+
+            let mut delay = self.retry.delay(attempt);
+
+            match result {
+                Ok(response) => {
+                    if attempt >= self.retry.max_attempts
+                        || !self.retry.retries_status(&method, response.status())
+                    {
+                        return Ok(response);
+                    }
+
+                    // respect the server asking us to slow down
+                    if let Some(retry_after) = response
+                        .headers()
+                        .get("Retry-After")
+                        .and_then(|value| value.to_str().ok())
+                        .and_then(|value| value.parse().ok())
+                    {
+                        delay = delay.max(Duration::from_secs(retry_after));
+                    }
+
+                    log::warn!(
+                        "{method} {url} returned {}, retrying in {delay:?}",
+                        response.status()
+                    );
+                }
+                Err(e) => {
+                    if attempt >= self.retry.max_attempts || !self.retry.retries_error(&method, &e)
+                    {
+                        if e.is_timeout() {
+                            return Err(ClientError::Timeout);
+                        }
+                        return Err(ClientError::Http(e));
+                    }
+
+                    log::warn!("{method} {url} failed: {e}, retrying in {delay:?}");
+                }
+            }
+
+            if let Some(metrics) = &self.metrics {
+                metrics.request_retried(&method, &url, attempt, delay);
+            }
+
+            Delay::new(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Send a request and parse the response as JSON.
+    pub(crate) async fn request_json(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&Value>,
+    ) -> Result<Value, ClientError> {
+        let response = self.send(method, url, body).await?;
+
+        match check_status(response)?.json().await {
+            Ok(json) => Ok(json),
+            Err(e) => Err(ClientError::Http(e)),
+        }
+    }
+
+    /// Send a request and parse the response as JSON, returning [`None`] on a 404.
+    pub(crate) async fn request_json_optional(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<&Value>,
+    ) -> Result<Option<Value>, ClientError> {
+        let response = self.send(method, url, body).await?;
+
+        if response.status() == 404 {
+            return Ok(None);
+        }
+
+        match check_status(response)?.json().await {
+            Ok(json) => Ok(Some(json)),
+            Err(e) => Err(ClientError::Http(e)),
+        }
+    }
+
+    /// `GET` a file's JSON unless it's still at `version`, [`None`] if it is and `Some(None)` on a 404.
+    ///
+    /// The request is conditional on the `ETag` from the last poll of `url`,
+    /// a `304 Not Modified` doesn't count against github's rate limit.
+    /// Only the `ETag` and the version `version_of` finds in the response
+    /// are remembered, never the body.
+    pub(crate) async fn poll_json(
+        &self,
+        url: Url,
+        version: &str,
+        version_of: fn(&Value) -> Option<&str>,
+    ) -> Result<Option<Option<Value>>, ClientError> {
+        let etag = match self.etags().get(&url) {
+            Some((etag, known)) if known == version => Some(etag.clone()),
+            _ => None,
+        };
+
+        let response = self
+            .send_conditional(Method::GET, url.clone(), None, etag.as_deref())
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if response.status() == 404 {
+            self.etags().remove(&url);
+            return Ok(Some(None));
+        }
+
+        let response = check_status(response)?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+
+        let json: Value = match response.json().await {
+            Ok(json) => json,
+            Err(e) => return Err(ClientError::Http(e)),
+        };
+
+        let current = version_of(&json).map(str::to_string);
+        match (etag, &current) {
+            (Some(etag), Some(current)) => {
+                self.etags().insert(url, (etag, current.clone()));
+            }
+            _ => {
+                self.etags().remove(&url);
+            }
+        }
+
+        match current.as_deref() == Some(version) {
+            true => Ok(None),
+            false => Ok(Some(Some(json))),
+        }
+    }
+
+    /// Every item of a paginated JSON array, following the `Link` header to the next page.
+    ///
+    /// pages are fetched as the stream is polled, a 404 is an empty list.
+    pub(crate) fn paginate(&self, url: Url) -> impl Stream<Item = Result<Value, ClientError>> + '_ {
+        stream::try_unfold(Some(url), move |url| async move {
+            let url = match url {
+                Some(url) => url,
+                None => return Ok(None),
+            };
+
+            let response = self.send(Method::GET, url, None).await?;
+            if response.status() == 404 {
+                return Ok(None);
+            }
+
+            let response = check_status(response)?;
+            let next = next_page(&response);
+
+            let items = match response.json().await {
+                Ok(Value::Array(items)) => items,
+                Ok(_) => return Err(ClientError::NoContent),
+                Err(e) => return Err(ClientError::Http(e)),
+            };
+
+            Ok(Some((stream::iter(items.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+    }
+
+    fn etags(&self) -> MutexGuard<'_, HashMap<Url, (String, String)>> {
+        match self.etags.lock() {
+            Ok(etags) => etags,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+pub(crate) fn check_status(response: Response) -> Result<Response, ClientError> {
+    if response.status().is_success() {
+        Ok(response)
+    } else if response.status() == 409 {
+        Err(ClientError::Conflict)
+    } else {
+        Err(ClientError::Status(response.status()).in_request(response.url()))
+    }
+}
+
+/// Like [`check_status`], but a `status` response whose body mentions one of
+/// `conflicts` is a [`ClientError::Conflict`], for forges that don't use 409.
+pub(crate) async fn check_write_status(
+    response: Response,
+    status: StatusCode,
+    conflicts: &[&str],
+) -> Result<Response, ClientError> {
+    if response.status() != status {
+        return check_status(response);
+    }
+
+    let url = response.url().clone();
+    let body = response.text().await.unwrap_or_default();
+
+    if conflicts.iter().any(|conflict| body.contains(conflict)) {
+        Err(ClientError::Conflict)
+    } else {
+        Err(ClientError::Status(status).in_request(&url))
+    }
+}
+
+/// The `rel="next"` url in a response's `Link` header.
+fn next_page(response: &Response) -> Option<Url> {
+    let link = response.headers().get(LINK)?.to_str().ok()?;
+
+    // <https://...?page=2>; rel="next", <https://...?page=5>; rel="last"
+    link.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        if !params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+        {
+            return None;
+        }
+
+        Url::parse(url.trim().trim_start_matches('<').trim_end_matches('>')).ok()
+    })
+}
+
+/// Append path segments to `base`, percent-encoding each one.
+pub(crate) fn join_url<'a>(base: &Url, segments: impl IntoIterator<Item = &'a str>) -> Url {
+    let mut url = base.clone();
+
+    if let Ok(mut path) = url.path_segments_mut() {
+        path.pop_if_empty().extend(segments);
+    }
+
+    url
+}
@@ -0,0 +1,161 @@
+use std::{
+    fmt::{self, Debug},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Collection;
+
+type Generate = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// The start of snowflake time, 2020-01-01, in milliseconds since the unix epoch.
+const SNOWFLAKE_EPOCH: u64 = 1_577_836_800_000;
+
+/// The last snowflake made, as milliseconds since [`SNOWFLAKE_EPOCH`] and a sequence in the low 12 bits.
+static SNOWFLAKE: AtomicU64 = AtomicU64::new(0);
+
+/// The alphabet ULIDs are written in.
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// How a [`Collection`] makes new document ids, see [`Collection::new_id`].
+///
+/// Ids that sort by creation time keep new documents together, which keeps
+/// shards and history queries over recent documents small.
+#[derive(Default)]
+pub enum IdStrategy {
+    /// a random UUID, like `1b4e28ba-2fa1-4d3b-883f-0016d3cca427`, the default
+    #[default]
+    UuidV4,
+    /// a UUID starting with the time it was made, so ids sort by creation time to the millisecond
+    UuidV7,
+    /// a 26 character ULID, like `01ARZ3NDEKTSV4RRFFQ69G5FAV`, which sorts by creation time to the millisecond
+    Ulid,
+    /// a 64 bit twitter style snowflake written in decimal, which sorts by creation time when compared as a number
+    ///
+    /// only the low 10 bits of `node` are used, give each process writing
+    /// the collection a different one so their ids can't collide.
+    Snowflake { node: u16 },
+    /// ids from a callback, see [`custom`](Self::custom)
+    Custom(Generate),
+}
+
+impl IdStrategy {
+    /// make ids with a callback, like a counter or an id service
+    pub fn custom<F>(generate: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(generate))
+    }
+
+    /// make a new id
+    pub fn generate(&self) -> String {
+        match self {
+            Self::UuidV4 => uuid(fastrand::u128(..), 4),
+            Self::UuidV7 => uuid((now_millis() as u128) << 80 | fastrand::u128(..1 << 80), 7),
+            Self::Ulid => ulid((now_millis() as u128) << 80 | fastrand::u128(..1 << 80)),
+            Self::Snowflake { node } => snowflake(*node).to_string(),
+            Self::Custom(generate) => generate(),
+        }
+    }
+}
+
+impl Clone for IdStrategy {
+    fn clone(&self) -> Self {
+        match self {
+            Self::UuidV4 => Self::UuidV4,
+            Self::UuidV7 => Self::UuidV7,
+            Self::Ulid => Self::Ulid,
+            Self::Snowflake { node } => Self::Snowflake { node: *node },
+            Self::Custom(generate) => Self::Custom(generate.clone()),
+        }
+    }
+}
+
+impl Debug for IdStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UuidV4 => f.write_str("UuidV4"),
+            Self::UuidV7 => f.write_str("UuidV7"),
+            Self::Ulid => f.write_str("Ulid"),
+            Self::Snowflake { node } => f.debug_struct("Snowflake").field("node", node).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// make ids for this collection's documents with `strategy`
+    pub fn with_id_strategy(mut self, strategy: IdStrategy) -> Self {
+        self.ids = strategy;
+        self
+    }
+
+    /// the strategy [`new_id`](Self::new_id) makes ids with
+    pub fn id_strategy(&self) -> &IdStrategy {
+        &self.ids
+    }
+
+    /// make an id for a new document with the collection's [`IdStrategy`]
+    ///
+    /// ```ignore
+    /// let id = users.new_id();
+    /// users.insert(User { id, name }).await?;
+    /// ```
+    pub fn new_id(&self) -> String {
+        self.ids.generate()
+    }
+}
+
+fn now_millis() -> u64 {
+    Utc::now().timestamp_millis().max(0) as u64
+}
+
+/// Write 128 bits as a UUID of `version`, replacing the version and variant bits.
+fn uuid(bits: u128, version: u8) -> String {
+    let bits = bits & !(0xf << 76) | (version as u128) << 76;
+    let bits = bits & !(0b11 << 62) | 0b10 << 62;
+    let hex = format!("{bits:032x}");
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Write 128 bits in crockford base32, five bits a character from the most significant.
+fn ulid(bits: u128) -> String {
+    (0..26)
+        .rev()
+        .map(|position| CROCKFORD[(bits >> (position * 5)) as usize & 0x1f] as char)
+        .collect()
+}
+
+/// The next snowflake, later than every one made before it in this process.
+fn snowflake(node: u16) -> u64 {
+    let now = now_millis().saturating_sub(SNOWFLAKE_EPOCH) << 12;
+
+    // past 4096 ids in a millisecond the next one is borrowed, so ids never repeat
+    let mut last = SNOWFLAKE.load(Ordering::Relaxed);
+    let next = loop {
+        let next = now.max(last + 1);
+        match SNOWFLAKE.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => break next,
+            Err(current) => last = current,
+        }
+    };
+
+    let millis = next >> 12;
+    let sequence = next & 0xfff;
+
+    (millis & ((1 << 41) - 1)) << 22 | (node as u64 & 0x3ff) << 12 | sequence
+}
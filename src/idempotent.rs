@@ -0,0 +1,157 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    message::with_trailer, ClientError, Collection, CommitInfo, Operation, MAX_CONFLICT_RETRIES,
+};
+
+/// How many of a collection's most recent commits are searched for an operation id.
+const RECENT_COMMITS: usize = 20;
+
+/// The git trailer an operation id is recorded in at the end of the commit message.
+const TRAILER: &str = "Operation-Id";
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// apply `change` to the documents and write them, at most once for `operation_id`
+    ///
+    /// the id is recorded in the commit message, and before anything is
+    /// written the collection's recent commits are checked for it. so when a
+    /// write times out or fails with a server error, leaving it unclear
+    /// whether the commit landed, it's retried only if it didn't, and calling
+    /// this again later with the same id returns the earlier commit instead
+    /// of applying `change` twice. ids must be unique, a random UUID from
+    /// [`new_id`](Self::new_id) works well.
+    pub async fn write_idempotent<F>(
+        &mut self,
+        operation_id: impl AsRef<str>,
+        message: impl AsRef<str>,
+        change: F,
+    ) -> Result<CommitInfo, ClientError>
+    where
+        F: FnMut(&mut Vec<T>),
+    {
+        self.write_operation(
+            &Operation::Update,
+            operation_id.as_ref(),
+            message.as_ref(),
+            change,
+        )
+        .await
+    }
+
+    /// Apply `change` for `operation` at most once for `operation_id`.
+    async fn write_operation<F>(
+        &mut self,
+        operation: &Operation,
+        operation_id: &str,
+        message: &str,
+        mut change: F,
+    ) -> Result<CommitInfo, ClientError>
+    where
+        F: FnMut(&mut Vec<T>),
+    {
+        if operation_id.trim().is_empty() || operation_id.contains('\n') {
+            return Err(ClientError::InvalidKey(operation_id.to_string()));
+        }
+
+        let message = with_trailer(message, TRAILER, operation_id);
+        let mut retries = 0;
+
+        loop {
+            if let Some(commit) = self.find_operation(operation_id).await? {
+                return Ok(commit);
+            }
+
+            self.update().await?;
+
+            change(&mut self.inner);
+
+            match self.write(operation, &message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => retries += 1,
+                Err(err) if is_ambiguous(&err) && retries < MAX_CONFLICT_RETRIES => retries += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// The commit that already applied `operation_id`, syncing the collection if there is one.
+    async fn find_operation(
+        &mut self,
+        operation_id: &str,
+    ) -> Result<Option<CommitInfo>, ClientError> {
+        let trailer = format!("{TRAILER}: {operation_id}");
+
+        let entry = self
+            .history(RECENT_COMMITS)
+            .await?
+            .into_iter()
+            .find(|entry| entry.message.lines().any(|line| line.trim() == trailer));
+
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        self.update().await?;
+
+        Ok(Some(CommitInfo {
+            commit: Some(entry.sha),
+            timestamp: Some(entry.timestamp),
+            ..CommitInfo::new(self.sha.clone())
+        }))
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> Collection<T> {
+    /// push document to the database at most once for `operation_id`, see [`write_idempotent`](Self::write_idempotent)
+    pub async fn insert_idempotent(
+        &mut self,
+        data: T,
+        operation_id: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError> {
+        let message = self.message(&Operation::Insert);
+        self.write_operation(
+            &Operation::Insert,
+            operation_id.as_ref(),
+            &message,
+            |documents| documents.push(data.clone()),
+        )
+        .await
+    }
+
+    /// replace the document with the same key as `data`, or push it if there isn't one, at most once for `operation_id`
+    pub async fn upsert_idempotent<K, F>(
+        &mut self,
+        data: T,
+        key: F,
+        operation_id: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        let new_key = key(&data);
+
+        let message = self.message(&Operation::Upsert);
+        self.write_operation(
+            &Operation::Upsert,
+            operation_id.as_ref(),
+            &message,
+            |documents| match documents
+                .iter()
+                .position(|document| key(document) == new_key)
+            {
+                Some(position) => documents[position] = data.clone(),
+                None => documents.push(data.clone()),
+            },
+        )
+        .await
+    }
+}
+
+/// Whether a failed write may still have been committed.
+fn is_ambiguous(err: &ClientError) -> bool {
+    matches!(
+        err.without_context(),
+        ClientError::Timeout | ClientError::Http(_)
+    ) || err.status().is_some_and(|status| status.is_server_error())
+}
@@ -0,0 +1,378 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    expiry::expiry_key,
+    fsck::Problem,
+    merge::KeyFn,
+    search::{build_search_index, SearchIndex},
+    Client, ClientError, Collection, FileChange, Format, Json, Operation, RemoteFile,
+};
+
+/// Field value (as JSON) to the ids of the documents that have it.
+///
+/// A document's id is its [key](Collection::with_key), or its position in
+/// collections without one.
+type Index = BTreeMap<String, Vec<String>>;
+
+/// The contents of `<name>.index.json`.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct IndexFile {
+    /// the sha of the collection file the indexes were built from
+    pub(crate) sha: String,
+    indexes: BTreeMap<String, Index>,
+    /// see [`Collection::create_search_index`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) search: Option<SearchIndex>,
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// build and maintain an index on `field`
+    ///
+    /// nested fields are separated by dots, like `"address.city"`. the index
+    /// is stored in `<name>.index.json` and committed together with the
+    /// documents on every write. documents are indexed by their
+    /// [key](Self::with_key), or by position in collections without one.
+    pub async fn create_index(&mut self, field: impl AsRef<str>) -> Result<(), ClientError> {
+        self.update().await?;
+
+        let field = field.as_ref().to_string();
+        if !self.indexes.contains(&field) {
+            self.indexes.push(field);
+        }
+
+        self.write_indexes().await
+    }
+
+    /// stop maintaining the index on `field`
+    pub async fn drop_index(&mut self, field: impl AsRef<str>) -> Result<(), ClientError> {
+        self.indexes.retain(|f| f != field.as_ref());
+
+        self.remove_index().await
+    }
+
+    /// the fields this collection maintains indexes on
+    pub fn indexes(&self) -> &[String] {
+        &self.indexes
+    }
+
+    /// returns the documents whose `field` equals `value`, using its index
+    ///
+    /// only the index file is read, not the collection. it's only synced
+    /// if the index was built from a newer version of it than the one held.
+    pub async fn find_by_index(
+        &mut self,
+        field: impl AsRef<str>,
+        value: impl Serialize,
+    ) -> Result<Vec<&T>, ClientError> {
+        let field = field.as_ref();
+
+        let value = match serde_json::to_value(value) {
+            Ok(value) => value.to_string(),
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+
+        let mut index_file = match read_index_file(&self.client, &self.name).await? {
+            Some(index_file) if index_file.indexes.contains_key(field) => index_file,
+            _ => return Err(ClientError::MissingIndex(field.to_string())),
+        };
+
+        // the index is written with the documents, so it only differs if someone else wrote since we synced
+        if index_file.sha != self.sha {
+            self.update().await?;
+        }
+
+        let mut index = match index_file.sha == self.sha {
+            true => index_file.indexes.remove(field).unwrap_or_default(),
+            // the collection was changed without its index, like through github's web interface
+            false => {
+                let documents = identified(&self.inner, self.key.as_ref())?;
+                build_indexes(&documents, &[field.to_string()])
+                    .remove(field)
+                    .unwrap_or_default()
+            }
+        };
+
+        let ids = index.remove(&value).unwrap_or_default();
+
+        self.documents_by_id(&ids)
+    }
+
+    /// The documents with the ids in an index.
+    fn documents_by_id(&self, ids: &[String]) -> Result<Vec<&T>, ClientError> {
+        match &self.key {
+            Some(key) => {
+                let ids: BTreeSet<&str> = ids.iter().map(String::as_str).collect();
+
+                let mut documents = Vec::new();
+                for document in &self.inner {
+                    if ids.contains(expiry_key(&key(document)?).as_str()) {
+                        documents.push(document);
+                    }
+                }
+                Ok(documents)
+            }
+            None => Ok(ids
+                .iter()
+                .filter_map(|id| self.inner.get(id.parse::<usize>().ok()?))
+                .collect()),
+        }
+    }
+
+    /// Rebuild the index file after an index was dropped, deleting it if there are none left.
+    pub(crate) async fn remove_index(&mut self) -> Result<(), ClientError> {
+        if self.indexes.is_empty() && self.search.is_none() {
+            let path = index_path(&self.client, &self.name);
+
+            return match self.client.get_file(&path).await? {
+                Some(file) => {
+                    let message = self.message(&Operation::DropIndexes);
+                    self.client.delete_file(&path, &file.sha, &message).await
+                }
+                None => Ok(()),
+            };
+        }
+
+        self.update().await?;
+        self.write_indexes().await
+    }
+
+    /// rebuild the index file from the current documents, for when the indexed fields change
+    pub(crate) async fn write_indexes(&mut self) -> Result<(), ClientError> {
+        if self.indexes.is_empty() && self.search.is_none() {
+            return Ok(());
+        }
+
+        let change = self.index_change(&self.inner, &self.sha).await?;
+        let message = self.message(&Operation::UpdateIndexes);
+
+        let content = change.content.unwrap_or_default();
+        self.client
+            .put_file(
+                &change.path,
+                &content,
+                change.expected_sha.as_deref(),
+                &message,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// The index file for `documents`, stored in a collection file whose version token is `sha`, as a change to commit with it.
+    pub(crate) async fn index_change<'a>(
+        &self,
+        documents: impl IntoIterator<Item = &'a T>,
+        sha: &str,
+    ) -> Result<FileChange, ClientError>
+    where
+        T: 'a,
+    {
+        let documents = identified(documents, self.key.as_ref())?;
+        let content = encode_index_file(&documents, &self.indexes, self.search.as_deref(), sha)?;
+
+        let path = index_path(&self.client, &self.name);
+        let expected_sha = self.client.get_file(&path).await?.map(|file| file.sha);
+
+        Ok(FileChange {
+            path,
+            content: Some(content),
+            expected_sha,
+        })
+    }
+}
+
+/// Each of `documents` as JSON with its id in an index, its key or otherwise its position.
+fn identified<'a, T: Serialize + 'a>(
+    documents: impl IntoIterator<Item = &'a T>,
+    key: Option<&KeyFn<T>>,
+) -> Result<Vec<(String, Value)>, ClientError> {
+    documents
+        .into_iter()
+        .enumerate()
+        .map(|(position, document)| {
+            let id = match key {
+                Some(key) => expiry_key(&key(document)?),
+                None => position.to_string(),
+            };
+
+            match serde_json::to_value(document) {
+                Ok(document) => Ok((id, document)),
+                Err(err) => Err(ClientError::Json(err)),
+            }
+        })
+        .collect()
+}
+
+/// Pair decoded `documents` with their ids, given the keys of those that have one in a keyed collection.
+///
+/// Documents without a key in a keyed collection, like those that didn't
+/// decode, aren't indexed.
+pub(crate) fn with_ids<'a>(
+    documents: &'a [Value],
+    keys: Option<&[Option<String>]>,
+) -> Vec<(String, &'a Value)> {
+    match keys {
+        Some(keys) => keys
+            .iter()
+            .zip(documents)
+            .filter_map(|(key, document)| Some((key.clone()?, document)))
+            .collect(),
+        None => documents
+            .iter()
+            .enumerate()
+            .map(|(position, document)| (position.to_string(), document))
+            .collect(),
+    }
+}
+
+/// The index file for `documents` and their ids, stored in a collection file whose version token is `sha`.
+pub(crate) fn encode_index_file<D: Borrow<Value>>(
+    documents: &[(String, D)],
+    fields: &[String],
+    search: Option<&[String]>,
+    sha: &str,
+) -> Result<Vec<u8>, ClientError> {
+    let search = match search {
+        Some(fields) => {
+            let documents: Vec<&Value> = documents
+                .iter()
+                .map(|(_, document)| document.borrow())
+                .collect();
+            Some(build_search_index(&documents, fields)?)
+        }
+        None => None,
+    };
+
+    let index_file = IndexFile {
+        sha: sha.to_string(),
+        indexes: build_indexes(documents, fields),
+        search,
+    };
+
+    match serde_json::to_value(&index_file) {
+        Ok(value) => Json.serialize(&value),
+        Err(err) => Err(ClientError::Json(err)),
+    }
+}
+
+/// Check a collection's index file against `documents` and their ids, read from a collection file whose version token is `sha`.
+pub(crate) async fn check_indexes(
+    client: &Client,
+    name: &str,
+    fields: &[String],
+    sha: &str,
+    documents: &[(String, &Value)],
+) -> Result<Vec<Problem>, ClientError> {
+    let index_file = match read_index_file(client, name).await? {
+        Some(index_file) => index_file,
+        None => {
+            return Ok(fields
+                .iter()
+                .map(|field| Problem::MissingIndex(field.clone()))
+                .collect())
+        }
+    };
+
+    let mut problems = Vec::new();
+    if index_file.sha != sha {
+        problems.push(Problem::StaleIndex);
+    }
+
+    let stored: Vec<String> = index_file.indexes.keys().cloned().collect();
+    let current = build_indexes(documents, &stored);
+
+    for (field, index) in &index_file.indexes {
+        for (value, ids) in index {
+            for id in ids {
+                let found = current
+                    .get(field)
+                    .and_then(|index| index.get(value))
+                    .is_some_and(|current| current.contains(id));
+
+                if !found {
+                    problems.push(Problem::DanglingIndexEntry {
+                        field: field.clone(),
+                        value: value.clone(),
+                        document: id.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    for field in fields {
+        if !index_file.indexes.contains_key(field) {
+            problems.push(Problem::MissingIndex(field.clone()));
+        }
+    }
+
+    Ok(problems)
+}
+
+/// The fields indexed for a collection from its index file, or none if it has none.
+pub(crate) fn index_fields(file: Option<&RemoteFile>) -> Result<Vec<String>, ClientError> {
+    match file {
+        Some(file) => Ok(parse_index_file(file)?.indexes.into_keys().collect()),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub(crate) async fn read_index_file(
+    client: &Client,
+    name: &str,
+) -> Result<Option<IndexFile>, ClientError> {
+    match client.get_file(&index_path(client, name)).await? {
+        Some(file) => parse_index_file(&file).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The fields searched by a collection's search index, or [`None`] if it has none.
+pub(crate) fn search_fields(file: Option<&RemoteFile>) -> Result<Option<Vec<String>>, ClientError> {
+    match file {
+        Some(file) => Ok(parse_index_file(file)?.search.map(|search| search.fields)),
+        None => Ok(None),
+    }
+}
+
+fn parse_index_file(file: &RemoteFile) -> Result<IndexFile, ClientError> {
+    match serde_json::from_slice(&file.content) {
+        Ok(index_file) => Ok(index_file),
+        Err(err) => Err(ClientError::Json(err)),
+    }
+}
+
+pub(crate) fn index_path(client: &Client, name: &str) -> String {
+    client.file_path(&format!("{name}.index.json"))
+}
+
+fn build_indexes<D: Borrow<Value>>(
+    documents: &[(String, D)],
+    fields: &[String],
+) -> BTreeMap<String, Index> {
+    let mut indexes: BTreeMap<String, Index> = fields
+        .iter()
+        .map(|field| (field.clone(), Index::new()))
+        .collect();
+
+    for (id, document) in documents {
+        for (field, index) in indexes.iter_mut() {
+            if let Some(value) = field_value(document.borrow(), field) {
+                index.entry(value.to_string()).or_default().push(id.clone());
+            }
+        }
+    }
+
+    indexes
+}
+
+/// Look up a dot separated field path in a document.
+pub(crate) fn field_value<'a>(document: &'a Value, field: &str) -> Option<&'a Value> {
+    document.pointer(&format!("/{}", field.replace('.', "/")))
+}
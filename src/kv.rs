@@ -0,0 +1,123 @@
+use std::marker::PhantomData;
+
+use futures::{future, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Client, ClientError, CommitInfo, EntryKind, Format, Json, Operation};
+
+/// A key-value store where every key is its own file in the repository.
+///
+/// Values are stored at `<path_prefix><name>/<key>.json`.
+pub struct KvStore<T> {
+    pub name: String,
+    client: Client,
+    format: Json,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> KvStore<T> {
+    pub(crate) fn new(client: Client, name: String) -> Self {
+        Self {
+            name,
+            client,
+            format: Json,
+            _marker: PhantomData,
+        }
+    }
+
+    /// get the value stored under a key
+    pub async fn get(&self, key: impl AsRef<str>) -> Result<Option<T>, ClientError> {
+        let path = self.key_path(key.as_ref())?;
+
+        match self.client.get_file(&path).await? {
+            Some(file) => self.decode(&file.content).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// store a value under a key, replacing any existing value
+    pub async fn set(&self, key: impl AsRef<str>, value: T) -> Result<CommitInfo, ClientError> {
+        let key = key.as_ref();
+        let path = self.key_path(key)?;
+
+        let sha = self.client.get_file(&path).await?.map(|file| file.sha);
+
+        let value = match serde_json::to_value(&value) {
+            Ok(value) => value,
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+        let content = self.format.serialize(&value)?;
+        let operation = Operation::Set {
+            key: key.to_string(),
+        };
+        let message = self.client.commit_message(&operation, Some(&self.name));
+
+        self.client
+            .put_file(&path, &content, sha.as_deref(), &message)
+            .await
+    }
+
+    /// remove a key, returning its value if it was present
+    pub async fn remove(&self, key: impl AsRef<str>) -> Result<Option<T>, ClientError> {
+        let key = key.as_ref();
+        let path = self.key_path(key)?;
+
+        let file = match self.client.get_file(&path).await? {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+
+        let operation = Operation::Unset {
+            key: key.to_string(),
+        };
+        let message = self.client.commit_message(&operation, Some(&self.name));
+        self.client.delete_file(&path, &file.sha, &message).await?;
+
+        self.decode(&file.content).map(Some)
+    }
+
+    /// list every key in the store
+    pub async fn keys(&self) -> Result<Vec<String>, ClientError> {
+        let path = self.client.file_path(&self.name);
+        let suffix = format!(".{}", self.format.extension());
+
+        self.client
+            .list_dir_pages(&path)
+            .try_filter_map(|entry| {
+                let key = match entry.kind {
+                    EntryKind::File => entry.name.strip_suffix(&suffix).map(str::to_string),
+                    _ => None,
+                };
+                future::ready(Ok(key))
+            })
+            .try_collect()
+            .await
+    }
+
+    /// check if a key is present in the store
+    pub async fn contains_key(&self, key: impl AsRef<str>) -> Result<bool, ClientError> {
+        let path = self.key_path(key.as_ref())?;
+
+        Ok(self.client.get_file(&path).await?.is_some())
+    }
+
+    fn key_path(&self, key: &str) -> Result<String, ClientError> {
+        // keys map directly to file names so they can't escape the store's directory
+        if key.is_empty() || key.contains('/') || key == "." || key == ".." {
+            return Err(ClientError::InvalidKey(key.to_string()));
+        }
+
+        Ok(self
+            .client
+            .file_path(&format!("{}/{key}.{}", self.name, self.format.extension())))
+    }
+
+    fn decode(&self, content: &[u8]) -> Result<T, ClientError> {
+        let value = self.format.deserialize(content)?;
+
+        match serde_json::from_value(value) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(ClientError::Json(err)),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    merge,
+    meta::{self, CollectionMeta},
+    ClientError, Collection, Operation, MAX_CONFLICT_RETRIES,
+};
+
+/// A document that couldn't be deserialized: its position in the collection, why, and its raw value.
+pub type InvalidDocument = (usize, serde_json::Error, Value);
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// returns the documents that deserialize, and the position, error, and raw value of each that doesn't
+    ///
+    /// unlike [`data`](Self::data), one malformed document doesn't make the
+    /// rest of the collection unreadable. fix the malformed ones with
+    /// [`repair_with`](Self::repair_with).
+    pub async fn data_lenient(&self) -> Result<(Vec<T>, Vec<InvalidDocument>), ClientError> {
+        let file = match self.client.get_file(&self.path).await? {
+            Some(file) => file,
+            None => return Err(ClientError::NoContent),
+        };
+
+        let (_, documents) = meta::decode_collection_lenient(self.format.as_ref(), &file.content)?;
+
+        let mut valid = Vec::with_capacity(documents.len());
+        let mut invalid = Vec::new();
+        for (position, document) in documents.into_iter().enumerate() {
+            match document {
+                Ok(document) => valid.push(document),
+                Err((err, value)) => invalid.push((position, err, value)),
+            }
+        }
+
+        Ok((valid, invalid))
+    }
+
+    /// replace every document that doesn't deserialize with what `repair` returns for it, or remove it if that's [`None`]
+    ///
+    /// `repair` gets each malformed document's position and raw value.
+    /// returns how many documents were repaired or removed, nothing is
+    /// written if every document was already valid.
+    pub async fn repair_with<F>(&mut self, mut repair: F) -> Result<usize, ClientError>
+    where
+        F: FnMut(usize, Value) -> Option<T>,
+    {
+        let mut retries = 0;
+
+        loop {
+            let file = match self.client.get_file(&self.path).await? {
+                Some(file) => file,
+                None => return Err(ClientError::NoContent),
+            };
+
+            let (meta, documents) =
+                meta::decode_collection_lenient(self.format.as_ref(), &file.content)?;
+
+            let mut repaired = 0;
+            let mut inner = Vec::with_capacity(documents.len());
+            for (position, document) in documents.into_iter().enumerate() {
+                match document {
+                    Ok(document) => inner.push(document),
+                    Err((_, value)) => {
+                        repaired += 1;
+                        inner.extend(repair(position, value));
+                    }
+                }
+            }
+
+            if let Some(key) = &self.key {
+                self.base = merge::keyed(&inner, key)?;
+            }
+            self.meta = meta.unwrap_or_else(|| CollectionMeta::legacy(self.format.as_ref()));
+            self.inner = inner;
+            self.sha = file.sha;
+
+            if repaired == 0 {
+                return Ok(0);
+            }
+
+            let message = self.message(&Operation::Repair);
+            match self.write(&Operation::Repair, &message).await {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(_) => return Ok(repaired),
+            }
+        }
+    }
+}
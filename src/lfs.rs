@@ -0,0 +1,207 @@
+//! Storing large attachments in Git LFS, see [`ClientBuilder::lfs`](crate::ClientBuilder::lfs).
+//!
+//! Content is uploaded to the repository's LFS server through the batch API
+//! and a small pointer file is committed in its place, so the repository
+//! itself stays small and the contents API size limits don't apply.
+
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE, USER_AGENT},
+    Method, Response,
+};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{
+    http::{check_status, join_url, Http},
+    Client, ClientError,
+};
+
+/// The media type of requests to and responses from the batch API.
+const LFS_MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+/// A Git LFS object, identified by the sha256 of its content.
+#[derive(Clone, Debug)]
+pub(crate) struct Pointer {
+    pub(crate) oid: String,
+    pub(crate) size: u64,
+}
+
+impl Pointer {
+    pub(crate) fn new(content: &[u8]) -> Self {
+        let oid = Sha256::digest(content)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        Self {
+            oid,
+            size: content.len() as u64,
+        }
+    }
+
+    /// The pointer file committed in place of the content.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {}\n",
+            self.oid, self.size
+        )
+        .into_bytes()
+    }
+}
+
+impl Client {
+    /// A copy of this client that stores attachments of at least `threshold` bytes in Git LFS.
+    pub fn with_lfs(&self, threshold: u64) -> Client {
+        self.derive(|inner| inner.lfs_threshold = Some(threshold))
+    }
+}
+
+/// The batch API of the LFS server for the repository at `project`, like `owner/repo`, served from `web_host`.
+pub(crate) fn batch_url(web_host: &Url, project: &str) -> Url {
+    let mut url = join_url(web_host, project.split('/'));
+
+    if let Ok(mut path) = url.path_segments_mut() {
+        let repo = project.rsplit('/').next().unwrap_or_default();
+        path.pop()
+            .push(&format!("{repo}.git"))
+            .extend(["info", "lfs", "objects", "batch"]);
+    }
+
+    url
+}
+
+/// Upload `content` to the LFS server unless it already has it.
+///
+/// LFS servers take the token as the password of basic auth, `username` is
+/// whatever the forge expects alongside it.
+pub(crate) async fn upload(
+    http: &Http,
+    url: Url,
+    username: &str,
+    pointer: &Pointer,
+    content: &[u8],
+) -> Result<(), ClientError> {
+    let actions = batch(http, url, username, "upload", pointer).await?;
+
+    // no actions means the server already has the object
+    if let Some(upload) = actions.get("upload") {
+        send_action(http, upload, Method::PUT, Some(content.to_vec())).await?;
+    }
+
+    if let Some(verify) = actions.get("verify") {
+        let body = json!({ "oid": pointer.oid, "size": pointer.size });
+        let body = match serde_json::to_vec(&body) {
+            Ok(body) => body,
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+
+        send_action(http, verify, Method::POST, Some(body)).await?;
+    }
+
+    Ok(())
+}
+
+/// Download an object from the LFS server.
+pub(crate) async fn download(
+    http: &Http,
+    url: Url,
+    username: &str,
+    pointer: &Pointer,
+) -> Result<Vec<u8>, ClientError> {
+    let actions = batch(http, url, username, "download", pointer).await?;
+
+    let download = match actions.get("download") {
+        Some(download) => download,
+        None => return Err(ClientError::Lfs(format!("no download for {}", pointer.oid))),
+    };
+
+    let response = send_action(http, download, Method::GET, None).await?;
+
+    match response.bytes().await {
+        Ok(content) => Ok(content.to_vec()),
+        Err(err) => Err(ClientError::Http(err)),
+    }
+}
+
+/// Ask the batch API how to transfer a single object, returning its actions.
+async fn batch(
+    http: &Http,
+    url: Url,
+    username: &str,
+    operation: &str,
+    pointer: &Pointer,
+) -> Result<Value, ClientError> {
+    let body = json!({
+        "operation": operation,
+        "transfers": ["basic"],
+        "objects": [{ "oid": pointer.oid, "size": pointer.size }],
+    });
+
+    let token = http.credentials.token().await?;
+
+    let mut request = http
+        .client
+        .post(url)
+        .header(ACCEPT, LFS_MEDIA_TYPE)
+        .header(CONTENT_TYPE, LFS_MEDIA_TYPE)
+        .header(USER_AGENT, &http.user_agent)
+        .json(&body);
+    // public repositories can be downloaded from anonymously
+    if !token.is_empty() {
+        request = request.basic_auth(username, Some(token));
+    }
+
+    let response = match request.send().await {
+        Ok(response) => check_status(response)?,
+        Err(err) => return Err(ClientError::Http(err)),
+    };
+
+    let mut json: Value = match response.json().await {
+        Ok(json) => json,
+        Err(err) => return Err(ClientError::Http(err)),
+    };
+
+    let object = json["objects"][0].take();
+
+    if let Some(message) = object["error"]["message"].as_str() {
+        return Err(ClientError::Lfs(message.to_string()));
+    }
+
+    Ok(object["actions"].clone())
+}
+
+/// Follow an action from the batch API, which brings its own url and headers.
+async fn send_action(
+    http: &Http,
+    action: &Value,
+    method: Method,
+    body: Option<Vec<u8>>,
+) -> Result<Response, ClientError> {
+    let href = match action["href"].as_str() {
+        Some(href) => href,
+        None => return Err(ClientError::Lfs("action without a url".to_string())),
+    };
+
+    let mut request = http
+        .client
+        .request(method, href)
+        .header(USER_AGENT, &http.user_agent);
+
+    if let Some(headers) = action["header"].as_object() {
+        for (name, value) in headers {
+            if let Some(value) = value.as_str() {
+                request = request.header(name.as_str(), value);
+            }
+        }
+    }
+
+    if let Some(body) = body {
+        request = request.body(body);
+    }
+
+    match request.send().await {
+        Ok(response) => check_status(response),
+        Err(err) => Err(ClientError::Http(err)),
+    }
+}
@@ -1,22 +1,191 @@
+mod admin;
+mod aggregate;
+mod api_version;
+#[cfg(feature = "github-app")]
+mod app;
+mod attachment;
+mod audit;
+mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod borrowed;
+mod buffered;
+mod builder;
+mod changes;
+mod conflict;
+mod counter;
+#[cfg(feature = "csv")]
+mod csv;
+mod document;
+mod dry_run;
 mod error;
+mod expiry;
+mod export;
+mod fetch;
+mod fixtures;
+mod format;
+pub mod fsck;
+mod history;
+mod hook;
+mod http;
+mod id;
+mod idempotent;
+mod index;
+mod kv;
+mod lenient;
+#[cfg(feature = "lfs")]
+mod lfs;
+mod limits;
+mod lock;
+mod merge;
+mod message;
+mod meta;
+mod metrics;
+mod middleware;
+mod migration;
+pub mod mock;
+mod namespace;
+#[cfg(not(target_arch = "wasm32"))]
+mod offline;
+mod position;
+mod project;
+pub mod provider;
+mod pull_request;
+mod read_only;
+mod record;
+pub mod replicate;
+mod retry;
+#[cfg(feature = "schema")]
+pub mod schema;
+mod search;
+mod sharded;
+mod shared;
+mod signing;
+mod sort;
+mod stats;
+mod subcollection;
+mod trace;
+mod transaction;
+mod trash;
+mod validate;
+mod watch;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
-use bytes::Bytes;
-use reqwest::header::{HeaderMap, HeaderValue};
+use std::sync::Arc;
+
+use dry_run::DryRunLog;
+use fetch::CollectionFiles;
+use futures::{future, stream, Stream, TryStreamExt};
+use merge::KeyFn;
+use provider::blob_sha;
+use pull_request::PullRequestState;
 use serde::de::DeserializeOwned;
 pub use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use url::Url;
+use serde_json::Value;
+use sort::OrderFn;
+use validate::Validator;
+
+pub use api_version::ApiVersion;
+pub use async_trait::async_trait;
+pub use attachment::Attachment;
+pub use audit::{AuditEntry, AuditLog};
+pub use auth::{Auth, CredentialProvider};
+pub use borrowed::BorrowedData;
+pub use buffered::{BufferOptions, BufferedCollection};
+pub use builder::ClientBuilder;
+pub use changes::{ChangeSet, Modified};
+pub use chrono;
+pub use conflict::ConflictStrategy;
+pub use counter::Counter;
+pub use document::Document;
+pub use dry_run::{DryRunChange, DryRunCommit};
+pub use error::{ClientError, ErrorContext};
+pub use export::{Bundle, BundleFile, ImportMode};
+pub use fixtures::Fixtures;
+#[cfg(feature = "cbor")]
+pub use format::Cbor;
+#[cfg(feature = "encryption")]
+pub use format::Encrypted;
+#[cfg(feature = "gzip")]
+pub use format::Gzip;
+#[cfg(feature = "msgpack")]
+pub use format::MessagePack;
+#[cfg(feature = "toml")]
+pub use format::Toml;
+#[cfg(feature = "yaml")]
+pub use format::Yaml;
+#[cfg(feature = "zstd")]
+pub use format::Zstd;
+pub use format::{Format, Json};
+#[cfg(feature = "derive")]
+pub use github_db_derive::Document;
+pub use history::HistoryEntry;
+pub use hook::{Hook, ReadEvent, WriteEvent};
+pub use id::IdStrategy;
+pub use kv::KvStore;
+pub use lenient::InvalidDocument;
+pub use limits::{Limits, CONTENTS_API_LIMIT};
+pub use lock::{Lock, LockHolder};
+pub use message::{DefaultFormatter, MessageContext, MessageFormatter, Operation};
+pub use meta::CollectionMeta;
+pub use metrics::{Metrics, RequestMetrics};
+pub use middleware::Middleware;
+pub use migration::Migrations;
+#[cfg(not(target_arch = "wasm32"))]
+pub use provider::LocalBackend;
+pub use provider::{
+    ChangeKind, ChangedFile, CommitAuthor, CommitInfo, CommitOptions, CompactOptions, DirEntries,
+    DirEntry, EntryKind, FileChange, Forge, Polled, Provider, PullRequestOptions, RemoteFile,
+    RepositoryOptions,
+};
+pub use pull_request::WriteMode;
+pub use record::Record;
+pub use retry::RetryPolicy;
+pub use sharded::ShardedCollection;
+pub use shared::SharedCollection;
+pub use signing::CommitSigner;
+pub use stats::CollectionStats;
+pub use transaction::Transaction;
+pub use trash::Trashed;
 
-pub use error::ClientError;
+/// How many times a write is retried after losing a race with another writer.
+pub const MAX_CONFLICT_RETRIES: usize = 3;
 
 /// The entrypoint for your database connection.
+///
+/// Clones are cheap and share the connection and configuration, so a
+/// client can be cloned freely and every collection holds one.
 #[derive(Clone, Debug)]
 pub struct Client {
-    owner: String,
-    repo: String,
-    host: Url,
+    inner: Arc<ClientInner>,
+}
+
+/// What every clone of a [`Client`] shares.
+#[derive(Clone, Debug)]
+struct ClientInner {
+    provider: Arc<dyn Provider>,
     path_prefix: Option<String>,
-    client: reqwest::Client,
+    branch: Option<String>,
+    author: Option<CommitAuthor>,
+    metrics: Option<Arc<dyn Metrics>>,
+    /// the commits a dry run client would have made, see [`Client::dry_run`]
+    dry_run: Option<DryRunLog>,
+    /// the pull request writes go to, see [`WriteMode::PullRequest`]
+    pull_request: Option<Arc<futures::lock::Mutex<PullRequestState>>>,
+    /// attachments at least this large are stored in git lfs, see [`Client::with_lfs`]
+    #[cfg(feature = "lfs")]
+    lfs_threshold: Option<u64>,
+    /// writes the client refuses to make, see [`Client::with_limits`]
+    limits: Limits,
+    /// writes the message of every commit, see [`Client::with_message_formatter`]
+    messages: Arc<dyn MessageFormatter>,
+    /// snapshots of read files to fall back to, see [`Client::with_offline_cache`]
+    #[cfg(not(target_arch = "wasm32"))]
+    offline: Option<Arc<offline::OfflineCache>>,
+    /// the owner and name of the repository on the forge, which offline snapshots are kept under
+    #[cfg(not(target_arch = "wasm32"))]
+    repository: Option<(String, String)>,
 }
 
 impl Client {
@@ -28,63 +197,117 @@ impl Client {
         host: Option<String>,
         path_prefix: Option<String>,
     ) -> Result<Self, ClientError> {
-        let auth = auth_token.as_ref().to_string();
-        let owner = owner.as_ref().to_string();
-        let repo = repo.as_ref().to_string();
-
-        let host: Url = match host {
-            Some(host) => match Url::parse(&host) {
-                Ok(host) => host,
-                Err(err) => return Err(ClientError::Parse(err)),
-            },
-            None => match Url::parse("https://api.github.com") {
-                Ok(host) => host,
-                Err(err) => return Err(ClientError::Parse(err)),
-            },
-        };
-
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "Accept",
-            HeaderValue::from_str("application/vnd.github+json").unwrap(),
-        );
-        headers.insert(
-            "Authorization",
-            HeaderValue::from_str(format!("Bearer {auth}").as_str()).unwrap(),
-        );
-
-        let builder = reqwest::Client::builder()
-            .default_headers(headers)
-            .user_agent(&format!("{}-{}", &owner, &repo));
-
-        let client = match builder.build() {
-            Ok(client) => client,
-            Err(err) => return Err(ClientError::Http(err)),
-        };
-
-        Ok(Self {
+        Self::with_auth(
+            Auth::PersonalAccessToken(auth_token.as_ref().to_string()),
             owner,
             repo,
             host,
             path_prefix,
-            client,
-        })
+        )
     }
 
-    fn create_url(&self, path: Option<&str>) -> Url {
-        let mut base_url = self.host.clone();
+    /// Create a new [`Client`] using any supported [`Auth`] method.
+    pub fn with_auth(
+        auth: Auth,
+        owner: impl AsRef<str>,
+        repo: impl AsRef<str>,
+        host: Option<String>,
+        path_prefix: Option<String>,
+    ) -> Result<Self, ClientError> {
+        let mut builder = Self::builder(owner, repo).auth(auth);
+        builder.host = host;
+        builder.path_prefix = path_prefix;
 
-        let prefix = &self.path_prefix.clone().unwrap_or_default();
+        builder.build()
+    }
 
-        base_url.set_path(&format!(
-            "/repos/{}/{}/contents/{}{}",
-            self.owner,
-            self.repo,
-            prefix,
-            path.unwrap_or_default(),
-        ));
+    /// Create a new [`Client`] that asks a [`CredentialProvider`] for a token before every request.
+    ///
+    /// Use this when tokens are rotated externally, for example by Vault or OIDC.
+    pub fn with_credentials(
+        credentials: impl CredentialProvider + 'static,
+        owner: impl AsRef<str>,
+        repo: impl AsRef<str>,
+        host: Option<String>,
+        path_prefix: Option<String>,
+    ) -> Result<Self, ClientError> {
+        let mut builder = Self::builder(owner, repo).credentials(credentials);
+        builder.host = host;
+        builder.path_prefix = path_prefix;
 
-        base_url
+        builder.build()
+    }
+
+    /// Create a new [`Client`] that sends requests through an existing [`reqwest::Client`].
+    ///
+    /// Useful for sharing a connection pool or a client already set up with
+    /// tracing, metrics, or corporate proxy settings.
+    pub fn with_http_client(
+        http_client: reqwest::Client,
+        auth: Auth,
+        owner: impl AsRef<str>,
+        repo: impl AsRef<str>,
+        host: Option<String>,
+        path_prefix: Option<String>,
+    ) -> Result<Self, ClientError> {
+        let mut builder = Self::builder(owner, repo)
+            .auth(auth)
+            .http_client(http_client);
+        builder.host = host;
+        builder.path_prefix = path_prefix;
+
+        builder.build()
+    }
+
+    /// Configure a [`Client`] with timeouts, connection pooling, a proxy, etc.
+    pub fn builder(owner: impl AsRef<str>, repo: impl AsRef<str>) -> ClientBuilder {
+        ClientBuilder::new(owner.as_ref().to_string(), repo.as_ref().to_string())
+    }
+
+    /// Create a new [`Client`] that stores everything through a custom [`Provider`].
+    pub fn with_provider(provider: impl Provider + 'static, path_prefix: Option<String>) -> Self {
+        Self {
+            inner: Arc::new(ClientInner {
+                provider: Arc::new(provider),
+                path_prefix,
+                branch: None,
+                author: None,
+                metrics: None,
+                dry_run: None,
+                pull_request: None,
+                #[cfg(feature = "lfs")]
+                lfs_threshold: None,
+                limits: Limits::default(),
+                messages: Arc::new(DefaultFormatter),
+                #[cfg(not(target_arch = "wasm32"))]
+                offline: None,
+                #[cfg(not(target_arch = "wasm32"))]
+                repository: None,
+            }),
+        }
+    }
+
+    /// A copy of this client with its configuration changed by `change`, leaving this one as it is.
+    fn derive(&self, change: impl FnOnce(&mut ClientInner)) -> Client {
+        let mut inner = ClientInner::clone(&self.inner);
+        change(&mut inner);
+
+        Client {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Create a new [`Client`] that stores everything in a local directory, see [`LocalBackend`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn local(root: impl Into<std::path::PathBuf>) -> Self {
+        Self::with_provider(LocalBackend::new(root), None)
+    }
+
+    /// The path of a file relative to the root of the repository.
+    fn file_path(&self, path: &str) -> String {
+        let prefix = self.inner.path_prefix.as_deref().unwrap_or_default();
+
+        format!("{prefix}{path}")
     }
 
     /// Return a reference to a collection in the database.
@@ -93,215 +316,846 @@ impl Client {
     pub async fn collection<T: Serialize + DeserializeOwned>(
         &self,
         name: impl AsRef<str>,
+    ) -> Result<Collection<T>, ClientError> {
+        self.collection_with_format(name, Json).await
+    }
+
+    /// Return a reference to a collection stored in the given [`Format`].
+    ///
+    /// The format's extension is used for the file name, so the same name
+    /// in two formats refers to two separate collections.
+    pub async fn collection_with_format<T: Serialize + DeserializeOwned>(
+        &self,
+        name: impl AsRef<str>,
+        format: impl Format + 'static,
     ) -> Result<Collection<T>, ClientError> {
         let name = name.as_ref().to_string();
-        let url = self.create_url(Some(&format!("{name}.json"))).clone();
 
-        // start by trying to get the document to see if it's already there
-        let get_bytes: Option<Bytes> = match self.client.get(url.clone()).send().await {
-            Ok(response) => {
-                if response.status() == 404 {
-                    None
-                } else {
-                    match response.bytes().await {
-                        Ok(bytes) => Some(bytes),
-                        Err(e) => return Err(ClientError::Http(e)),
-                    }
-                }
-            }
-            Err(e) => return Err(ClientError::Http(e)),
+        match self.load_collection(name.clone(), Arc::new(format)).await {
+            Err(err) => Err(err.in_collection(&name, "open")),
+            collection => collection,
+        }
+    }
+
+    /// Fetch a collection's files and open it.
+    async fn load_collection<T: Serialize + DeserializeOwned>(
+        &self,
+        name: String,
+        format: Arc<dyn Format>,
+    ) -> Result<Collection<T>, ClientError> {
+        let files = CollectionFiles {
+            collection: self.get_file(&self.collection_path(&name, &format)).await?,
+            index: self.get_file(&index::index_path(self, &name)).await?,
+            #[cfg(feature = "schema")]
+            schema: self.get_file(&schema::schema_path(self, &name)).await?,
         };
 
-        // if there was a 404 for trying to get it then we try to create an empty document
-        let bytes: Bytes = match get_bytes {
-            Some(b) => b,
+        self.open_collection(name, format, files).await
+    }
+
+    /// The path of a collection's file.
+    pub(crate) fn collection_path(&self, name: &str, format: &Arc<dyn Format>) -> String {
+        self.file_path(&format!("{name}.{}", format.extension()))
+    }
+
+    /// Open a collection from its already fetched files, creating it if it doesn't exist.
+    async fn open_collection<T: Serialize + DeserializeOwned>(
+        &self,
+        name: String,
+        format: Arc<dyn Format>,
+        files: CollectionFiles,
+    ) -> Result<Collection<T>, ClientError> {
+        let path = self.collection_path(&name, &format);
+
+        // start by trying to get the document to see if it's already there
+        let (meta, inner, sha) = match files.collection {
+            Some(file) => decode_collection_file(format.as_ref(), file)?,
+            // if there was a 404 for trying to get it then we try to create an empty document
             None => {
-                let request_body = format!(
-                    "{{\"message\":\"Creating Collection '{}'\",\"content\":\"{}\"}}",
-                    &name,
-                    base64::encode("[]".as_bytes())
-                );
-
-                match self.client.put(url.clone()).body(request_body).send().await {
-                    Ok(response) => match response.bytes().await {
-                        Ok(r) => r,
-                        Err(e) => return Err(ClientError::Http(e)),
-                    },
-                    Err(e) => return Err(ClientError::Http(e)),
-                }
-            }
-        };
+                let meta = CollectionMeta::new(format.as_ref(), 0);
+                let content = meta::encode_collection::<T>(format.as_ref(), &meta, &[])?;
+                let message = self.commit_message(&Operation::CreateCollection, Some(&name));
+                let mut retries = 0;
 
-        let json: Value = match serde_json::from_slice(&bytes) {
-            Ok(json) => json,
-            Err(err) => return Err(ClientError::Json(err)),
-        };
+                loop {
+                    match self.put_file(&path, &content, None, &message).await {
+                        Ok(commit) => break (meta, Vec::new(), commit.sha),
+                        // another client created it in between, so open theirs
+                        Err(ClientError::Conflict) => (),
+                        Err(err) => return Err(err),
+                    }
 
-        let inner: Vec<T> = if let Some(content_value) = json.get("content") {
-            decode_serde_base64(content_value)?
-        } else {
-            return Err(ClientError::NoContent);
+                    match self.get_file(&path).await? {
+                        Some(file) => break decode_collection_file(format.as_ref(), file)?,
+                        // it was deleted again, or the forge isn't showing it yet
+                        None if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                        None => return Err(ClientError::Conflict),
+                    }
+                }
+            }
         };
 
-        // github requires we send along a sha with our updates so we store it every time we download
-        let sha = if let Some(sha) = json.get("sha") {
-            sha.to_string().replace('"', "")
-        } else {
-            return Err(ClientError::NoSha);
-        };
+        let indexes = index::index_fields(files.index.as_ref())?;
+        let search = index::search_fields(files.index.as_ref())?;
+        #[cfg(feature = "schema")]
+        let schema = schema::load(files.schema.as_ref())?;
 
         Ok(Collection {
             name,
-            url,
-            client: self.client.clone(),
+            path,
+            client: self.clone(),
+            format,
             inner,
             sha,
+            meta,
+            indexes,
+            search,
+            conflict: ConflictStrategy::default(),
+            key: None,
+            base: Vec::new(),
+            order: None,
+            validators: Vec::new(),
+            ids: IdStrategy::default(),
+            hooks: Vec::new(),
+            #[cfg(feature = "schema")]
+            schema,
         })
     }
+
+    /// List the names of the JSON collections in the database.
+    ///
+    /// The listing is fetched a page at a time as the stream is polled, so
+    /// it works for directories of any size. Files stored alongside
+    /// collections, like their index and schema files, are skipped.
+    pub fn list_collections(&self) -> impl Stream<Item = Result<String, ClientError>> + '_ {
+        let root = self.inner.path_prefix.as_deref().unwrap_or_default();
+        let suffix = format!(".{}", Json.extension());
+
+        self.list_dir_pages(root).try_filter_map(move |entry| {
+            let name = match entry.kind {
+                EntryKind::File => entry
+                    .name
+                    .strip_suffix(&suffix)
+                    .filter(|name| !name.contains('.'))
+                    .map(str::to_string),
+                _ => None,
+            };
+            future::ready(Ok(name))
+        })
+    }
+
+    /// Return a single document in the database, stored as `<name>.document.json`.
+    pub fn document<T: Serialize + DeserializeOwned>(&self, name: impl AsRef<str>) -> Document<T> {
+        Document::new(self.clone(), name.as_ref().to_string())
+    }
+
+    /// Return a counter in the database, stored as `<name>.counter.json`.
+    pub fn counter(&self, name: impl AsRef<str>) -> Counter {
+        Counter::new(self.clone(), name.as_ref().to_string())
+    }
+
+    /// Return a key-value store in the database.
+    ///
+    /// Each key is stored as its own file in a directory named after the store.
+    pub fn kv<T: Serialize + DeserializeOwned>(&self, name: impl AsRef<str>) -> KvStore<T> {
+        KvStore::new(self.clone(), name.as_ref().to_string())
+    }
+
+    /// Return a collection split across several files of at most `shard_size` documents.
+    ///
+    /// Shards are stored as `<name>/000000.json`, `<name>/000001.json`, etc.
+    /// Reads only fetch the shards they need, so this is the layout to use
+    /// for collections too large to comfortably load all at once.
+    pub fn sharded_collection<T: Serialize + DeserializeOwned>(
+        &self,
+        name: impl AsRef<str>,
+        shard_size: usize,
+    ) -> ShardedCollection<T> {
+        ShardedCollection::new(self.clone(), name.as_ref().to_string(), shard_size)
+    }
+
+    /// Start a [`Transaction`] that commits changes to several collections at once.
+    pub fn transaction<'a>(&self) -> Transaction<'a> {
+        Transaction::new(self.clone())
+    }
+
+    fn commit_options(&self, message: &str) -> CommitOptions {
+        CommitOptions {
+            message: message.to_string(),
+            branch: self.inner.branch.clone(),
+            author: self.inner.author.clone(),
+        }
+    }
+
+    /// Fetch a file from the configured branch, returning [`None`] if it doesn't exist.
+    ///
+    /// With an offline cache, the file is read from its last snapshot if the forge can't be reached.
+    async fn get_file(&self, path: &str) -> Result<Option<RemoteFile>, ClientError> {
+        let result = self
+            .inner
+            .provider
+            .get_file(path, self.inner.branch.as_deref())
+            .await;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(cache) = &self.inner.offline {
+            let snapshot = self.snapshot_path(path);
+            return match result {
+                Ok(file) => {
+                    cache.store(&snapshot, file.as_ref());
+                    Ok(file)
+                }
+                Err(err) if offline::is_unreachable(&err) => cache.fall_back(&snapshot, err),
+                Err(err) => Err(err),
+            };
+        }
+
+        result
+    }
+
+    /// Fetch a file as of a branch or commit, returning [`None`] if it doesn't exist.
+    async fn get_file_at(
+        &self,
+        path: &str,
+        reference: &str,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        self.inner.provider.get_file(path, Some(reference)).await
+    }
+
+    /// Create or update a file, returning the commit and its new sha.
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        message: &str,
+    ) -> Result<CommitInfo, ClientError> {
+        let commit = self
+            .inner
+            .provider
+            .put_file(path, content, sha, &self.commit_options(message))
+            .await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.remember_write(
+            path,
+            Some(&RemoteFile {
+                content: content.to_vec(),
+                sha: commit.sha.clone(),
+            }),
+        );
+
+        Ok(commit)
+    }
+
+    /// Delete a file.
+    async fn delete_file(&self, path: &str, sha: &str, message: &str) -> Result<(), ClientError> {
+        self.inner
+            .provider
+            .delete_file(path, sha, &self.commit_options(message))
+            .await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.remember_write(path, None);
+
+        Ok(())
+    }
+
+    /// Write several files in a single commit, returning the commit's sha.
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        message: &str,
+    ) -> Result<String, ClientError> {
+        let commit = self
+            .inner
+            .provider
+            .commit_files(changes, &self.commit_options(message))
+            .await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        for change in changes {
+            let file = change.content.as_ref().map(|content| RemoteFile {
+                content: content.clone(),
+                sha: self.inner.provider.version_after_commit(content, &commit),
+            });
+            self.remember_write(&change.path, file.as_ref());
+        }
+
+        Ok(commit)
+    }
+
+    /// List the entries of a directory, returning an empty list if it doesn't exist.
+    async fn list_dir(&self, path: &str) -> Result<Vec<DirEntry>, ClientError> {
+        self.inner
+            .provider
+            .list_dir(path, self.inner.branch.as_deref())
+            .await
+    }
+
+    /// List the entries of a directory a page at a time, see [`Provider::list_dir_pages`].
+    fn list_dir_pages<'a>(&'a self, path: &'a str) -> DirEntries<'a> {
+        self.inner
+            .provider
+            .list_dir_pages(path, self.inner.branch.as_deref())
+    }
 }
 
 /// A collection of documents in the database
 pub struct Collection<T> {
     pub name: String,
-    url: Url,
-    client: reqwest::Client,
+    path: String,
+    client: Client,
+    format: Arc<dyn Format>,
     sha: String,
     inner: Vec<T>,
+    meta: CollectionMeta,
+    indexes: Vec<String>,
+    /// the fields the search index covers, see [`Collection::create_search_index`]
+    search: Option<Vec<String>>,
+    conflict: ConflictStrategy<T>,
+    /// how documents are matched when merging, see [`Collection::with_key`]
+    key: Option<KeyFn<T>>,
+    /// the documents as of the last sync, keyed for merging
+    base: Vec<(String, Value)>,
+    /// the order documents are kept in, see [`Collection::sort_by`]
+    order: Option<OrderFn<T>>,
+    /// checks run before every write, see [`Collection::with_validator`]
+    validators: Vec<Validator<T>>,
+    /// how new document ids are made, see [`Collection::new_id`]
+    ids: IdStrategy,
+    /// callbacks run around reads and writes, see [`Collection::with_hook`]
+    hooks: Vec<Arc<dyn Hook>>,
+    #[cfg(feature = "schema")]
+    schema: Option<schema::Schema>,
 }
 
 impl<T: Serialize + DeserializeOwned> Collection<T> {
     /// update client state to be in line with the database
     pub async fn update(&mut self) -> Result<(), ClientError> {
-        let bytes: Bytes = match self.client.get(self.url.clone()).send().await {
-            Ok(response) => match response.bytes().await {
-                Ok(bytes) => bytes,
-                Err(e) => return Err(ClientError::Http(e)),
-            },
-            Err(e) => return Err(ClientError::Http(e)),
-        };
+        let name = self.name.clone();
 
-        let json: Value = match serde_json::from_slice(&bytes) {
-            Ok(json) => json,
-            Err(err) => return Err(ClientError::Json(err)),
-        };
+        match trace::collection(&name, "update", self.fetch()).await {
+            Err(err) => Err(err.in_collection(&name, "update")),
+            Ok(()) => self.read_hooks().await,
+        }
+    }
 
-        self.inner = if let Some(content_value) = json.get("content") {
-            decode_serde_base64(content_value)?
-        } else {
-            return Err(ClientError::NoContent);
+    async fn fetch(&mut self) -> Result<(), ClientError> {
+        let file = match self.client.get_file(&self.path).await? {
+            Some(file) => file,
+            None => return Err(ClientError::NoContent),
         };
 
-        // github requires we send along a sha with our updates so we store it every time we download
-        self.sha = if let Some(sha) = json.get("sha") {
-            sha.to_string().replace('"', "")
-        } else {
-            return Err(ClientError::NoSha);
-        };
+        let (meta, inner) = meta::decode_collection(self.format.as_ref(), &file.content)?;
+        if let Some(key) = &self.key {
+            self.base = merge::keyed(&inner, key)?;
+        }
+        self.meta = meta.unwrap_or_else(|| CollectionMeta::legacy(self.format.as_ref()));
+        self.inner = inner;
+        self.sha = file.sha;
+
+        expiry::retain_unexpired(&mut self.inner, self.key.as_ref(), &self.meta.expires)?;
 
         Ok(())
     }
 
     /// push document to the database
-    pub async fn insert(&mut self, data: T) -> Result<(), ClientError> {
-        self.update().await?;
+    pub async fn insert(&mut self, data: T) -> Result<CommitInfo, ClientError> {
+        let message = self.message(&Operation::Insert);
+        self.insert_with(data, message).await
+    }
 
-        self.inner.push(data);
+    /// like [`insert`](Self::insert), with `message` as the commit message
+    pub async fn insert_with(
+        &mut self,
+        data: T,
+        message: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError> {
+        let message = message.as_ref();
+        let mut data = data;
+        let mut retries = 0;
 
-        let inner_json = match serde_json::to_string(&self.inner) {
-            Ok(json) => json,
-            Err(err) => return Err(ClientError::Json(err)),
-        };
+        loop {
+            self.update().await?;
 
-        let request_body = match serde_json::to_string(&json!({
-            "message": "Insert",
-            "content": base64::encode(inner_json.as_bytes()),
-            "sha": self.sha,
-        })) {
-            Ok(body) => body,
-            Err(err) => return Err(ClientError::Json(err)),
-        };
+            let sha = self.sha.clone();
+            self.inner.push(data);
 
-        let _response: Value = match self
-            .client
-            .put(self.url.clone())
-            .body(request_body)
-            .send()
-            .await
-        {
-            Ok(response) => match response.json().await {
-                Ok(r) => r,
-                Err(e) => return Err(ClientError::Http(e)),
-            },
-            Err(e) => return Err(ClientError::Http(e)),
-        };
+            match self.write(&Operation::Insert, message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => {
+                    retries += 1;
 
-        Ok(())
+                    data = match self.inner.pop() {
+                        Some(data) => data,
+                        None => return Err(ClientError::Conflict),
+                    };
+                }
+                Err(err) => {
+                    // nothing was committed, so take the document back out
+                    if self.sha == sha {
+                        self.inner.pop();
+                    }
+                    return Err(err);
+                }
+                Ok(commit) => return Ok(commit),
+            }
+        }
+    }
+
+    /// push document to the database unless another document has the same key
+    ///
+    /// returns [`ClientError::DuplicateKey`] without writing anything if
+    /// `key` returns the same value for `data` and an existing document
+    pub async fn insert_unique<K, F>(&mut self, data: T, key: F) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        let message = self.message(&Operation::Insert);
+        self.insert_unique_with(data, key, message).await
+    }
+
+    /// like [`insert_unique`](Self::insert_unique), with `message` as the commit message
+    pub async fn insert_unique_with<K, F>(
+        &mut self,
+        data: T,
+        key: F,
+        message: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        let message = message.as_ref();
+        let new_key = key(&data);
+        let mut data = data;
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+
+            if self.inner.iter().any(|document| key(document) == new_key) {
+                return Err(ClientError::DuplicateKey);
+            }
+
+            let sha = self.sha.clone();
+            self.inner.push(data);
+
+            match self.write(&Operation::Insert, message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => {
+                    retries += 1;
+
+                    data = match self.inner.pop() {
+                        Some(data) => data,
+                        None => return Err(ClientError::Conflict),
+                    };
+                }
+                Err(err) => {
+                    // nothing was committed, so take the document back out
+                    if self.sha == sha {
+                        self.inner.pop();
+                    }
+                    return Err(err);
+                }
+                Ok(commit) => return Ok(commit),
+            }
+        }
+    }
+
+    /// replace the document with the same key as `data`, or push it if there isn't one
+    ///
+    /// if another writer changes the collection between reading and writing
+    /// it's handled by the collection's [`ConflictStrategy`]
+    pub async fn upsert<K, F>(&mut self, data: T, key: F) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        let message = self.message(&Operation::Upsert);
+        self.upsert_with(data, key, message).await
+    }
+
+    /// like [`upsert`](Self::upsert), with `message` as the commit message
+    pub async fn upsert_with<K, F>(
+        &mut self,
+        data: T,
+        key: F,
+        message: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        let message = message.as_ref();
+        let new_key = key(&data);
+        let mut data = data;
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+
+            let sha = self.sha.clone();
+            let position = self
+                .inner
+                .iter()
+                .position(|document| key(document) == new_key);
+            let previous = match position {
+                Some(position) => {
+                    Some((position, std::mem::replace(&mut self.inner[position], data)))
+                }
+                None => {
+                    self.inner.push(data);
+                    None
+                }
+            };
+
+            match self.write(&Operation::Upsert, message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => {
+                    retries += 1;
+
+                    // take the document back out so it can be applied to the fresh copy
+                    data = match position {
+                        Some(position) => self.inner.swap_remove(position),
+                        None => match self.inner.pop() {
+                            Some(data) => data,
+                            None => return Err(ClientError::Conflict),
+                        },
+                    };
+                }
+                Err(err) => {
+                    // nothing was committed, so put back the document it replaced
+                    if self.sha == sha {
+                        match previous {
+                            Some((position, previous)) => self.inner[position] = previous,
+                            None => {
+                                self.inner.pop();
+                            }
+                        }
+                    }
+                    return Err(err);
+                }
+                Ok(commit) => return Ok(commit),
+            }
+        }
     }
 
     /// overwrite the entire collection
-    pub async fn set_as(&mut self, value: Vec<T>) -> Result<(), ClientError> {
+    pub async fn set_as(&mut self, value: Vec<T>) -> Result<CommitInfo, ClientError> {
+        let message = self.message(&Operation::Overwrite);
+        self.set_as_with(value, message).await
+    }
+
+    /// like [`set_as`](Self::set_as), with `message` as the commit message
+    pub async fn set_as_with(
+        &mut self,
+        value: Vec<T>,
+        message: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError> {
+        let message = message.as_ref();
+        let mut value = value;
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+
+            let sha = self.sha.clone();
+            let previous = std::mem::replace(&mut self.inner, value);
+
+            match self.write(&Operation::Overwrite, message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => {
+                    retries += 1;
+                    value = std::mem::take(&mut self.inner);
+                }
+                Err(err) => {
+                    // nothing was committed, so put back the documents it replaced
+                    if self.sha == sha {
+                        self.inner = previous;
+                    }
+                    return Err(err);
+                }
+                Ok(commit) => return Ok(commit),
+            }
+        }
+    }
+
+    /// syncs and returns all documents
+    pub async fn data(&mut self) -> Result<&Vec<T>, ClientError> {
+        self.update().await?;
+
+        Ok(&self.inner)
+    }
+
+    /// syncs and returns `limit` documents starting at `offset`
+    pub async fn page(&mut self, offset: usize, limit: usize) -> Result<&[T], ClientError> {
         self.update().await?;
 
-        self.inner = value;
+        let start = offset.min(self.inner.len());
+        let end = offset.saturating_add(limit).min(self.inner.len());
+
+        Ok(&self.inner[start..end])
+    }
+
+    /// stream every document in the collection
+    ///
+    /// the collection is fetched when the stream is first polled. for
+    /// collections too large to hold in memory use a
+    /// [`ShardedCollection`] instead.
+    pub fn iter(&self) -> impl Stream<Item = Result<T, ClientError>> + '_ {
+        stream::once(async move {
+            let file = match self.client.get_file(&self.path).await? {
+                Some(file) => file,
+                None => return Err(ClientError::NoContent),
+            };
+
+            let (meta, mut documents) =
+                meta::decode_collection(self.format.as_ref(), &file.content)?;
+            if let Some(meta) = meta {
+                expiry::retain_unexpired(&mut documents, self.key.as_ref(), &meta.expires)?;
+            }
+
+            Ok(documents)
+        })
+        .map_ok(|documents| stream::iter(documents.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// the format this collection is stored in
+    pub fn format(&self) -> &dyn Format {
+        self.format.as_ref()
+    }
+
+    /// The commit message for `operation` on this collection.
+    pub(crate) fn message(&self, operation: &Operation) -> String {
+        self.client.commit_message(operation, Some(&self.name))
+    }
 
-        let inner_json = match serde_json::to_string(&self.inner) {
-            Ok(json) => json,
-            Err(err) => return Err(ClientError::Json(err)),
+    /// Write the collection for `operation`, returning the commit it made.
+    async fn write(
+        &mut self,
+        operation: &Operation,
+        message: &str,
+    ) -> Result<CommitInfo, ClientError> {
+        let name = self.name.clone();
+        let write = self.write_with_retries(operation, message, true);
+        trace::collection(&name, message, write).await
+    }
+
+    /// Like [`write`](Self::write), but a keyed collection isn't merged on a conflict since that loses the order of `ours`.
+    async fn write_ordered(
+        &mut self,
+        operation: &Operation,
+        message: &str,
+    ) -> Result<CommitInfo, ClientError> {
+        let name = self.name.clone();
+        let write = self.write_with_retries(operation, message, false);
+        trace::collection(&name, message, write).await
+    }
+
+    async fn write_with_retries(
+        &mut self,
+        operation: &Operation,
+        message: &str,
+        merge_by_key: bool,
+    ) -> Result<CommitInfo, ClientError> {
+        let mut retries = 0;
+
+        let commit = loop {
+            match self.try_write(operation, message, Vec::new()).await {
+                Ok(commit) => break commit,
+                Err(ClientError::Conflict) => {
+                    if let Some(metrics) = &self.client.inner.metrics {
+                        metrics.conflict(&self.name);
+                    }
+                    if retries >= MAX_CONFLICT_RETRIES {
+                        return Err(ClientError::Conflict);
+                    }
+                    retries += 1;
+                }
+                Err(err) => return Err(err),
+            }
+
+            // without a key, rebasing re-runs the whole operation so it's handled by the caller
+            let merge = match (&self.conflict, &self.key) {
+                (ConflictStrategy::FailFast, _) | (ConflictStrategy::RetryRebase, None) => {
+                    return Err(ClientError::Conflict)
+                }
+                (ConflictStrategy::RetryRebase, Some(_)) if !merge_by_key => {
+                    return Err(ClientError::Conflict)
+                }
+                (ConflictStrategy::LastWriteWins, _) => None,
+                (ConflictStrategy::RetryRebase, Some(_)) | (ConflictStrategy::Merge(_), _) => {
+                    Some(self.conflict.clone())
+                }
+            };
+
+            let file = match self.client.get_file(&self.path).await? {
+                Some(file) => file,
+                None => return Err(ClientError::NoContent),
+            };
+            self.sha = file.sha;
+
+            if let Some(merge) = merge {
+                let (meta, theirs) = meta::decode_collection(self.format.as_ref(), &file.content)?;
+                if let Some(meta) = meta {
+                    self.meta = meta;
+                }
+
+                let ours = std::mem::take(&mut self.inner);
+                self.inner = match (merge, &self.key) {
+                    (ConflictStrategy::Merge(merge), _) => merge(ours, theirs),
+                    (_, Some(key)) => {
+                        let base = merge::keyed(&theirs, key)?;
+                        let merged = merge::three_way_merge(&self.base, ours, theirs, key)?;
+                        self.base = base;
+                        merged
+                    }
+                    (_, None) => ours,
+                };
+            }
         };
 
-        let request_body = match serde_json::to_string(&json!({
-            "message": "Overwrite",
-            "content": base64::encode(inner_json.as_bytes()),
-            "sha": self.sha,
-        })) {
-            Ok(body) => body,
-            Err(err) => return Err(ClientError::Json(err)),
+        self.after_write_hooks(operation, message, &commit).await?;
+
+        Ok(commit)
+    }
+
+    /// Write the collection for `operation` once, committing `extra` alongside it, without retrying on a conflict.
+    ///
+    /// Runs the same checks and hooks as [`write`](Self::write), for writes
+    /// that change other files in the same commit and so re-read them when
+    /// there's a conflict.
+    pub(crate) async fn write_with_files(
+        &mut self,
+        operation: &Operation,
+        message: &str,
+        extra: Vec<FileChange>,
+    ) -> Result<CommitInfo, ClientError> {
+        let name = self.name.clone();
+        let write = async {
+            let commit = self.try_write(operation, message, extra).await?;
+            self.after_write_hooks(operation, message, &commit).await?;
+            Ok(commit)
         };
+        trace::collection(&name, message, write).await
+    }
 
-        let _response: Value = match self
-            .client
-            .put(self.url.clone())
-            .body(request_body)
-            .send()
-            .await
-        {
-            Ok(response) => match response.json().await {
-                Ok(r) => r,
-                Err(e) => return Err(ClientError::Http(e)),
-            },
-            Err(e) => return Err(ClientError::Http(e)),
+    /// Validate, prune, and sort the documents, then commit them along with `extra`.
+    async fn try_write(
+        &mut self,
+        operation: &Operation,
+        message: &str,
+        extra: Vec<FileChange>,
+    ) -> Result<CommitInfo, ClientError> {
+        let mut changes = self.stage(operation, message).await?;
+
+        let commit = if changes.len() == 1 && extra.is_empty() {
+            let content = changes[0].content.as_deref().unwrap_or_default();
+            self.client
+                .put_file(&self.path, content, Some(&self.sha), message)
+                .await?
+        } else {
+            changes.extend(extra);
+            let commit = self.client.commit_files(&changes, message).await?;
+            let content = changes[0].content.as_deref().unwrap_or_default();
+
+            CommitInfo {
+                sha: self
+                    .client
+                    .inner
+                    .provider
+                    .version_after_commit(content, &commit),
+                commit: Some(commit),
+                ..CommitInfo::default()
+            }
         };
 
-        Ok(())
+        self.committed(commit.sha.clone());
+
+        Ok(commit)
     }
 
-    /// syncs and returns all documents
-    pub async fn data(&mut self) -> Result<&Vec<T>, ClientError> {
-        self.update().await?;
+    /// Validate, prune, and encode the documents for `operation`, returning the changes that write them.
+    ///
+    /// The first change is the collection file, followed by its index file
+    /// if it has indexes, so the indexes never describe another version of
+    /// the documents. Nothing is committed, and `inner` is left unsorted so
+    /// positions in it stay valid if the commit fails.
+    pub(crate) async fn stage(
+        &mut self,
+        operation: &Operation,
+        message: &str,
+    ) -> Result<Vec<FileChange>, ClientError> {
+        self.validate(&self.inner)?;
+        self.before_write_hooks(operation, message).await?;
+        self.meta.indexes = self.indexes.clone();
+        self.prune_expiries()?;
 
-        Ok(&self.inner)
+        let mut documents: Vec<&T> = self.inner.iter().collect();
+        if let Some(order) = &self.order {
+            documents.sort_by(|a, b| order(a, b));
+        }
+        let content = meta::encode_collection(self.format.as_ref(), &self.meta, &documents)?;
+        self.check_limits(&content)?;
+
+        let index = if self.indexes.is_empty() && self.search.is_none() {
+            None
+        } else {
+            Some(
+                self.index_change(documents.iter().copied(), &blob_sha(&content))
+                    .await?,
+            )
+        };
+
+        let mut changes = vec![FileChange {
+            path: self.path.clone(),
+            content: Some(content),
+            expected_sha: Some(self.sha.clone()),
+        }];
+        changes.extend(index);
+
+        Ok(changes)
+    }
+
+    /// Sort the documents like the [`stage`](Self::stage)d file and record its version token `sha` once it's committed.
+    pub(crate) fn committed(&mut self, sha: String) {
+        if let Some(order) = &self.order {
+            self.inner.sort_by(|a, b| order(a, b));
+        }
+        self.sha = sha;
+    }
+}
+
+/// Decode a collection file that already exists, returning its metadata, documents, and sha.
+fn decode_collection_file<T: DeserializeOwned>(
+    format: &dyn Format,
+    file: RemoteFile,
+) -> Result<(CollectionMeta, Vec<T>, String), ClientError> {
+    match meta::decode_collection(format, &file.content) {
+        Ok((meta, inner)) => {
+            let meta = meta.unwrap_or_else(|| CollectionMeta::legacy(format));
+            Ok((meta, inner, file.sha))
+        }
+        // decoding each document separately is slower, so only done to find which failed
+        Err(ClientError::Json(_)) => {
+            let (meta, documents) = meta::decode_collection_lenient(format, &file.content)?;
+            let meta = meta.unwrap_or_else(|| CollectionMeta::legacy(format));
+
+            // a malformed document doesn't stop the collection from opening, reads
+            // still fail until it's repaired and without a sha nothing overwrites it
+            match documents.into_iter().collect::<Result<Vec<T>, _>>() {
+                Ok(inner) => Ok((meta, inner, file.sha)),
+                Err(_) => Ok((meta, Vec::new(), String::new())),
+            }
+        }
+        Err(err) => Err(err),
     }
 }
 
-fn decode_serde_base64<T: DeserializeOwned>(value: &Value) -> Result<Vec<T>, ClientError> {
-    // both serde_json and github are messing me up here.
-    // it puts "\n" into the base64? I have no idea why.
-    let content_encoded = value.to_string().replace("\\n", "");
-    let content_encoded = content_encoded
-        .split_at(content_encoded.len() - 1)
-        .0
-        .split_at(1)
-        .1;
-
-    let content_decoded = match base64::decode(content_encoded) {
-        Ok(decoded) => decoded,
-        Err(err) => return Err(ClientError::BadEncoding(err)),
-    };
-
-    let data: Vec<T> = match serde_json::from_slice(&content_decoded) {
-        Ok(inner) => inner,
-        Err(err) => return Err(ClientError::Json(err)),
-    };
-
-    Ok(data)
+/// Decode the documents in a collection file, ignoring its metadata.
+fn decode_documents<T: DeserializeOwned>(
+    format: &dyn Format,
+    content: &[u8],
+) -> Result<Vec<T>, ClientError> {
+    meta::decode_collection(format, content).map(|(_, documents)| documents)
 }
@@ -0,0 +1,93 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{trace, Client, ClientError, Collection};
+
+/// The largest file github's contents api returns the content of.
+///
+/// Larger collections are read through the Git Data API, which takes an extra request.
+pub const CONTENTS_API_LIMIT: usize = 1024 * 1024;
+
+/// The largest file github accepts in a commit.
+const GITHUB_FILE_LIMIT: usize = 100 * 1024 * 1024;
+
+/// Writes a client refuses to make, checked before anything is sent.
+///
+/// Every limit is off by default. A write breaking one fails with
+/// [`ClientError::TooLarge`] before it's sent, instead of the forge
+/// rejecting it after the whole collection was uploaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// the most bytes a single document may take serialized as JSON
+    pub max_document_size: Option<usize>,
+    /// the most bytes a collection's file may take
+    pub max_collection_size: Option<usize>,
+    /// the most documents a collection may hold
+    pub max_documents: Option<usize>,
+}
+
+impl Limits {
+    /// the largest files github accepts
+    pub fn github() -> Self {
+        Self {
+            max_collection_size: Some(GITHUB_FILE_LIMIT),
+            ..Default::default()
+        }
+    }
+}
+
+impl Client {
+    /// A copy of this client that refuses writes breaking `limits`, see [`ClientBuilder::limits`](crate::ClientBuilder::limits).
+    pub fn with_limits(&self, limits: Limits) -> Client {
+        self.derive(|inner| inner.limits = limits)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// Check the documents and their encoded `content` against the client's limits before writing.
+    pub(crate) fn check_limits(&self, content: &[u8]) -> Result<(), ClientError> {
+        let limits = &self.client.inner.limits;
+
+        if let Some(max) = limits.max_documents {
+            if self.inner.len() > max {
+                return Err(ClientError::TooLarge(format!(
+                    "collection '{}' would hold {} documents, more than {max}",
+                    self.name,
+                    self.inner.len()
+                )));
+            }
+        }
+
+        if let Some(max) = limits.max_collection_size {
+            if content.len() > max {
+                return Err(ClientError::TooLarge(format!(
+                    "collection '{}' would take {} bytes, more than {max}",
+                    self.name,
+                    content.len()
+                )));
+            }
+        }
+
+        if let Some(max) = limits.max_document_size {
+            for (position, document) in self.inner.iter().enumerate() {
+                let size = match serde_json::to_vec(document) {
+                    Ok(document) => document.len(),
+                    Err(err) => return Err(ClientError::Json(err)),
+                };
+
+                if size > max {
+                    return Err(ClientError::TooLarge(format!(
+                        "document {position} in collection '{}' takes {size} bytes, more than {max}",
+                        self.name
+                    )));
+                }
+            }
+        }
+
+        // reads slow down past the limit, worth knowing about before it happens
+        if content.len() > CONTENTS_API_LIMIT / 10 * 9 {
+            trace::approaching_limit(&self.name, content.len(), CONTENTS_API_LIMIT);
+        }
+
+        Ok(())
+    }
+}
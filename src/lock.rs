@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Client, ClientError, Format, Json, Operation};
+
+/// Who holds a [`Lock`], stored in its lock file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    /// a random id for the lease, so a holder can tell its own lease apart from a later one
+    pub owner: String,
+    pub acquired_at: DateTime<Utc>,
+    /// when other clients may take the lock over
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A lease on a named lock, see [`Client::lock`].
+///
+/// The lease isn't released when the lock is dropped, call
+/// [`release`](Self::release) when finished or it's held until it expires.
+#[derive(Debug)]
+pub struct Lock {
+    client: Client,
+    name: String,
+    path: String,
+    /// the version token of the lock file as this lease wrote it
+    sha: String,
+    holder: LockHolder,
+}
+
+impl Client {
+    /// Acquire the lock called `name` for `ttl`, for coordinating exclusive work between clients.
+    ///
+    /// The lock is a file, `<name>.lock.json`, created only if it doesn't
+    /// exist or holds an expired lease, so of several clients trying at once
+    /// exactly one succeeds. Fails with [`ClientError::Locked`] if another
+    /// client holds an unexpired lease.
+    pub async fn lock(&self, name: impl AsRef<str>, ttl: Duration) -> Result<Lock, ClientError> {
+        let name = name.as_ref().to_string();
+        let path = self.file_path(&format!("{name}.lock.json"));
+
+        let sha = match self.get_file(&path).await? {
+            Some(file) => {
+                let holder = decode_holder(&file.content)?;
+                if holder.expires_at > Utc::now() {
+                    return Err(ClientError::Locked(name));
+                }
+
+                Some(file.sha)
+            }
+            None => None,
+        };
+
+        let now = Utc::now();
+        let holder = LockHolder {
+            owner: format!("{:016x}", fastrand::u64(..)),
+            acquired_at: now,
+            expires_at: expiry(now, ttl),
+        };
+
+        let message = self.commit_message(&Operation::AcquireLock, Some(&name));
+        let result = self
+            .put_file(&path, &encode_holder(&holder)?, sha.as_deref(), &message)
+            .await;
+
+        match result {
+            Ok(commit) => Ok(Lock {
+                client: self.clone(),
+                name,
+                path,
+                sha: commit.sha,
+                holder,
+            }),
+            // someone else created or took over the lock first
+            Err(ClientError::Conflict) => Err(ClientError::Locked(name)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Lock {
+    /// the name the lock was acquired with
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// this lease, as stored in the lock file
+    pub fn holder(&self) -> &LockHolder {
+        &self.holder
+    }
+
+    /// whether the lease has run out, after which another client may take the lock
+    pub fn is_expired(&self) -> bool {
+        self.holder.expires_at <= Utc::now()
+    }
+
+    /// extend the lease to `ttl` from now
+    ///
+    /// fails with [`ClientError::Locked`] if the lease expired and another
+    /// client has taken the lock since.
+    pub async fn renew(&mut self, ttl: Duration) -> Result<(), ClientError> {
+        let holder = LockHolder {
+            expires_at: expiry(Utc::now(), ttl),
+            ..self.holder.clone()
+        };
+
+        let message = self
+            .client
+            .commit_message(&Operation::RenewLock, Some(&self.name));
+        let result = self
+            .client
+            .put_file(
+                &self.path,
+                &encode_holder(&holder)?,
+                Some(&self.sha),
+                &message,
+            )
+            .await;
+
+        match result {
+            Ok(commit) => {
+                self.sha = commit.sha;
+                self.holder = holder;
+                Ok(())
+            }
+            Err(ClientError::Conflict) => Err(ClientError::Locked(self.name.clone())),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// release the lock so other clients can acquire it
+    ///
+    /// the lock file is only deleted if it still holds this lease, if
+    /// another client has taken the lock since this does nothing.
+    pub async fn release(self) -> Result<(), ClientError> {
+        let message = self
+            .client
+            .commit_message(&Operation::ReleaseLock, Some(&self.name));
+
+        match self
+            .client
+            .delete_file(&self.path, &self.sha, &message)
+            .await
+        {
+            Ok(()) | Err(ClientError::Conflict) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn expiry(now: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    match chrono::Duration::from_std(ttl) {
+        Ok(ttl) => now
+            .checked_add_signed(ttl)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC),
+        Err(_) => DateTime::<Utc>::MAX_UTC,
+    }
+}
+
+fn encode_holder(holder: &LockHolder) -> Result<Vec<u8>, ClientError> {
+    match serde_json::to_value(holder) {
+        Ok(value) => Json.serialize(&value),
+        Err(err) => Err(ClientError::Json(err)),
+    }
+}
+
+fn decode_holder(content: &[u8]) -> Result<LockHolder, ClientError> {
+    let value: Value = Json.deserialize(content)?;
+
+    match serde_json::from_value(value) {
+        Ok(holder) => Ok(holder),
+        Err(err) => Err(ClientError::Json(err)),
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::ClientError;
+
+/// Extracts the key documents are matched by when merging, as JSON, failing if it doesn't serialize.
+pub(crate) type KeyFn<T> = Arc<dyn Fn(&T) -> Result<Value, ClientError> + Send + Sync>;
+
+/// A document's key and content as JSON, for comparing revisions.
+pub(crate) fn keyed<T: Serialize>(
+    documents: &[T],
+    key: &KeyFn<T>,
+) -> Result<Vec<(String, Value)>, ClientError> {
+    documents
+        .iter()
+        .map(|document| match serde_json::to_value(document) {
+            Ok(value) => Ok((key(document)?.to_string(), value)),
+            Err(err) => Err(ClientError::Json(err)),
+        })
+        .collect()
+}
+
+/// Merge our changes and theirs to the `base` revision of a collection, matching documents by key.
+///
+/// Changes to different documents are combined, the result keeps their
+/// order with our new documents at the end. A document both sides changed
+/// differently, or that one side changed and the other removed, is a
+/// [`ClientError::MergeConflict`].
+pub(crate) fn three_way_merge<T: Serialize>(
+    base: &[(String, Value)],
+    ours: Vec<T>,
+    theirs: Vec<T>,
+    key: &KeyFn<T>,
+) -> Result<Vec<T>, ClientError> {
+    let base: HashMap<&str, &Value> = base
+        .iter()
+        .map(|(key, value)| (key.as_str(), value))
+        .collect();
+
+    let our_keys = keyed(&ours, key)?;
+    let their_keys = keyed(&theirs, key)?;
+    let their_index: HashMap<&str, usize> = their_keys
+        .iter()
+        .enumerate()
+        .map(|(position, (key, _))| (key.as_str(), position))
+        .collect();
+
+    let mut ours: Vec<Option<T>> = ours.into_iter().map(Some).collect();
+    let our_index: HashMap<&str, usize> = our_keys
+        .iter()
+        .enumerate()
+        .map(|(position, (key, _))| (key.as_str(), position))
+        .collect();
+
+    let mut merged = Vec::with_capacity(theirs.len());
+    let mut conflicts = Vec::new();
+
+    for (document, (key, their_value)) in theirs.into_iter().zip(&their_keys) {
+        let base = base.get(key.as_str()).copied();
+
+        match (our_index.get(key.as_str()), base) {
+            (Some(&position), base) => {
+                let our_value = &our_keys[position].1;
+
+                if base == Some(our_value) || our_value == their_value {
+                    // we didn't change it, or made the same change
+                    merged.push(document);
+                } else if base == Some(their_value) {
+                    // only we changed it
+                    merged.extend(ours[position].take());
+                } else {
+                    conflicts.push(key.clone());
+                }
+            }
+            // they added it
+            (None, None) => merged.push(document),
+            // we removed it, and they didn't change it
+            (None, Some(base)) if base == their_value => {}
+            (None, Some(_)) => conflicts.push(key.clone()),
+        }
+    }
+
+    // documents they don't have, in our order
+    for (position, (key, our_value)) in our_keys.iter().enumerate() {
+        if their_index.contains_key(key.as_str()) {
+            continue;
+        }
+
+        match base.get(key.as_str()) {
+            // we added it
+            None => merged.extend(ours[position].take()),
+            // they removed it, and we didn't change it
+            Some(base) if *base == our_value => {}
+            Some(_) => conflicts.push(key.clone()),
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(merged)
+    } else {
+        Err(ClientError::MergeConflict(conflicts))
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Client;
+
+/// The git trailer the [`Operation::name`] of a commit is recorded in at the end of its message.
+pub(crate) const OPERATION_TRAILER: &str = "Operation";
+
+/// What a commit does, which the client's [`MessageFormatter`] turns into its message.
+///
+/// Serializes as an object tagged with the operation's [`name`](Self::name),
+/// like `{"operation": "migrate", "version": 3}`, for formatters writing
+/// machine readable commit messages.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Operation {
+    /// documents were added
+    Insert,
+    /// documents were added or replaced by key
+    Upsert,
+    /// every document was replaced
+    Overwrite,
+    /// documents were changed in place
+    Update,
+    /// a [`Record`](crate::Record) was added or replaced by its id
+    Save,
+    /// documents were removed
+    Delete,
+    /// a document was moved to another position
+    Move,
+    /// the documents were sorted
+    Sort,
+    /// expired documents were removed
+    PurgeExpired,
+    /// documents that didn't decode were fixed or removed
+    Repair,
+    /// data was imported, into a collection or the whole database
+    Import,
+    /// a collection was created
+    CreateCollection,
+    /// a collection was migrated to a new version
+    Migrate { version: u32 },
+    /// a collection was restored to how it was at a commit
+    Rollback { commit: String },
+    /// a file was attached to a collection
+    Attach { name: String },
+    /// an attachment was deleted
+    DeleteAttachment { name: String },
+    /// a collection's index file was rewritten
+    UpdateIndexes,
+    /// a collection's index file was removed
+    DropIndexes,
+    /// a collection's JSON Schema was set
+    SetSchema,
+    /// documents were moved to the trash
+    SoftDelete,
+    /// documents were restored from the trash
+    Restore,
+    /// old documents were removed from the trash
+    PurgeTrash,
+    /// every subcollection under a document was deleted
+    DeleteSubcollections { document: String },
+    /// an empty shard was removed from a sharded collection
+    RemoveShard,
+    /// buffered changes were written together
+    Flush { changes: usize },
+    /// a key was set in a key-value store
+    Set { key: String },
+    /// a key was removed from a key-value store
+    Unset { key: String },
+    /// a counter was changed
+    UpdateCounter,
+    /// a single document file was written
+    WriteDocument,
+    /// a lock was acquired
+    AcquireLock,
+    /// a lock's lease was extended
+    RenewLock,
+    /// a lock was released
+    ReleaseLock,
+    /// the path prefix directory was created
+    CreateDatabase,
+    /// fixtures were written
+    ApplyFixtures,
+    /// files were copied from another database
+    Replicate,
+    /// everything in a namespace was deleted
+    DropNamespace { namespace: String },
+    /// the history was squashed
+    Compact,
+}
+
+impl Operation {
+    /// a short name for the operation in snake case, like `"insert"` or `"purge_expired"`, which never changes
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Insert => "insert",
+            Self::Upsert => "upsert",
+            Self::Overwrite => "overwrite",
+            Self::Update => "update",
+            Self::Save => "save",
+            Self::Delete => "delete",
+            Self::Move => "move",
+            Self::Sort => "sort",
+            Self::PurgeExpired => "purge_expired",
+            Self::Repair => "repair",
+            Self::Import => "import",
+            Self::CreateCollection => "create_collection",
+            Self::Migrate { .. } => "migrate",
+            Self::Rollback { .. } => "rollback",
+            Self::Attach { .. } => "attach",
+            Self::DeleteAttachment { .. } => "delete_attachment",
+            Self::UpdateIndexes => "update_indexes",
+            Self::DropIndexes => "drop_indexes",
+            Self::SetSchema => "set_schema",
+            Self::SoftDelete => "soft_delete",
+            Self::Restore => "restore",
+            Self::PurgeTrash => "purge_trash",
+            Self::DeleteSubcollections { .. } => "delete_subcollections",
+            Self::RemoveShard => "remove_shard",
+            Self::Flush { .. } => "flush",
+            Self::Set { .. } => "set",
+            Self::Unset { .. } => "unset",
+            Self::UpdateCounter => "update_counter",
+            Self::WriteDocument => "write_document",
+            Self::AcquireLock => "acquire_lock",
+            Self::RenewLock => "renew_lock",
+            Self::ReleaseLock => "release_lock",
+            Self::CreateDatabase => "create_database",
+            Self::ApplyFixtures => "apply_fixtures",
+            Self::Replicate => "replicate",
+            Self::DropNamespace { .. } => "drop_namespace",
+            Self::Compact => "compact",
+        }
+    }
+}
+
+/// What else a [`MessageFormatter`] is told about a commit.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct MessageContext<'a> {
+    /// the collection, or other named file like a counter or lock, the commit changes, [`None`] for commits across the database
+    pub collection: Option<&'a str>,
+}
+
+/// Writes the message of every commit the client makes, see [`ClientBuilder::message_formatter`](crate::ClientBuilder::message_formatter).
+///
+/// The client ends every formatted message with an `Operation: <name>`
+/// trailer holding the operation's [`name`](Operation::name), which
+/// [`Client::audit_log`](crate::Client::audit_log) reads back. Messages given
+/// explicitly, like to [`Collection::insert_with`](crate::Collection::insert_with)
+/// or [`Transaction::commit`](crate::Transaction::commit), are used as they are.
+pub trait MessageFormatter: Debug + Send + Sync {
+    /// the commit message for `operation`
+    fn format(&self, operation: &Operation, context: &MessageContext<'_>) -> String;
+}
+
+/// The [`MessageFormatter`] clients use by default, which writes title case messages like `Migrate 'users' To Version 3`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultFormatter;
+
+impl MessageFormatter for DefaultFormatter {
+    fn format(&self, operation: &Operation, context: &MessageContext<'_>) -> String {
+        let collection = context.collection.unwrap_or_default();
+
+        match operation {
+            Operation::Insert => "Insert".to_string(),
+            Operation::Upsert => "Upsert".to_string(),
+            Operation::Overwrite => "Overwrite".to_string(),
+            Operation::Update => "Update".to_string(),
+            Operation::Save => "Save".to_string(),
+            Operation::Delete => "Remove".to_string(),
+            Operation::Move => "Move".to_string(),
+            Operation::Sort => "Sort".to_string(),
+            Operation::PurgeExpired => "Purge Expired Documents".to_string(),
+            Operation::Repair => format!("Repair Collection '{collection}'"),
+            Operation::Import => match context.collection {
+                Some(collection) => format!("Import Into '{collection}'"),
+                None => "Import Database".to_string(),
+            },
+            Operation::CreateCollection => format!("Creating Collection '{collection}'"),
+            Operation::Migrate { version } => {
+                format!("Migrate '{collection}' To Version {version}")
+            }
+            Operation::Rollback { commit } => format!("Rollback '{collection}' to {commit}"),
+            Operation::Attach { name } => format!("Attach '{name}' In '{collection}'"),
+            Operation::DeleteAttachment { name } => {
+                format!("Delete Attachment '{name}' In '{collection}'")
+            }
+            Operation::UpdateIndexes => format!("Update Indexes For '{collection}'"),
+            Operation::DropIndexes => format!("Drop Indexes For '{collection}'"),
+            Operation::SetSchema => format!("Set Schema For '{collection}'"),
+            Operation::SoftDelete => format!("Soft Delete From '{collection}'"),
+            Operation::Restore => format!("Restore To '{collection}'"),
+            Operation::PurgeTrash => format!("Purge Trash For '{collection}'"),
+            Operation::DeleteSubcollections { document } => {
+                format!("Delete Subcollections of '{collection}/{document}'")
+            }
+            Operation::RemoveShard => "Remove Shard".to_string(),
+            Operation::Flush { changes } => format!("Flush {changes} Changes"),
+            Operation::Set { key } => format!("Set '{key}'"),
+            Operation::Unset { key } => format!("Remove '{key}'"),
+            Operation::UpdateCounter => format!("Update Counter '{collection}'"),
+            Operation::WriteDocument => format!("Write Document '{collection}'"),
+            Operation::AcquireLock => format!("Acquire Lock '{collection}'"),
+            Operation::RenewLock => format!("Renew Lock '{collection}'"),
+            Operation::ReleaseLock => format!("Release Lock '{collection}'"),
+            Operation::CreateDatabase => "Create Database Directory".to_string(),
+            Operation::ApplyFixtures => "Apply Fixtures".to_string(),
+            Operation::Replicate => "Replicate".to_string(),
+            Operation::DropNamespace { namespace } => format!("Drop Namespace '{namespace}'"),
+            Operation::Compact => "Compact History".to_string(),
+        }
+    }
+}
+
+impl Client {
+    /// A copy of this client whose commit messages are written by `formatter`, see [`ClientBuilder::message_formatter`](crate::ClientBuilder::message_formatter).
+    pub fn with_message_formatter(&self, formatter: impl MessageFormatter + 'static) -> Client {
+        let formatter: std::sync::Arc<dyn MessageFormatter> = std::sync::Arc::new(formatter);
+
+        self.derive(|inner| inner.messages = formatter)
+    }
+
+    /// The commit message for `operation` on `collection`, or on the whole database, ending in its operation trailer.
+    pub(crate) fn commit_message(&self, operation: &Operation, collection: Option<&str>) -> String {
+        let message = self
+            .inner
+            .messages
+            .format(operation, &MessageContext { collection });
+
+        with_trailer(&message, OPERATION_TRAILER, operation.name())
+    }
+}
+
+/// `message` with the git trailer `key: value` added to the end, in the same paragraph as any trailers already there.
+pub(crate) fn with_trailer(message: &str, key: &str, value: &str) -> String {
+    let message = message.trim_end();
+
+    match message.lines().last() {
+        Some(last) if trailer(last).is_some() && message.contains("\n\n") => {
+            format!("{message}\n{key}: {value}")
+        }
+        _ => format!("{message}\n\n{key}: {value}"),
+    }
+}
+
+/// The value of the last `key` trailer in `message`, [`None`] if it has none.
+pub(crate) fn find_trailer<'a>(message: &'a str, key: &str) -> Option<&'a str> {
+    let (_, trailers) = message.trim_end().rsplit_once("\n\n")?;
+
+    trailers
+        .lines()
+        .rev()
+        .filter_map(trailer)
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value)
+}
+
+/// Split a line like `Operation: insert` into its key and value, [`None`] if it isn't a trailer.
+fn trailer(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(": ")?;
+    let is_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+    match is_key {
+        true => Some((key, value.trim())),
+        false => None,
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{Attachment, ClientError, Collection, Format};
+
+/// Metadata stored at the top of a collection file.
+///
+/// Collections are stored as `{ "meta": { ... }, "documents": [ ... ] }`.
+/// Files written before metadata existed are a bare array of documents,
+/// they're still read and get a header the next time they're written.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectionMeta {
+    /// the schema version, see [`Migrations`](crate::Migrations)
+    #[serde(default)]
+    pub version: u32,
+    /// when the collection was created, [`None`] for collections created before metadata was stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// the extension of the format the collection is stored in
+    #[serde(default)]
+    pub format: String,
+    /// the fields that were indexed when the collection was last written
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indexes: Vec<String>,
+    /// the files attached to each document, by primary key, see [`Collection::attach`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attachments: BTreeMap<String, Vec<Attachment>>,
+    /// when each document expires, by primary key, see [`Collection::save_expiring`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub expires: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl CollectionMeta {
+    /// The header for a collection created now.
+    pub(crate) fn new(format: &dyn Format, version: u32) -> Self {
+        Self {
+            version,
+            created_at: Some(Utc::now()),
+            format: format.extension().to_string(),
+            indexes: Vec::new(),
+            attachments: BTreeMap::new(),
+            expires: BTreeMap::new(),
+        }
+    }
+
+    /// The header for a legacy collection file that doesn't have one.
+    pub(crate) fn legacy(format: &dyn Format) -> Self {
+        Self {
+            format: format.extension().to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl<T> Collection<T> {
+    /// the metadata header as of the last sync
+    pub fn meta(&self) -> &CollectionMeta {
+        &self.meta
+    }
+}
+
+/// The contents of a collection file.
+#[derive(Deserialize)]
+struct Envelope<D> {
+    meta: CollectionMeta,
+    documents: D,
+}
+
+/// Decode a collection file, with or without a metadata header.
+///
+/// Returns [`None`] for the metadata of legacy files that are a bare array.
+pub(crate) fn decode_collection<T: DeserializeOwned>(
+    format: &dyn Format,
+    content: &[u8],
+) -> Result<(Option<CollectionMeta>, Vec<T>), ClientError> {
+    // a large collection parsed into a value first takes several times the memory of its documents
+    if let Some(json) = format.as_json(content) {
+        return decode_json_collection(json);
+    }
+
+    let (meta, documents) = decode_envelope(format, content)?;
+
+    match serde_json::from_value(documents) {
+        Ok(documents) => Ok((meta, documents)),
+        Err(err) => Err(ClientError::Json(err)),
+    }
+}
+
+/// Like [`decode_collection`], but each document is decoded separately so
+/// one malformed document doesn't fail the rest.
+pub(crate) fn decode_collection_lenient<T: DeserializeOwned>(
+    format: &dyn Format,
+    content: &[u8],
+) -> Result<(Option<CollectionMeta>, Vec<Decoded<T>>), ClientError> {
+    let (meta, documents) = decode_envelope(format, content)?;
+
+    let documents: Vec<Value> = match serde_json::from_value(documents) {
+        Ok(documents) => documents,
+        Err(err) => return Err(ClientError::Json(err)),
+    };
+
+    let documents = documents
+        .into_iter()
+        .map(|document| match T::deserialize(&document) {
+            Ok(document) => Ok(document),
+            Err(err) => Err((err, document)),
+        })
+        .collect();
+
+    Ok((meta, documents))
+}
+
+/// A document from [`decode_collection_lenient`], or why it couldn't be decoded and its raw value.
+pub(crate) type Decoded<T> = Result<T, (serde_json::Error, Value)>;
+
+/// Split a collection file into its metadata and its documents.
+fn decode_envelope(
+    format: &dyn Format,
+    content: &[u8],
+) -> Result<(Option<CollectionMeta>, Value), ClientError> {
+    match format.deserialize(content)? {
+        Value::Array(documents) => Ok((None, Value::Array(documents))),
+        value => match serde_json::from_value::<Envelope<Value>>(value) {
+            Ok(envelope) => Ok((Some(envelope.meta), envelope.documents)),
+            Err(err) => Err(ClientError::Json(err)),
+        },
+    }
+}
+
+/// Like [`decode_collection`], parsing the documents straight from JSON, which they may borrow from.
+pub(crate) fn decode_json_collection<'a, T: Deserialize<'a>>(
+    json: &'a [u8],
+) -> Result<(Option<CollectionMeta>, Vec<T>), ClientError> {
+    let legacy = json.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'[');
+
+    let decoded = match legacy {
+        true => serde_json::from_slice(json).map(|documents| (None, documents)),
+        false => serde_json::from_slice::<Envelope<Vec<T>>>(json)
+            .map(|envelope| (Some(envelope.meta), envelope.documents)),
+    };
+
+    match decoded {
+        Ok(decoded) => Ok(decoded),
+        Err(err) => Err(ClientError::Json(err)),
+    }
+}
+
+/// Encode a collection file with its metadata header.
+pub(crate) fn encode_collection<T: Serialize>(
+    format: &dyn Format,
+    meta: &CollectionMeta,
+    documents: &[T],
+) -> Result<Vec<u8>, ClientError> {
+    let documents = match serde_json::to_value(documents) {
+        Ok(documents) => documents,
+        Err(err) => return Err(ClientError::Json(err)),
+    };
+
+    format.serialize(&json!({ "meta": meta, "documents": documents }))
+}
@@ -0,0 +1,49 @@
+use std::{fmt::Debug, time::Duration};
+
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+/// Callbacks for exporting usage metrics, like to prometheus.
+///
+/// Every method does nothing by default, so implementations only need the
+/// ones they record. They're called inline with requests, so they should be
+/// quick (like incrementing a counter). Set with
+/// [`ClientBuilder::metrics`](crate::ClientBuilder::metrics).
+pub trait Metrics: Debug + Send + Sync {
+    /// called right before every attempt at a request is sent
+    fn request_started(&self, method: &Method, url: &Url) {
+        let _ = (method, url);
+    }
+
+    /// called when an attempt at a request finishes, successfully or not
+    fn request_finished(&self, request: &RequestMetrics) {
+        let _ = request;
+    }
+
+    /// called when a request failed and will be attempted again after `delay`
+    fn request_retried(&self, method: &Method, url: &Url, attempt: u32, delay: Duration) {
+        let _ = (method, url, attempt, delay);
+    }
+
+    /// called when a write to a collection lost a race with another writer
+    fn conflict(&self, collection: &str) {
+        let _ = collection;
+    }
+}
+
+/// A single attempt at a request, passed to [`Metrics::request_finished`].
+#[derive(Clone, Debug)]
+pub struct RequestMetrics {
+    pub method: Method,
+    pub url: Url,
+    /// the response's status, [`None`] if no response was received
+    pub status: Option<StatusCode>,
+    /// how long the request took, until the response headers were received
+    pub elapsed: Duration,
+    /// the size of the request body
+    pub bytes_sent: u64,
+    /// the size of the response body, if the server sent a `Content-Length`
+    pub bytes_received: Option<u64>,
+    /// which attempt this was, starting at 1
+    pub attempt: u32,
+}
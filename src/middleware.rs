@@ -0,0 +1,27 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+
+use crate::ClientError;
+
+/// Hooks run around every request the [`Client`](crate::Client) sends.
+///
+/// Middleware can add headers (like corporate proxy authentication), record
+/// tracing spans or metrics, or reject requests outright. Hooks run in the
+/// order they were added to the [`ClientBuilder`](crate::ClientBuilder) and
+/// run again for every retry.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Middleware: Debug + Send + Sync {
+    /// called right before a request is sent, returning an error cancels it
+    async fn on_request(&self, request: &mut Request) -> Result<(), ClientError> {
+        let _ = request;
+        Ok(())
+    }
+
+    /// called with every response before the client handles it
+    async fn on_response(&self, response: &Response) {
+        let _ = response;
+    }
+}
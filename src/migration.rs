@@ -0,0 +1,169 @@
+use std::fmt::{self, Debug};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    meta::{decode_collection, encode_collection},
+    Client, ClientError, Collection, CollectionMeta, FileChange, Format, Json, Operation,
+    MAX_CONFLICT_RETRIES,
+};
+
+type Migration = Box<dyn Fn(Value) -> Result<Value, ClientError> + Send + Sync>;
+
+/// An ordered list of migrations that upgrade a collection's documents.
+///
+/// A collection's schema version is the number of migrations that have been
+/// applied to it, recorded in its [`CollectionMeta`]. Collections without a
+/// version are version 0, so the first migration upgrades documents from
+/// version 0 to 1, the second from 1 to 2, and so on.
+///
+/// ```
+/// # use github_db::Migrations;
+/// let migrations = Migrations::new()
+///     // version 1 renamed `title` to `name`
+///     .migrate(|mut document| {
+///         if let Some(title) = document.as_object_mut().and_then(|d| d.remove("title")) {
+///             document["name"] = title;
+///         }
+///         document
+///     });
+/// ```
+#[derive(Default)]
+pub struct Migrations {
+    steps: Vec<Migration>,
+}
+
+impl Debug for Migrations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migrations")
+            .field("version", &self.version())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a migration that transforms each document as json
+    pub fn migrate<F>(mut self, migration: F) -> Self
+    where
+        F: Fn(Value) -> Value + Send + Sync + 'static,
+    {
+        self.steps
+            .push(Box::new(move |document| Ok(migration(document))));
+        self
+    }
+
+    /// add a migration that converts each document from type `A` to type `B`
+    pub fn migrate_typed<A, B, F>(mut self, migration: F) -> Self
+    where
+        A: DeserializeOwned,
+        B: Serialize,
+        F: Fn(A) -> B + Send + Sync + 'static,
+    {
+        self.steps.push(Box::new(move |document| {
+            let document = match serde_json::from_value(document) {
+                Ok(document) => document,
+                Err(err) => return Err(ClientError::Json(err)),
+            };
+
+            match serde_json::to_value(migration(document)) {
+                Ok(document) => Ok(document),
+                Err(err) => Err(ClientError::Json(err)),
+            }
+        }));
+        self
+    }
+
+    /// the schema version after every migration has been applied
+    pub fn version(&self) -> u32 {
+        self.steps.len() as u32
+    }
+
+    /// Upgrade every document from `version` to the latest version.
+    fn apply(&self, version: u32, documents: Vec<Value>) -> Result<Vec<Value>, ClientError> {
+        let mut migrated = Vec::with_capacity(documents.len());
+        for mut document in documents {
+            for step in &self.steps[version as usize..] {
+                document = step(document)?;
+            }
+            migrated.push(document);
+        }
+
+        Ok(migrated)
+    }
+}
+
+impl Client {
+    /// Return a reference to a collection, first upgrading it with any [`Migrations`] it hasn't had yet.
+    ///
+    /// The migrated documents and the new schema version are committed
+    /// together, so other clients never see one without the other. New
+    /// collections start at the latest version.
+    pub async fn collection_with_migrations<T: Serialize + DeserializeOwned>(
+        &self,
+        name: impl AsRef<str>,
+        migrations: &Migrations,
+    ) -> Result<Collection<T>, ClientError> {
+        let name = name.as_ref();
+        let format = Json;
+        let path = self.file_path(&format!("{name}.{}", format.extension()));
+        let mut retries = 0;
+
+        loop {
+            let file = self.get_file(&path).await?;
+            let (meta, documents) = match &file {
+                Some(file) => decode_collection::<Value>(&format, &file.content)?,
+                None => (None, Vec::new()),
+            };
+
+            let version = match &meta {
+                Some(meta) => meta.version,
+                None => 0,
+            };
+
+            if version > migrations.version() {
+                return Err(ClientError::SchemaVersion(version));
+            }
+
+            // nothing to migrate, legacy files are still rewritten to add a header
+            if meta.is_some() && version == migrations.version() {
+                break;
+            }
+
+            let documents = migrations.apply(version, documents)?;
+            let mut meta = match (&file, meta) {
+                (Some(_), Some(meta)) => meta,
+                (Some(_), None) => CollectionMeta::legacy(&format),
+                // new collections start on the latest version
+                (None, _) => CollectionMeta::new(&format, migrations.version()),
+            };
+            meta.version = migrations.version();
+
+            let operation = match &file {
+                Some(_) => Operation::Migrate {
+                    version: migrations.version(),
+                },
+                None => Operation::CreateCollection,
+            };
+            let message = self.commit_message(&operation, Some(name));
+
+            let changes = [FileChange {
+                path: path.clone(),
+                content: Some(encode_collection(&format, &meta, &documents)?),
+                expected_sha: file.map(|file| file.sha),
+            }];
+
+            match self.commit_files(&changes, &message).await {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(_) => break,
+            }
+        }
+
+        self.collection(name).await
+    }
+}
@@ -0,0 +1,479 @@
+//! An in-memory database for unit testing code that uses this library.
+//!
+//! [`MockClient`] dereferences to a real [`Client`], so collections,
+//! documents, and key-value stores behave exactly as they would against a
+//! repository, but every file lives in memory and nothing touches the
+//! network. Failures can be injected to test how your code handles
+//! conflicts, rate limits, and timeouts.
+//!
+//! ```
+//! # async fn run() -> Result<(), github_db::ClientError> {
+//! use github_db::mock::{Failure, MockClient};
+//!
+//! let mock = MockClient::new();
+//! let mut numbers = mock.collection::<u32>("numbers").await?;
+//!
+//! mock.fail_next_write(Failure::RateLimited);
+//! assert!(numbers.insert(1).await.is_err());
+//!
+//! numbers.insert(1).await?;
+//! assert_eq!(numbers.data().await?, &vec![1]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::Deref,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+
+use crate::{
+    provider::blob_sha, ChangeKind, ChangedFile, Client, ClientError, CommitInfo, CommitOptions,
+    DirEntry, EntryKind, FileChange, HistoryEntry, Provider, RemoteFile, RepositoryOptions,
+};
+
+/// A failure to inject into a [`MockClient`].
+#[derive(Clone, Debug)]
+pub enum Failure {
+    /// someone else wrote first, [`ClientError::Conflict`]
+    Conflict,
+    /// the rate limit was exceeded, [`ClientError::Status`] with a 429
+    RateLimited,
+    /// the request took too long, [`ClientError::Timeout`]
+    Timeout,
+    /// the forge responded with an unexpected status
+    Status(StatusCode),
+}
+
+impl Failure {
+    fn into_error(self) -> ClientError {
+        match self {
+            Failure::Conflict => ClientError::Conflict,
+            Failure::RateLimited => ClientError::Status(StatusCode::TOO_MANY_REQUESTS),
+            Failure::Timeout => ClientError::Timeout,
+            Failure::Status(status) => ClientError::Status(status),
+        }
+    }
+}
+
+/// A [`Client`] backed by a [`MockProvider`], see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct MockClient {
+    client: Client,
+    provider: MockProvider,
+}
+
+impl MockClient {
+    /// Create a client with an empty in-memory repository.
+    pub fn new() -> Self {
+        Self::with_provider(MockProvider::default())
+    }
+
+    /// Create a client on top of an existing [`MockProvider`], for sharing one repository between clients.
+    pub fn with_provider(provider: MockProvider) -> Self {
+        Self {
+            client: Client::with_provider(provider.clone(), None),
+            provider,
+        }
+    }
+
+    /// the provider holding this client's files
+    pub fn provider(&self) -> &MockProvider {
+        &self.provider
+    }
+
+    /// make the next operation of any kind fail
+    pub fn fail_next(&self, failure: Failure) {
+        self.provider.state().failures.push_back(failure);
+    }
+
+    /// make the next write, delete, or commit fail, reads still succeed
+    pub fn fail_next_write(&self, failure: Failure) {
+        self.provider.state().write_failures.push_back(failure);
+    }
+
+    /// commit the next write, delete, or commit but fail it with [`ClientError::Timeout`],
+    /// like a request that timed out after the forge applied it
+    pub fn lose_next_write_response(&self) {
+        self.provider.state().lost_responses += 1;
+    }
+
+    /// the raw content of a file, [`None`] if it doesn't exist
+    pub fn file(&self, path: impl AsRef<str>) -> Option<Vec<u8>> {
+        self.provider.state().files.get(path.as_ref()).cloned()
+    }
+
+    /// create or overwrite a file without recording a commit, like an edit made outside the client
+    pub fn set_file(&self, path: impl AsRef<str>, content: impl Into<Vec<u8>>) {
+        self.provider
+            .state()
+            .files
+            .insert(path.as_ref().to_string(), content.into());
+    }
+
+    /// every commit made so far, oldest first
+    pub fn commits(&self) -> Vec<HistoryEntry> {
+        self.provider
+            .state()
+            .commits
+            .iter()
+            .map(|commit| commit.entry.clone())
+            .collect()
+    }
+}
+
+impl Default for MockClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MockClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+/// A [`Provider`] that keeps files and commits in memory.
+///
+/// Clones share the same files.
+#[derive(Clone, Debug, Default)]
+pub struct MockProvider {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    files: BTreeMap<String, Vec<u8>>,
+    commits: Vec<Commit>,
+    failures: VecDeque<Failure>,
+    write_failures: VecDeque<Failure>,
+    /// how many of the next writes are committed but reported as timed out
+    lost_responses: usize,
+    /// git lfs objects by their sha256
+    lfs: BTreeMap<String, Vec<u8>>,
+}
+
+/// A commit and a snapshot of every file after it.
+#[derive(Debug)]
+struct Commit {
+    entry: HistoryEntry,
+    files: BTreeMap<String, Vec<u8>>,
+    changed: Vec<String>,
+}
+
+impl MockProvider {
+    fn state(&self) -> MutexGuard<'_, State> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl State {
+    /// Take the next injected failure for a read.
+    fn check_read(&mut self) -> Result<(), ClientError> {
+        match self.failures.pop_front() {
+            Some(failure) => Err(failure.into_error()),
+            None => Ok(()),
+        }
+    }
+
+    /// Take the next injected failure for a write.
+    fn check_write(&mut self) -> Result<(), ClientError> {
+        self.check_read()?;
+
+        match self.write_failures.pop_front() {
+            Some(failure) => Err(failure.into_error()),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail a write that was already committed if its response should be lost.
+    fn check_response(&mut self) -> Result<(), ClientError> {
+        match self.lost_responses {
+            0 => Ok(()),
+            _ => {
+                self.lost_responses -= 1;
+                Err(ClientError::Timeout)
+            }
+        }
+    }
+
+    /// The files as of a commit sha, or the current files if `reference` isn't a commit.
+    fn files_at(&self, reference: Option<&str>) -> &BTreeMap<String, Vec<u8>> {
+        self.commits
+            .iter()
+            .find(|commit| Some(commit.entry.sha.as_str()) == reference)
+            .map_or(&self.files, |commit| &commit.files)
+    }
+
+    fn check_sha(&self, path: &str, expected: Option<&str>) -> Result<(), ClientError> {
+        let current = self.files.get(path).map(|content| blob_sha(content));
+
+        if current.as_deref() == expected {
+            Ok(())
+        } else {
+            Err(ClientError::Conflict)
+        }
+    }
+
+    fn commit(&mut self, changed: Vec<String>, options: &CommitOptions) -> String {
+        let sha = blob_sha(format!("commit {}", self.commits.len()).as_bytes());
+
+        self.commits.push(Commit {
+            entry: HistoryEntry {
+                sha: sha.clone(),
+                message: options.message.clone(),
+                author: options
+                    .author
+                    .as_ref()
+                    .map(|author| author.name.clone())
+                    .unwrap_or_else(|| "mock".to_string()),
+                timestamp: Utc::now(),
+            },
+            files: self.files.clone(),
+            changed,
+        });
+
+        sha
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Provider for MockProvider {
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        let mut state = self.state();
+        state.check_read()?;
+
+        Ok(state
+            .files_at(reference)
+            .get(path)
+            .map(|content| RemoteFile {
+                content: content.clone(),
+                sha: blob_sha(content),
+            }))
+    }
+
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError> {
+        let mut state = self.state();
+        state.check_write()?;
+        state.check_sha(path, sha)?;
+
+        state.files.insert(path.to_string(), content.to_vec());
+        let commit = state.commit(vec![path.to_string()], options);
+        state.check_response()?;
+
+        Ok(CommitInfo {
+            commit: Some(commit),
+            timestamp: state.commits.last().map(|commit| commit.entry.timestamp),
+            ..CommitInfo::new(blob_sha(content))
+        })
+    }
+
+    async fn delete_file(
+        &self,
+        path: &str,
+        sha: &str,
+        options: &CommitOptions,
+    ) -> Result<(), ClientError> {
+        let mut state = self.state();
+        state.check_write()?;
+        state.check_sha(path, Some(sha))?;
+
+        state.files.remove(path);
+        state.commit(vec![path.to_string()], options);
+        state.check_response()?;
+
+        Ok(())
+    }
+
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError> {
+        let mut state = self.state();
+        state.check_read()?;
+
+        let prefix = match path.trim_end_matches('/') {
+            "" => String::new(),
+            path => format!("{path}/"),
+        };
+        let mut entries: BTreeMap<String, EntryKind> = BTreeMap::new();
+
+        for file in state.files_at(reference).keys() {
+            if let Some(rest) = file.strip_prefix(&prefix) {
+                match rest.split_once('/') {
+                    Some((dir, _)) => entries.insert(dir.to_string(), EntryKind::Dir),
+                    None => entries.insert(rest.to_string(), EntryKind::File),
+                };
+            }
+        }
+
+        Ok(entries
+            .into_iter()
+            .map(|(name, kind)| DirEntry { name, kind })
+            .collect())
+    }
+
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let mut state = self.state();
+        state.check_read()?;
+
+        // start from the referenced commit, or the newest one
+        let end = match reference.and_then(|reference| {
+            state
+                .commits
+                .iter()
+                .position(|commit| commit.entry.sha == reference)
+        }) {
+            Some(position) => position + 1,
+            None => state.commits.len(),
+        };
+
+        Ok(state.commits[..end]
+            .iter()
+            .rev()
+            .filter(|commit| {
+                commit
+                    .changed
+                    .iter()
+                    .any(|changed| is_within(changed, path))
+            })
+            .filter(|commit| until.is_none_or(|until| commit.entry.timestamp <= until))
+            .take(limit)
+            .map(|commit| commit.entry.clone())
+            .collect())
+    }
+
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        let mut state = self.state();
+        state.check_read()?;
+
+        let position = match state
+            .commits
+            .iter()
+            .position(|commit| commit.entry.sha == sha)
+        {
+            Some(position) => position,
+            None => return Err(ClientError::NotFound),
+        };
+
+        let empty = BTreeMap::new();
+        let before = match position {
+            0 => &empty,
+            _ => &state.commits[position - 1].files,
+        };
+        let commit = &state.commits[position];
+
+        Ok(commit
+            .changed
+            .iter()
+            .map(|path| ChangedFile {
+                path: path.clone(),
+                kind: match (before.contains_key(path), commit.files.contains_key(path)) {
+                    (false, _) => ChangeKind::Added,
+                    (true, false) => ChangeKind::Deleted,
+                    (true, true) => ChangeKind::Modified,
+                },
+                // files are only snapshotted, not diffed
+                additions: None,
+                deletions: None,
+            })
+            .collect())
+    }
+
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        let mut state = self.state();
+        state.check_write()?;
+
+        for change in changes {
+            state.check_sha(&change.path, change.expected_sha.as_deref())?;
+        }
+
+        for change in changes {
+            match &change.content {
+                Some(content) => state.files.insert(change.path.clone(), content.clone()),
+                None => state.files.remove(&change.path),
+            };
+        }
+
+        let changed = changes.iter().map(|change| change.path.clone()).collect();
+
+        let commit = state.commit(changed, options);
+        state.check_response()?;
+
+        Ok(commit)
+    }
+
+    /// the in-memory repository always exists
+    async fn ensure_repository(
+        &self,
+        _options: &RepositoryOptions,
+        _branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        self.state().check_write()?;
+
+        Ok(false)
+    }
+
+    async fn upload_lfs(&self, oid: &str, content: &[u8]) -> Result<(), ClientError> {
+        let mut state = self.state();
+        state.check_write()?;
+
+        state.lfs.insert(oid.to_string(), content.to_vec());
+
+        Ok(())
+    }
+
+    async fn download_lfs(&self, oid: &str, _size: u64) -> Result<Vec<u8>, ClientError> {
+        let mut state = self.state();
+        state.check_read()?;
+
+        match state.lfs.get(oid) {
+            Some(content) => Ok(content.clone()),
+            None => Err(ClientError::NotFound),
+        }
+    }
+}
+
+/// Whether `changed` is the file at `path` or inside the directory at `path`, as git filters commits by path.
+fn is_within(changed: &str, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+
+    path.is_empty()
+        || changed == path
+        || changed
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
@@ -0,0 +1,90 @@
+use crate::{Client, ClientError, EntryKind, FileChange, Operation};
+
+impl Client {
+    /// A copy of this client whose collections live in the `namespace` directory under the path prefix.
+    ///
+    /// Every collection, index, lock, and other file the copy uses is kept
+    /// in `<path_prefix><namespace>/`, so data for separate tenants can share
+    /// a repository without their names clashing. Namespaces can't contain
+    /// `/` or `.`.
+    pub fn namespace(&self, namespace: impl AsRef<str>) -> Result<Client, ClientError> {
+        let namespace = namespace.as_ref();
+        if !is_namespace(namespace) {
+            return Err(ClientError::InvalidKey(namespace.to_string()));
+        }
+
+        let path_prefix = self.file_path(&format!("{namespace}/"));
+
+        Ok(self.derive(|inner| inner.path_prefix = Some(path_prefix)))
+    }
+
+    /// the namespaces under the path prefix, sorted
+    ///
+    /// this is every directory with a valid namespace name, so key-value
+    /// stores and sharded collections next to the namespaces are listed too.
+    pub async fn list_namespaces(&self) -> Result<Vec<String>, ClientError> {
+        let root = self.file_path("");
+
+        let mut namespaces: Vec<String> = self
+            .list_dir(root.trim_end_matches('/'))
+            .await?
+            .into_iter()
+            .filter(|entry| entry.kind == EntryKind::Dir && is_namespace(&entry.name))
+            .map(|entry| entry.name)
+            .collect();
+        namespaces.sort();
+
+        Ok(namespaces)
+    }
+
+    /// delete everything in `namespace` in a single commit, returning how many files were deleted
+    pub async fn drop_namespace(&self, namespace: impl AsRef<str>) -> Result<usize, ClientError> {
+        let namespace = namespace.as_ref();
+        let client = self.namespace(namespace)?;
+
+        let operation = Operation::DropNamespace {
+            namespace: namespace.to_string(),
+        };
+        let message = self.commit_message(&operation, None);
+        self.delete_directory(&client, &message).await
+    }
+
+    /// Delete every file under `directory`'s path prefix in a single commit, returning how many there were.
+    pub(crate) async fn delete_directory(
+        &self,
+        directory: &Client,
+        message: &str,
+    ) -> Result<usize, ClientError> {
+        let paths: Vec<String> = directory
+            .list_files()
+            .await?
+            .iter()
+            .map(|path| directory.file_path(path))
+            .collect();
+        if paths.is_empty() {
+            return Ok(0);
+        }
+
+        let changes: Vec<FileChange> = paths
+            .iter()
+            .zip(self.get_files(&paths).await?)
+            .filter_map(|(path, file)| {
+                Some(FileChange {
+                    path: path.clone(),
+                    content: None,
+                    expected_sha: Some(file?.sha),
+                })
+            })
+            .collect();
+
+        // clears the offline snapshot of every deleted file
+        self.commit_files(&changes, message).await?;
+
+        Ok(changes.len())
+    }
+}
+
+/// Whether `name` can be a namespace, which keeps them apart from attachment directories and the like.
+fn is_namespace(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '.'])
+}
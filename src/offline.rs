@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{Client, ClientError, Collection, RemoteFile};
+
+/// The file in a path's snapshot directory naming its newest snapshot.
+const HEAD: &str = "HEAD";
+
+/// The directory snapshots of the default branch are kept in, which can't be a branch name since they can't start with `.`.
+const DEFAULT_BRANCH: &str = ".default";
+
+/// Snapshots of every file a client reads or writes, kept on disk to read from when the forge can't be reached.
+///
+/// Each file gets a directory under the cache's, at
+/// `<owner>/<repo>/<branch>/<path>` (just `<branch>/<path>` for clients
+/// with a custom provider), holding its content keyed by sha and a `HEAD`
+/// file recording which sha is newest and when it was read. Only the newest
+/// snapshot is kept. Failing to write a snapshot doesn't fail the read that
+/// made it.
+#[derive(Debug)]
+pub(crate) struct OfflineCache {
+    dir: PathBuf,
+    /// the paths last read from a snapshot, and when it was taken
+    stale: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+/// The newest snapshot of a file.
+#[derive(Serialize, Deserialize)]
+struct Head {
+    /// [`None`] if the file didn't exist
+    sha: Option<String>,
+    cached_at: DateTime<Utc>,
+}
+
+impl OfflineCache {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            stale: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record `path`'s current content, [`None`] if it doesn't exist, so it's no longer stale.
+    pub(crate) fn store(&self, path: &str, file: Option<&RemoteFile>) {
+        self.stale().remove(path);
+
+        if let Some(dir) = self.snapshot_dir(path) {
+            // a snapshot that can't be written only costs resilience, not the read
+            let _ = write_snapshot(&dir, file);
+        }
+    }
+
+    /// The newest snapshot of `path`, marking it stale, or the original error if there isn't one.
+    pub(crate) fn fall_back(
+        &self,
+        path: &str,
+        err: ClientError,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        let snapshot = match self.snapshot_dir(path) {
+            Some(dir) => read_snapshot(&dir),
+            None => None,
+        };
+
+        match snapshot {
+            Some((head, file)) => {
+                self.stale().insert(path.to_string(), head.cached_at);
+                Ok(file)
+            }
+            None => Err(err),
+        }
+    }
+
+    /// When the snapshot `path` was last read from was taken, [`None`] if it was read from the forge.
+    pub(crate) fn stale_since(&self, path: &str) -> Option<DateTime<Utc>> {
+        self.stale().get(path).copied()
+    }
+
+    /// The directory `path`'s snapshots are kept in, [`None`] for paths that would leave the cache.
+    fn snapshot_dir(&self, path: &str) -> Option<PathBuf> {
+        let relative = Path::new(path);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return None;
+        }
+
+        Some(self.dir.join(relative))
+    }
+
+    fn stale(&self) -> std::sync::MutexGuard<'_, HashMap<String, DateTime<Utc>>> {
+        match self.stale.lock() {
+            Ok(stale) => stale,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Replace the snapshot in `dir` with `file`.
+fn write_snapshot(dir: &Path, file: Option<&RemoteFile>) -> Result<(), ClientError> {
+    if let Err(err) = fs::create_dir_all(dir) {
+        return Err(ClientError::Io(err));
+    }
+
+    let head = Head {
+        sha: file.map(|file| file.sha.clone()),
+        cached_at: Utc::now(),
+    };
+
+    if let Some(file) = file {
+        let path = dir.join(&file.sha);
+        if !path.exists() {
+            if let Err(err) = fs::write(&path, &file.content) {
+                return Err(ClientError::Io(err));
+            }
+        }
+    }
+
+    let content = match serde_json::to_vec(&head) {
+        Ok(content) => content,
+        Err(err) => return Err(ClientError::Json(err)),
+    };
+    if let Err(err) = fs::write(dir.join(HEAD), content) {
+        return Err(ClientError::Io(err));
+    }
+
+    // older snapshots are never read again
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => return Err(ClientError::Io(err)),
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let keep = name == HEAD || Some(name.to_string_lossy().as_ref()) == head.sha.as_deref();
+
+        if !keep && entry.path().is_file() {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+/// The newest snapshot in `dir`, [`None`] if there isn't one.
+fn read_snapshot(dir: &Path) -> Option<(Head, Option<RemoteFile>)> {
+    let head: Head = serde_json::from_slice(&fs::read(dir.join(HEAD)).ok()?).ok()?;
+
+    let file = match &head.sha {
+        Some(sha) => Some(RemoteFile {
+            content: fs::read(dir.join(sha)).ok()?,
+            sha: sha.clone(),
+        }),
+        None => None,
+    };
+
+    Some((head, file))
+}
+
+/// Whether a failed read means the forge couldn't be reached, rather than that it refused the request.
+pub(crate) fn is_unreachable(err: &ClientError) -> bool {
+    match err.without_context() {
+        ClientError::Timeout => true,
+        ClientError::Http(err) => err.status().is_none(),
+        err => err.status().is_some_and(|status| status.is_server_error()),
+    }
+}
+
+impl Client {
+    /// A copy of this client that keeps snapshots of the files it reads in `dir`, see [`ClientBuilder::offline_cache`](crate::ClientBuilder::offline_cache).
+    pub fn with_offline_cache(&self, dir: impl Into<PathBuf>) -> Client {
+        let cache = Arc::new(OfflineCache::new(dir.into()));
+
+        self.derive(|inner| inner.offline = Some(cache))
+    }
+
+    /// Update the snapshot of a file this client just wrote, or deleted if `file` is [`None`].
+    pub(crate) fn remember_write(&self, path: &str, file: Option<&RemoteFile>) {
+        // dry runs and pull requests don't change the branch reads come from
+        if self.inner.dry_run.is_some() || self.inner.pull_request.is_some() {
+            return;
+        }
+
+        if let Some(cache) = &self.inner.offline {
+            cache.store(&self.snapshot_path(path), file);
+        }
+    }
+
+    /// Where the snapshots of `path` are kept in an offline cache, apart from other repositories' and branches'.
+    pub(crate) fn snapshot_path(&self, path: &str) -> String {
+        let branch = match &self.inner.branch {
+            Some(branch) => escape(branch),
+            None => DEFAULT_BRANCH.to_string(),
+        };
+
+        match &self.inner.repository {
+            Some((owner, repo)) => format!("{}/{}/{branch}/{path}", escape(owner), escape(repo)),
+            None => format!("{branch}/{path}"),
+        }
+    }
+}
+
+/// `name` as a single path component, with `/` (like in a branch or gitlab group) escaped so it can't nest.
+fn escape(name: &str) -> String {
+    name.replace('%', "%25").replace('/', "%2F")
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// when the snapshot the collection was last synced from was taken, [`None`] if it was synced from the forge
+    ///
+    /// only clients with an [offline cache](crate::ClientBuilder::offline_cache)
+    /// fall back to snapshots, when the forge can't be reached.
+    pub fn stale_since(&self) -> Option<DateTime<Utc>> {
+        match &self.client.inner.offline {
+            Some(cache) => cache.stale_since(&self.client.snapshot_path(&self.path)),
+            None => None,
+        }
+    }
+
+    /// whether the collection was last synced from an offline snapshot, see [`stale_since`](Self::stale_since)
+    pub fn is_stale(&self) -> bool {
+        self.stale_since().is_some()
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{ClientError, Collection, CommitInfo, Operation};
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// insert `data` at `index`, shifting the documents after it back
+    ///
+    /// an `index` past the end pushes the document. if another writer
+    /// changed the collection in between, `data` is inserted at the same
+    /// index of their version. fails with [`ClientError::Unsupported`] for
+    /// collections kept [sorted](Self::sort_by).
+    pub async fn insert_at(&mut self, index: usize, data: T) -> Result<CommitInfo, ClientError> {
+        self.check_unsorted()?;
+
+        let message = self.message(&Operation::Insert);
+        let mut data = data;
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+
+            let sha = self.sha.clone();
+            let index = index.min(self.inner.len());
+            self.inner.insert(index, data);
+
+            match self.write_ordered(&Operation::Insert, &message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => {
+                    retries += 1;
+                    data = self.inner.remove(index);
+                }
+                Err(err) => {
+                    // nothing was committed, so take the document back out
+                    if self.sha == sha {
+                        self.inner.remove(index);
+                    }
+                    return Err(err);
+                }
+                Ok(commit) => return Ok(commit),
+            }
+        }
+    }
+
+    /// insert `data` before every other document
+    pub async fn push_front(&mut self, data: T) -> Result<CommitInfo, ClientError> {
+        self.insert_at(0, data).await
+    }
+
+    /// move the document at `from` to `to`, shifting the documents in between
+    ///
+    /// a `to` past the end moves the document to the end. in a collection
+    /// with a [key](Self::with_key), the same document is moved even if
+    /// another writer changed the collection in between. fails with
+    /// [`ClientError::NotFound`] if there's no document at `from`.
+    pub async fn move_document(
+        &mut self,
+        from: usize,
+        to: usize,
+    ) -> Result<CommitInfo, ClientError> {
+        self.check_unsorted()?;
+
+        self.update().await?;
+        let moved = match (&self.key, self.inner.get(from)) {
+            (_, None) => return Err(ClientError::NotFound),
+            (Some(key), Some(document)) => Some(key(document)?),
+            (None, Some(_)) => None,
+        };
+
+        let message = self.message(&Operation::Move);
+        let mut retries = 0;
+
+        loop {
+            let from = match (&self.key, &moved) {
+                (Some(key), Some(moved)) => {
+                    let mut position = None;
+                    for (i, document) in self.inner.iter().enumerate() {
+                        if key(document)? == *moved {
+                            position = Some(i);
+                            break;
+                        }
+                    }
+                    position
+                }
+                _ => Some(from).filter(|from| *from < self.inner.len()),
+            };
+            let from = match from {
+                Some(from) => from,
+                None => return Err(ClientError::NotFound),
+            };
+
+            let sha = self.sha.clone();
+            let document = self.inner.remove(from);
+            let to = to.min(self.inner.len());
+            self.inner.insert(to, document);
+
+            match self.write_ordered(&Operation::Move, &message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => {
+                    retries += 1;
+                    self.update().await?;
+                }
+                Err(err) => {
+                    // nothing was committed, so move the document back
+                    if self.sha == sha {
+                        let document = self.inner.remove(to);
+                        self.inner.insert(from, document);
+                    }
+                    return Err(err);
+                }
+                Ok(commit) => return Ok(commit),
+            }
+        }
+    }
+
+    /// Positions don't last in a collection that's sorted on every write.
+    fn check_unsorted(&self) -> Result<(), ClientError> {
+        match self.order {
+            Some(_) => Err(ClientError::Unsupported("positions in a sorted collection")),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{meta, ClientError, Collection};
+
+impl<T> Collection<T> {
+    /// fetch the documents deserialized as `P` instead of the collection's document type
+    ///
+    /// `P` is usually a struct with only the fields a reader needs, the
+    /// rest of each document is skipped. like
+    /// [`data_borrowed`](Self::data_borrowed) it reads the file as stored
+    /// without syncing the collection, so expired documents that haven't been
+    /// purged are included.
+    pub async fn data_as<P: DeserializeOwned>(&self) -> Result<Vec<P>, ClientError> {
+        let file = match self.client.get_file(&self.path).await? {
+            Some(file) => file,
+            None => return Err(ClientError::NoContent),
+        };
+
+        meta::decode_collection(self.format.as_ref(), &file.content).map(|(_, documents)| documents)
+    }
+
+    /// fetch the documents as raw JSON values, see [`data_as`](Self::data_as)
+    pub async fn data_values(&self) -> Result<Vec<Value>, ClientError> {
+        self.data_as().await
+    }
+}
@@ -0,0 +1,567 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::{
+    contents_commit, decode_content, json_str, parse_changed_file, parse_history_entry,
+    ChangedFile, CommitInfo, CommitOptions, DirEntry, EntryKind, FileChange, Polled, Provider,
+    PullRequestOptions, RemoteFile, RepositoryOptions,
+};
+#[cfg(feature = "lfs")]
+use crate::lfs::{self, Pointer};
+use crate::{
+    http::{check_write_status, join_url, Http},
+    ClientError, HistoryEntry,
+};
+
+/// The most commits gitea will return in a single page by default.
+const MAX_PER_PAGE: usize = 50;
+
+/// What gitea says when a write lost a race with another writer.
+const CONFLICT_MESSAGES: &[&str] = &["sha does not match", "already exists"];
+
+/// Gitea accepts a token with any username, but not without one.
+#[cfg(feature = "lfs")]
+const LFS_USERNAME: &str = "oauth2";
+
+/// Stores files through the Gitea (and Forgejo) contents API.
+#[derive(Debug)]
+pub(crate) struct Gitea {
+    http: Http,
+    host: Url,
+    owner: String,
+    repo: String,
+    /// `/api/v1/repos/{owner}/{repo}` in the API, built once since nearly every request is under it
+    repo_base: Url,
+}
+
+impl Gitea {
+    pub(crate) fn new(http: Http, host: Url, owner: String, repo: String) -> Self {
+        let repo_base = join_url(&host, ["api", "v1", "repos", owner.as_str(), repo.as_str()]);
+
+        Self {
+            http,
+            host,
+            owner,
+            repo,
+            repo_base,
+        }
+    }
+
+    /// The batch API of the repository's LFS server.
+    #[cfg(feature = "lfs")]
+    fn lfs_url(&self) -> Url {
+        lfs::batch_url(&self.host, &format!("{}/{}", self.owner, self.repo))
+    }
+
+    /// A url under `/api/v1/repos/{owner}/{repo}/` in the API.
+    fn repo_url(&self, path: &str) -> Url {
+        join_url(
+            &self.repo_base,
+            path.split('/').filter(|segment| !segment.is_empty()),
+        )
+    }
+
+    fn contents_url(&self, path: &str, reference: Option<&str>) -> Url {
+        let mut url = join_url(
+            &self.repo_base,
+            std::iter::once("contents")
+                .chain(path.split('/').filter(|segment| !segment.is_empty())),
+        );
+
+        if let Some(reference) = reference {
+            url.query_pairs_mut().append_pair("ref", reference);
+        }
+
+        url
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Provider for Gitea {
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        let json = match self
+            .http
+            .request_json_optional(Method::GET, self.contents_url(path, reference), None)
+            .await?
+        {
+            Some(json) => json,
+            None => return Ok(None),
+        };
+
+        contents_file(&json).map(Some)
+    }
+
+    async fn poll_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+        sha: &str,
+    ) -> Result<Polled, ClientError> {
+        match self
+            .http
+            .poll_json(self.contents_url(path, reference), sha, |json| {
+                json["sha"].as_str()
+            })
+            .await?
+        {
+            Some(Some(json)) => contents_file(&json).map(|file| Polled::Changed(Some(file))),
+            Some(None) => Ok(Polled::Changed(None)),
+            None => Ok(Polled::Unchanged),
+        }
+    }
+
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError> {
+        let mut request_body = commit_body(options);
+        request_body["content"] = json!(base64::encode(content));
+
+        // gitea creates with POST and updates with PUT
+        let method = match sha {
+            Some(sha) => {
+                request_body["sha"] = json!(sha);
+                Method::PUT
+            }
+            None => Method::POST,
+        };
+
+        let response = self
+            .http
+            .send(method, self.contents_url(path, None), Some(&request_body))
+            .await?;
+        let response = check_write_status(
+            response,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            CONFLICT_MESSAGES,
+        )
+        .await?;
+
+        let response: Value = match response.json().await {
+            Ok(response) => response,
+            Err(e) => return Err(ClientError::Http(e)),
+        };
+
+        contents_commit(&response)
+    }
+
+    async fn delete_file(
+        &self,
+        path: &str,
+        sha: &str,
+        options: &CommitOptions,
+    ) -> Result<(), ClientError> {
+        let mut request_body = commit_body(options);
+        request_body["sha"] = json!(sha);
+
+        let response = self
+            .http
+            .send(
+                Method::DELETE,
+                self.contents_url(path, None),
+                Some(&request_body),
+            )
+            .await?;
+
+        check_write_status(
+            response,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            CONFLICT_MESSAGES,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError> {
+        let entries = match self
+            .http
+            .request_json_optional(Method::GET, self.contents_url(path, reference), None)
+            .await?
+        {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => return Err(ClientError::NoContent),
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                Some(DirEntry {
+                    name: entry["name"].as_str()?.to_string(),
+                    kind: match entry["type"].as_str() {
+                        Some("file") => EntryKind::File,
+                        Some("dir") => EntryKind::Dir,
+                        _ => EntryKind::Other,
+                    },
+                })
+            })
+            .collect())
+    }
+
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let mut entries = Vec::new();
+        let mut page = 1;
+
+        while entries.len() < limit {
+            let per_page = (limit - entries.len()).min(MAX_PER_PAGE);
+
+            let mut url = self.repo_url("commits");
+            url.query_pairs_mut()
+                .append_pair("path", path)
+                .append_pair("limit", &per_page.to_string())
+                .append_pair("page", &page.to_string())
+                .append_pair("stat", "false");
+            if let Some(until) = until {
+                url.query_pairs_mut()
+                    .append_pair("until", &until.to_rfc3339());
+            }
+            if let Some(reference) = reference {
+                url.query_pairs_mut().append_pair("sha", reference);
+            }
+
+            let commits = match self.http.request_json(Method::GET, url, None).await? {
+                Value::Array(commits) => commits,
+                _ => return Err(ClientError::NoContent),
+            };
+            let count = commits.len();
+
+            for commit in commits {
+                entries.push(parse_history_entry(&commit)?);
+            }
+
+            if count < per_page {
+                break;
+            }
+
+            page += 1;
+        }
+
+        entries.truncate(limit);
+
+        Ok(entries)
+    }
+
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        let mut url = self.repo_url(&format!("git/commits/{sha}"));
+        url.query_pairs_mut()
+            .append_pair("stat", "false")
+            .append_pair("files", "true");
+
+        let commit = self.http.request_json(Method::GET, url, None).await?;
+
+        // gitea only reports how each file changed, not by how many lines
+        match commit["files"].as_array() {
+            Some(files) => files.iter().map(parse_changed_file).collect(),
+            None => Err(ClientError::NoContent),
+        }
+    }
+
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        if changes.is_empty() {
+            let branch = match &options.branch {
+                Some(branch) => branch.clone(),
+                None => {
+                    let repository = self
+                        .http
+                        .request_json(Method::GET, self.repo_url(""), None)
+                        .await?;
+                    json_str(&repository["default_branch"])?
+                }
+            };
+
+            let branch = self
+                .http
+                .request_json(
+                    Method::GET,
+                    self.repo_url(&format!("branches/{branch}")),
+                    None,
+                )
+                .await?;
+
+            return json_str(&branch["commit"]["id"]);
+        }
+
+        let mut request_body = commit_body(options);
+
+        // gitea checks every sha and applies the changes in one commit
+        let files: Vec<Value> = changes
+            .iter()
+            .map(|change| {
+                let mut file = json!({ "path": change.path });
+
+                match (&change.content, &change.expected_sha) {
+                    (Some(content), expected_sha) => {
+                        file["operation"] = json!(match expected_sha {
+                            Some(_) => "update",
+                            None => "create",
+                        });
+                        file["content"] = json!(base64::encode(content));
+                    }
+                    (None, _) => file["operation"] = json!("delete"),
+                }
+                if let Some(sha) = &change.expected_sha {
+                    file["sha"] = json!(sha);
+                }
+
+                file
+            })
+            .collect();
+        request_body["files"] = json!(files);
+
+        let response = self
+            .http
+            .send(Method::POST, self.repo_url("contents"), Some(&request_body))
+            .await?;
+        let response = check_write_status(
+            response,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            CONFLICT_MESSAGES,
+        )
+        .await?;
+
+        match response.json::<Value>().await {
+            Ok(commit) => json_str(&commit["commit"]["sha"]),
+            Err(e) => Err(ClientError::Http(e)),
+        }
+    }
+
+    async fn ensure_repository(
+        &self,
+        options: &RepositoryOptions,
+        branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        let mut created = false;
+
+        let repository = match self
+            .http
+            .request_json_optional(Method::GET, self.repo_url(""), None)
+            .await?
+        {
+            Some(repository) => repository,
+            None => {
+                // repositories are created differently for users and organizations
+                let user = self
+                    .http
+                    .request_json(
+                        Method::GET,
+                        join_url(&self.host, ["api", "v1", "user"]),
+                        None,
+                    )
+                    .await?;
+                let url = if json_str(&user["login"])?.eq_ignore_ascii_case(&self.owner) {
+                    join_url(&self.host, ["api", "v1", "user", "repos"])
+                } else {
+                    join_url(
+                        &self.host,
+                        ["api", "v1", "orgs", self.owner.as_str(), "repos"],
+                    )
+                };
+
+                let mut body = json!({
+                    "name": self.repo,
+                    "private": options.private,
+                    // the initial commit creates the default branch
+                    "auto_init": true,
+                });
+                if let Some(description) = &options.description {
+                    body["description"] = json!(description);
+                }
+                if let Some(branch) = branch {
+                    body["default_branch"] = json!(branch);
+                }
+
+                created = true;
+                self.http
+                    .request_json(Method::POST, url, Some(&body))
+                    .await?
+            }
+        };
+
+        let default_branch = json_str(&repository["default_branch"])?;
+        let branch = match branch {
+            Some(branch) if branch != default_branch => branch,
+            _ => return Ok(created),
+        };
+
+        if self
+            .http
+            .request_json_optional(
+                Method::GET,
+                self.repo_url(&format!("branches/{branch}")),
+                None,
+            )
+            .await?
+            .is_some()
+        {
+            return Ok(created);
+        }
+
+        self.http
+            .request_json(
+                Method::POST,
+                self.repo_url("branches"),
+                Some(&json!({
+                    "new_branch_name": branch,
+                    "old_branch_name": default_branch,
+                })),
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn create_branch(&self, branch: &str, from: Option<&str>) -> Result<(), ClientError> {
+        let mut body = json!({ "new_branch_name": branch });
+        if let Some(from) = from {
+            body["old_branch_name"] = json!(from);
+        }
+
+        self.http
+            .request_json(Method::POST, self.repo_url("branches"), Some(&body))
+            .await
+            .map(|_| ())
+    }
+
+    async fn open_pull_request(
+        &self,
+        options: &PullRequestOptions,
+        head: &str,
+        base: Option<&str>,
+    ) -> Result<u64, ClientError> {
+        let base = match base {
+            Some(base) => base.to_string(),
+            None => {
+                let repository = self
+                    .http
+                    .request_json(Method::GET, self.repo_url(""), None)
+                    .await?;
+                json_str(&repository["default_branch"])?
+            }
+        };
+
+        let mut body = json!({
+            "title": options.title,
+            "head": head,
+            "base": base,
+        });
+        if let Some(description) = &options.body {
+            body["body"] = json!(description);
+        }
+
+        // gitea only takes the ids of existing labels
+        if !options.labels.is_empty() {
+            let labels = match self
+                .http
+                .request_json(Method::GET, self.repo_url("labels"), None)
+                .await?
+            {
+                Value::Array(labels) => labels,
+                _ => return Err(ClientError::NoContent),
+            };
+
+            let mut ids = Vec::with_capacity(options.labels.len());
+            for name in &options.labels {
+                match labels.iter().find(|label| label["name"] == json!(name)) {
+                    Some(label) => ids.push(label["id"].clone()),
+                    None => return Err(ClientError::InvalidKey(name.clone())),
+                }
+            }
+            body["labels"] = json!(ids);
+        }
+
+        let pull_request = self
+            .http
+            .request_json(Method::POST, self.repo_url("pulls"), Some(&body))
+            .await?;
+        let number = match pull_request["number"].as_u64() {
+            Some(number) => number,
+            None => return Err(ClientError::NoContent),
+        };
+
+        if !options.reviewers.is_empty() {
+            self.http
+                .request_json(
+                    Method::POST,
+                    self.repo_url(&format!("pulls/{number}/requested_reviewers")),
+                    Some(&json!({ "reviewers": options.reviewers })),
+                )
+                .await?;
+        }
+
+        Ok(number)
+    }
+
+    #[cfg(feature = "lfs")]
+    async fn upload_lfs(&self, oid: &str, content: &[u8]) -> Result<(), ClientError> {
+        let pointer = Pointer {
+            oid: oid.to_string(),
+            size: content.len() as u64,
+        };
+
+        lfs::upload(&self.http, self.lfs_url(), LFS_USERNAME, &pointer, content).await
+    }
+
+    #[cfg(feature = "lfs")]
+    async fn download_lfs(&self, oid: &str, size: u64) -> Result<Vec<u8>, ClientError> {
+        let pointer = Pointer {
+            oid: oid.to_string(),
+            size,
+        };
+
+        lfs::download(&self.http, self.lfs_url(), LFS_USERNAME, &pointer).await
+    }
+}
+
+/// The file in a response from the contents API.
+fn contents_file(json: &Value) -> Result<RemoteFile, ClientError> {
+    let content = match json.get("content") {
+        Some(content) => decode_content(content)?,
+        None => return Err(ClientError::NoContent),
+    };
+
+    let sha = match json.get("sha").and_then(Value::as_str) {
+        Some(sha) => sha.to_string(),
+        None => return Err(ClientError::NoSha),
+    };
+
+    Ok(RemoteFile { content, sha })
+}
+
+/// The message, branch, and author shared by every contents API write.
+fn commit_body(options: &CommitOptions) -> Value {
+    let mut body = json!({ "message": options.message });
+
+    if let Some(branch) = &options.branch {
+        body["branch"] = json!(branch);
+    }
+    if let Some(author) = &options.author {
+        body["author"] = json!(author);
+    }
+
+    body
+}
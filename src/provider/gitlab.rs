@@ -0,0 +1,633 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{future, lock::Mutex, TryStreamExt};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+use url::Url;
+
+use super::{
+    decode_content, json_str, parse_timestamp, ChangeKind, ChangedFile, CommitInfo, CommitOptions,
+    DirEntries, DirEntry, EntryKind, FileChange, Polled, Provider, PullRequestOptions, RemoteFile,
+    RepositoryOptions,
+};
+#[cfg(feature = "lfs")]
+use crate::lfs::{self, Pointer};
+use crate::{
+    http::{check_status, check_write_status, join_url, Http},
+    ClientError, HistoryEntry,
+};
+
+/// The most items gitlab will return in a single page.
+const MAX_PER_PAGE: usize = 100;
+
+/// What gitlab says when a write lost a race with another writer.
+const CONFLICT_MESSAGES: &[&str] = &["has changed since", "already exists", "doesn't exist"];
+
+/// The username gitlab expects alongside an OAuth or personal access token.
+#[cfg(feature = "lfs")]
+const LFS_USERNAME: &str = "oauth2";
+
+/// Stores files through the GitLab repository files API.
+///
+/// GitLab doesn't expose blob shas for optimistic locking, so the version
+/// token of a file is the id of the last commit that changed it.
+#[derive(Debug)]
+pub(crate) struct GitLab {
+    http: Http,
+    host: Url,
+    /// the project's full path, like `owner/repo`
+    project: String,
+    /// `/api/v4/projects/{id}` in the API, built once since nearly every request is under it
+    project_base: Url,
+    default_branch: Mutex<Option<String>>,
+}
+
+impl GitLab {
+    pub(crate) fn new(http: Http, host: Url, owner: String, repo: String) -> Self {
+        let project = format!("{owner}/{repo}");
+        let project_base = join_url(&host, ["api", "v4", "projects", project.as_str()]);
+
+        Self {
+            http,
+            host,
+            project,
+            project_base,
+            default_branch: Mutex::new(None),
+        }
+    }
+
+    /// The batch API of the project's LFS server.
+    #[cfg(feature = "lfs")]
+    fn lfs_url(&self) -> Url {
+        lfs::batch_url(&self.host, &self.project)
+    }
+
+    /// A url under `/api/v4/projects/{id}/` in the API.
+    fn project_url<'a>(&'a self, segments: impl IntoIterator<Item = &'a str>) -> Url {
+        join_url(&self.project_base, segments)
+    }
+
+    fn file_url(&self, path: &str) -> Url {
+        // the file's path is a single url encoded segment
+        self.project_url(["repository", "files", path])
+    }
+
+    /// The url to read a file from, at `reference` or the default branch.
+    fn file_url_at(&self, path: &str, reference: Option<&str>) -> Url {
+        let mut url = self.file_url(path);
+        url.query_pairs_mut()
+            .append_pair("ref", reference.unwrap_or("HEAD"));
+
+        url
+    }
+
+    /// The branch to commit to, looking up the default branch the first time it's needed.
+    async fn branch(&self, options: &CommitOptions) -> Result<String, ClientError> {
+        if let Some(branch) = &options.branch {
+            return Ok(branch.clone());
+        }
+
+        let mut default_branch = self.default_branch.lock().await;
+
+        if let Some(branch) = &*default_branch {
+            return Ok(branch.clone());
+        }
+
+        let project = self
+            .http
+            .request_json(Method::GET, self.project_url([]), None)
+            .await?;
+        let branch = match project.get("default_branch").and_then(Value::as_str) {
+            Some(branch) => branch.to_string(),
+            None => return Err(ClientError::NoContent),
+        };
+
+        *default_branch = Some(branch.clone());
+
+        Ok(branch)
+    }
+
+    /// The id of the last commit to change `path` on `branch`.
+    async fn last_commit_id(&self, path: &str, branch: &str) -> Result<String, ClientError> {
+        let mut url = self.file_url(path);
+        url.query_pairs_mut().append_pair("ref", branch);
+
+        let response = check_status(self.http.send(Method::HEAD, url, None).await?)?;
+
+        match response
+            .headers()
+            .get("X-Gitlab-Last-Commit-Id")
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(sha) => Ok(sha.to_string()),
+            None => Err(ClientError::NoSha),
+        }
+    }
+
+    /// The branch, message, and author shared by every write.
+    async fn commit_body(&self, options: &CommitOptions) -> Result<(String, Value), ClientError> {
+        let branch = self.branch(options).await?;
+
+        let mut body = json!({
+            "branch": branch,
+            "commit_message": options.message,
+        });
+        if let Some(author) = &options.author {
+            body["author_name"] = json!(author.name);
+            body["author_email"] = json!(author.email);
+        }
+
+        Ok((branch, body))
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Provider for GitLab {
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        let json = match self
+            .http
+            .request_json_optional(Method::GET, self.file_url_at(path, reference), None)
+            .await?
+        {
+            Some(json) => json,
+            None => return Ok(None),
+        };
+
+        file(&json).map(Some)
+    }
+
+    async fn poll_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+        sha: &str,
+    ) -> Result<Polled, ClientError> {
+        match self
+            .http
+            .poll_json(self.file_url_at(path, reference), sha, |json| {
+                json["last_commit_id"].as_str()
+            })
+            .await?
+        {
+            Some(Some(json)) => file(&json).map(|file| Polled::Changed(Some(file))),
+            Some(None) => Ok(Polled::Changed(None)),
+            None => Ok(Polled::Unchanged),
+        }
+    }
+
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError> {
+        let (branch, mut request_body) = self.commit_body(options).await?;
+        request_body["content"] = json!(base64::encode(content));
+        request_body["encoding"] = json!("base64");
+
+        // gitlab creates with POST and updates with PUT
+        let method = match sha {
+            Some(sha) => {
+                request_body["last_commit_id"] = json!(sha);
+                Method::PUT
+            }
+            None => Method::POST,
+        };
+
+        let response = self
+            .http
+            .send(method, self.file_url(path), Some(&request_body))
+            .await?;
+        check_write_status(response, StatusCode::BAD_REQUEST, CONFLICT_MESSAGES).await?;
+
+        // the response doesn't include the new commit, so ask for it
+        let commit = self.last_commit_id(path, &branch).await?;
+
+        // the version token of a file on gitlab is the commit that last changed it
+        Ok(CommitInfo {
+            commit: Some(commit.clone()),
+            ..CommitInfo::new(commit)
+        })
+    }
+
+    async fn delete_file(
+        &self,
+        path: &str,
+        sha: &str,
+        options: &CommitOptions,
+    ) -> Result<(), ClientError> {
+        let (_, mut request_body) = self.commit_body(options).await?;
+        request_body["last_commit_id"] = json!(sha);
+
+        let response = self
+            .http
+            .send(Method::DELETE, self.file_url(path), Some(&request_body))
+            .await?;
+
+        check_write_status(response, StatusCode::BAD_REQUEST, CONFLICT_MESSAGES)
+            .await
+            .map(|_| ())
+    }
+
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError> {
+        self.list_dir_pages(path, reference).try_collect().await
+    }
+
+    fn list_dir_pages<'a>(&'a self, path: &'a str, reference: Option<&'a str>) -> DirEntries<'a> {
+        let mut url = self.project_url(["repository", "tree"]);
+        url.query_pairs_mut()
+            .append_pair("path", path.trim_end_matches('/'))
+            .append_pair("ref", reference.unwrap_or("HEAD"))
+            .append_pair("per_page", &MAX_PER_PAGE.to_string())
+            // offset pagination stops giving a next page after 10,000 entries
+            .append_pair("pagination", "keyset");
+
+        Box::pin(
+            self.http
+                .paginate(url)
+                .try_filter_map(|item| future::ready(Ok(tree_entry(&item)))),
+        )
+    }
+
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let mut entries = Vec::new();
+        let mut page = 1;
+
+        while entries.len() < limit {
+            let per_page = (limit - entries.len()).min(MAX_PER_PAGE);
+
+            let mut url = self.project_url(["repository", "commits"]);
+            url.query_pairs_mut()
+                .append_pair("path", path)
+                .append_pair("per_page", &per_page.to_string())
+                .append_pair("page", &page.to_string());
+            if let Some(until) = until {
+                url.query_pairs_mut()
+                    .append_pair("until", &until.to_rfc3339());
+            }
+            if let Some(reference) = reference {
+                url.query_pairs_mut().append_pair("ref_name", reference);
+            }
+
+            let commits = match self.http.request_json(Method::GET, url, None).await? {
+                Value::Array(commits) => commits,
+                _ => return Err(ClientError::NoContent),
+            };
+            let count = commits.len();
+
+            for commit in commits {
+                let timestamp = match commit["authored_date"].as_str() {
+                    Some(date) => parse_timestamp(date)?,
+                    None => return Err(ClientError::NoContent),
+                };
+
+                entries.push(HistoryEntry {
+                    sha: json_str(&commit["id"])?,
+                    message: commit["message"].as_str().unwrap_or_default().to_string(),
+                    author: commit["author_name"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    timestamp,
+                });
+            }
+
+            if count < per_page {
+                break;
+            }
+
+            page += 1;
+        }
+
+        entries.truncate(limit);
+
+        Ok(entries)
+    }
+
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        let mut url = self.project_url(["repository", "commits", sha, "diff"]);
+        url.query_pairs_mut()
+            .append_pair("per_page", &MAX_PER_PAGE.to_string());
+
+        self.http
+            .paginate(url)
+            .and_then(|diff| future::ready(changed_file(&diff)))
+            .try_collect()
+            .await
+    }
+
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        let (branch, mut request_body) = self.commit_body(options).await?;
+
+        if changes.is_empty() {
+            let branch = self
+                .http
+                .request_json(
+                    Method::GET,
+                    self.project_url(["repository", "branches", branch.as_str()]),
+                    None,
+                )
+                .await?;
+
+            return json_str(&branch["commit"]["id"]);
+        }
+
+        // gitlab checks every last_commit_id and applies the actions atomically
+        let actions: Vec<Value> = changes
+            .iter()
+            .map(|change| {
+                let mut action = json!({ "file_path": change.path });
+
+                match (&change.content, &change.expected_sha) {
+                    (Some(content), expected_sha) => {
+                        action["action"] = json!(match expected_sha {
+                            Some(_) => "update",
+                            None => "create",
+                        });
+                        action["content"] = json!(base64::encode(content));
+                        action["encoding"] = json!("base64");
+                    }
+                    (None, _) => action["action"] = json!("delete"),
+                }
+                if let Some(sha) = &change.expected_sha {
+                    action["last_commit_id"] = json!(sha);
+                }
+
+                action
+            })
+            .collect();
+        request_body["actions"] = json!(actions);
+
+        let response = self
+            .http
+            .send(
+                Method::POST,
+                self.project_url(["repository", "commits"]),
+                Some(&request_body),
+            )
+            .await?;
+        let response =
+            check_write_status(response, StatusCode::BAD_REQUEST, CONFLICT_MESSAGES).await?;
+
+        match response.json::<Value>().await {
+            Ok(commit) => json_str(&commit["id"]),
+            Err(e) => Err(ClientError::Http(e)),
+        }
+    }
+
+    fn version_after_commit(&self, _content: &[u8], commit: &str) -> String {
+        // a file's version token is the last commit that changed it
+        commit.to_string()
+    }
+
+    async fn ensure_repository(
+        &self,
+        options: &RepositoryOptions,
+        branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        let mut created = false;
+
+        let project = match self
+            .http
+            .request_json_optional(Method::GET, self.project_url([]), None)
+            .await?
+        {
+            Some(project) => project,
+            None => {
+                let (namespace, name) = match self.project.rsplit_once('/') {
+                    Some(parts) => parts,
+                    None => return Err(ClientError::InvalidKey(self.project.clone())),
+                };
+
+                // users and groups are both namespaces
+                let namespace = self
+                    .http
+                    .request_json(
+                        Method::GET,
+                        join_url(&self.host, ["api", "v4", "namespaces", namespace]),
+                        None,
+                    )
+                    .await?;
+
+                let mut body = json!({
+                    "name": name,
+                    "path": name,
+                    "namespace_id": namespace["id"],
+                    "visibility": if options.private { "private" } else { "public" },
+                    // the initial commit creates the default branch
+                    "initialize_with_readme": true,
+                });
+                if let Some(description) = &options.description {
+                    body["description"] = json!(description);
+                }
+
+                created = true;
+                self.http
+                    .request_json(
+                        Method::POST,
+                        join_url(&self.host, ["api", "v4", "projects"]),
+                        Some(&body),
+                    )
+                    .await?
+            }
+        };
+
+        let default_branch = json_str(&project["default_branch"])?;
+        let branch = match branch {
+            Some(branch) if branch != default_branch => branch,
+            _ => return Ok(created),
+        };
+
+        if self
+            .http
+            .request_json_optional(
+                Method::GET,
+                self.project_url(["repository", "branches", branch]),
+                None,
+            )
+            .await?
+            .is_some()
+        {
+            return Ok(created);
+        }
+
+        let mut url = self.project_url(["repository", "branches"]);
+        url.query_pairs_mut()
+            .append_pair("branch", branch)
+            .append_pair("ref", &default_branch);
+        self.http.request_json(Method::POST, url, None).await?;
+
+        Ok(true)
+    }
+
+    async fn create_branch(&self, branch: &str, from: Option<&str>) -> Result<(), ClientError> {
+        let from = self
+            .branch(&CommitOptions {
+                branch: from.map(str::to_string),
+                ..Default::default()
+            })
+            .await?;
+
+        let mut url = self.project_url(["repository", "branches"]);
+        url.query_pairs_mut()
+            .append_pair("branch", branch)
+            .append_pair("ref", &from);
+
+        self.http
+            .request_json(Method::POST, url, None)
+            .await
+            .map(|_| ())
+    }
+
+    async fn open_pull_request(
+        &self,
+        options: &PullRequestOptions,
+        head: &str,
+        base: Option<&str>,
+    ) -> Result<u64, ClientError> {
+        let base = self
+            .branch(&CommitOptions {
+                branch: base.map(str::to_string),
+                ..Default::default()
+            })
+            .await?;
+
+        let mut body = json!({
+            "title": options.title,
+            "source_branch": head,
+            "target_branch": base,
+        });
+        if let Some(description) = &options.body {
+            body["description"] = json!(description);
+        }
+        if !options.labels.is_empty() {
+            body["labels"] = json!(options.labels.join(","));
+        }
+
+        // reviewers are given by id
+        let mut reviewer_ids = Vec::with_capacity(options.reviewers.len());
+        for username in &options.reviewers {
+            let mut url = join_url(&self.host, ["api", "v4", "users"]);
+            url.query_pairs_mut().append_pair("username", username);
+
+            match self.http.request_json(Method::GET, url, None).await? {
+                Value::Array(users) if !users.is_empty() => {
+                    reviewer_ids.push(users[0]["id"].clone())
+                }
+                _ => return Err(ClientError::InvalidKey(username.clone())),
+            }
+        }
+        if !reviewer_ids.is_empty() {
+            body["reviewer_ids"] = json!(reviewer_ids);
+        }
+
+        // merge requests are numbered by their iid within the project
+        let merge_request = self
+            .http
+            .request_json(
+                Method::POST,
+                self.project_url(["merge_requests"]),
+                Some(&body),
+            )
+            .await?;
+
+        match merge_request["iid"].as_u64() {
+            Some(number) => Ok(number),
+            None => Err(ClientError::NoContent),
+        }
+    }
+
+    #[cfg(feature = "lfs")]
+    async fn upload_lfs(&self, oid: &str, content: &[u8]) -> Result<(), ClientError> {
+        let pointer = Pointer {
+            oid: oid.to_string(),
+            size: content.len() as u64,
+        };
+
+        lfs::upload(&self.http, self.lfs_url(), LFS_USERNAME, &pointer, content).await
+    }
+
+    #[cfg(feature = "lfs")]
+    async fn download_lfs(&self, oid: &str, size: u64) -> Result<Vec<u8>, ClientError> {
+        let pointer = Pointer {
+            oid: oid.to_string(),
+            size,
+        };
+
+        lfs::download(&self.http, self.lfs_url(), LFS_USERNAME, &pointer).await
+    }
+}
+
+/// Parse an entry from the repository tree API.
+/// The file in a response from the repository files API.
+fn file(json: &Value) -> Result<RemoteFile, ClientError> {
+    let content = match json.get("content") {
+        Some(content) => decode_content(content)?,
+        None => return Err(ClientError::NoContent),
+    };
+
+    let sha = match json.get("last_commit_id").and_then(Value::as_str) {
+        Some(sha) => sha.to_string(),
+        None => return Err(ClientError::NoSha),
+    };
+
+    Ok(RemoteFile { content, sha })
+}
+
+fn tree_entry(item: &Value) -> Option<DirEntry> {
+    Some(DirEntry {
+        name: item["name"].as_str()?.to_string(),
+        kind: match item["type"].as_str() {
+            Some("blob") => EntryKind::File,
+            Some("tree") => EntryKind::Dir,
+            _ => EntryKind::Other,
+        },
+    })
+}
+
+/// A file from a commit's diff.
+fn changed_file(diff: &Value) -> Result<ChangedFile, ClientError> {
+    let kind = match (diff["new_file"].as_bool(), diff["deleted_file"].as_bool()) {
+        (Some(true), _) => ChangeKind::Added,
+        (_, Some(true)) => ChangeKind::Deleted,
+        _ => ChangeKind::Modified,
+    };
+
+    // gitlab leaves out the diff of large or binary files
+    let lines: Option<Vec<&str>> = diff["diff"]
+        .as_str()
+        .filter(|diff| !diff.is_empty())
+        .map(|diff| diff.lines().collect());
+    let count = |prefix: char| {
+        lines
+            .as_ref()
+            .map(|lines| lines.iter().filter(|line| line.starts_with(prefix)).count() as u64)
+    };
+
+    Ok(ChangedFile {
+        path: json_str(&diff["new_path"])?,
+        kind,
+        additions: count('+'),
+        deletions: count('-'),
+    })
+}
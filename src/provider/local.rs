@@ -0,0 +1,635 @@
+use std::{
+    fmt::{self, Debug},
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+#[cfg(feature = "git")]
+use chrono::{DateTime, TimeZone, Utc};
+
+use super::{
+    blob_sha, CommitInfo, CommitOptions, DirEntry, EntryKind, FileChange, Provider, RemoteFile,
+    RepositoryOptions,
+};
+#[cfg(feature = "git")]
+use super::{ChangeKind, ChangedFile, CompactOptions};
+use crate::ClientError;
+#[cfg(feature = "git")]
+use crate::HistoryEntry;
+
+/// Stores files in a directory on the local filesystem.
+///
+/// Useful for running tests and developing offline without a network
+/// connection or a token. Version tokens are git blob shas, so they match
+/// the shas github would report for the same content.
+///
+/// With the `git` feature, [`LocalBackend::git`] also commits every write to
+/// a git repository in the directory, which enables history and reading
+/// files at older commits. Commits always go to the checked out branch.
+pub struct LocalBackend {
+    root: PathBuf,
+    /// held while checking a file's sha and writing it, so writers can't race
+    lock: Mutex<()>,
+    #[cfg(feature = "git")]
+    repository: Option<Mutex<git2::Repository>>,
+}
+
+impl Debug for LocalBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("LocalBackend");
+        debug.field("root", &self.root);
+        #[cfg(feature = "git")]
+        debug.field("git", &self.repository.is_some());
+        debug.finish()
+    }
+}
+
+impl LocalBackend {
+    /// Store files under `root`, which is created on the first write if it doesn't exist.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            lock: Mutex::new(()),
+            #[cfg(feature = "git")]
+            repository: None,
+        }
+    }
+
+    /// Store files under `root` and commit every write, enabled by the `git` feature.
+    ///
+    /// `root` is initialized as a git repository if it isn't one already.
+    #[cfg(feature = "git")]
+    pub fn git(root: impl Into<PathBuf>) -> Result<Self, ClientError> {
+        let root = root.into();
+
+        let repository = match git2::Repository::open(&root) {
+            Ok(repository) => repository,
+            Err(_) => match git2::Repository::init(&root) {
+                Ok(repository) => repository,
+                Err(err) => return Err(ClientError::Git(err)),
+            },
+        };
+
+        Ok(Self {
+            root,
+            lock: Mutex::new(()),
+            repository: Some(Mutex::new(repository)),
+        })
+    }
+
+    /// Where a repository path lives on disk, refusing paths that escape the root.
+    fn resolve(&self, path: &str) -> Result<PathBuf, ClientError> {
+        let relative = Path::new(path);
+
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ClientError::InvalidKey(path.to_string()));
+        }
+
+        Ok(self.root.join(relative))
+    }
+
+    fn read(&self, path: &str) -> Result<Option<RemoteFile>, ClientError> {
+        match fs::read(self.resolve(path)?) {
+            Ok(content) => Ok(Some(RemoteFile {
+                sha: blob_sha(&content),
+                content,
+            })),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+
+    /// Fail with [`ClientError::Conflict`] unless the file's current sha is `expected`.
+    fn check_sha(&self, path: &str, expected: Option<&str>) -> Result<(), ClientError> {
+        let current = self.read(path)?.map(|file| file.sha);
+
+        if current.as_deref() == expected {
+            Ok(())
+        } else {
+            Err(ClientError::Conflict)
+        }
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> Result<(), ClientError> {
+        let path = self.resolve(path)?;
+
+        if let Some(parent) = path.parent() {
+            if let Err(err) = fs::create_dir_all(parent) {
+                return Err(ClientError::Io(err));
+            }
+        }
+
+        match fs::write(path, content) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+
+    fn remove(&self, path: &str) -> Result<(), ClientError> {
+        match fs::remove_file(self.resolve(path)?) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+
+    /// Commit `paths` if the backend is a git repository, returning the new commit's sha.
+    fn commit(
+        &self,
+        paths: &[&str],
+        options: &CommitOptions,
+    ) -> Result<Option<String>, ClientError> {
+        #[cfg(feature = "git")]
+        if let Some(repository) = &self.repository {
+            let repository = match repository.lock() {
+                Ok(repository) => repository,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+
+            return match git_commit(&repository, &self.root, paths, options) {
+                Ok(oid) => Ok(Some(oid.to_string())),
+                Err(err) => Err(ClientError::Git(err)),
+            };
+        }
+
+        #[cfg(not(feature = "git"))]
+        let _ = (paths, options);
+
+        Ok(None)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ()> {
+        match self.lock.lock() {
+            Ok(guard) => guard,
+            // nothing is guarded, a panic while writing can't leave it inconsistent
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Provider for LocalBackend {
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        match reference {
+            #[cfg(feature = "git")]
+            Some(reference) => self.git_file(path, reference),
+            #[cfg(not(feature = "git"))]
+            Some(_) => Err(ClientError::Unsupported("references")),
+            None => self.read(path),
+        }
+    }
+
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError> {
+        let _guard = self.lock();
+
+        self.check_sha(path, sha)?;
+        self.write(path, content)?;
+        let commit = self.commit(&[path], options)?;
+
+        Ok(CommitInfo {
+            commit,
+            ..CommitInfo::new(blob_sha(content))
+        })
+    }
+
+    async fn delete_file(
+        &self,
+        path: &str,
+        sha: &str,
+        options: &CommitOptions,
+    ) -> Result<(), ClientError> {
+        let _guard = self.lock();
+
+        self.check_sha(path, Some(sha))?;
+        self.remove(path)?;
+        self.commit(&[path], options).map(|_| ())
+    }
+
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError> {
+        if reference.is_some() {
+            return Err(ClientError::Unsupported("references"));
+        }
+
+        let entries = match fs::read_dir(self.resolve(path.trim_end_matches('/'))?) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(ClientError::Io(err)),
+        };
+
+        let mut listing = Vec::new();
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => return Err(ClientError::Io(err)),
+            };
+            let kind = match entry.file_type() {
+                Ok(kind) if kind.is_file() => EntryKind::File,
+                Ok(kind) if kind.is_dir() => EntryKind::Dir,
+                Ok(_) => EntryKind::Other,
+                Err(err) => return Err(ClientError::Io(err)),
+            };
+
+            listing.push(DirEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                kind,
+            });
+        }
+
+        Ok(listing)
+    }
+
+    #[cfg(feature = "git")]
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let repository = match &self.repository {
+            Some(repository) => match repository.lock() {
+                Ok(repository) => repository,
+                Err(poisoned) => poisoned.into_inner(),
+            },
+            None => return Err(ClientError::Unsupported("history")),
+        };
+
+        match git_history(&repository, path, limit, until, reference) {
+            Ok(entries) => Ok(entries),
+            Err(err) => Err(ClientError::Git(err)),
+        }
+    }
+
+    #[cfg(feature = "git")]
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        let repository = match &self.repository {
+            Some(repository) => match repository.lock() {
+                Ok(repository) => repository,
+                Err(poisoned) => poisoned.into_inner(),
+            },
+            None => return Err(ClientError::Unsupported("audit log")),
+        };
+
+        match git_changed_files(&repository, sha) {
+            Ok(files) => Ok(files),
+            Err(err) => Err(ClientError::Git(err)),
+        }
+    }
+
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        let _guard = self.lock();
+
+        for change in changes {
+            self.check_sha(&change.path, change.expected_sha.as_deref())?;
+        }
+
+        for change in changes {
+            match &change.content {
+                Some(content) => self.write(&change.path, content)?,
+                None => self.remove(&change.path)?,
+            }
+        }
+
+        let paths: Vec<&str> = changes.iter().map(|change| change.path.as_str()).collect();
+        self.commit(&paths, options)?;
+
+        Ok(self.head())
+    }
+
+    /// create the root directory, branches are ignored since commits go to the checked out branch
+    async fn ensure_repository(
+        &self,
+        _options: &RepositoryOptions,
+        _branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        if self.root.is_dir() {
+            return Ok(false);
+        }
+
+        match fs::create_dir_all(&self.root) {
+            Ok(()) => Ok(true),
+            Err(err) => Err(ClientError::Io(err)),
+        }
+    }
+
+    /// compact the checked out branch, `branch` is ignored
+    #[cfg(feature = "git")]
+    async fn compact(
+        &self,
+        options: &CompactOptions,
+        _branch: Option<&str>,
+    ) -> Result<String, ClientError> {
+        let _guard = self.lock();
+
+        let repository = match &self.repository {
+            Some(repository) => match repository.lock() {
+                Ok(repository) => repository,
+                Err(poisoned) => poisoned.into_inner(),
+            },
+            None => return Err(ClientError::Unsupported("compaction")),
+        };
+
+        match git_compact(&repository, options) {
+            Ok(head) => Ok(head.to_string()),
+            Err(err) => Err(ClientError::Git(err)),
+        }
+    }
+}
+
+impl LocalBackend {
+    /// The sha of the latest commit, or an empty string without git.
+    fn head(&self) -> String {
+        #[cfg(feature = "git")]
+        if let Some(repository) = &self.repository {
+            let repository = match repository.lock() {
+                Ok(repository) => repository,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+
+            if let Ok(Some(oid)) = repository.head().map(|head| head.target()) {
+                return oid.to_string();
+            }
+        }
+
+        String::new()
+    }
+
+    /// Read a file as of a commit, branch, or tag.
+    #[cfg(feature = "git")]
+    fn git_file(&self, path: &str, reference: &str) -> Result<Option<RemoteFile>, ClientError> {
+        let repository = match &self.repository {
+            Some(repository) => match repository.lock() {
+                Ok(repository) => repository,
+                Err(poisoned) => poisoned.into_inner(),
+            },
+            None => return Err(ClientError::Unsupported("references")),
+        };
+
+        let tree = match repository
+            .revparse_single(reference)
+            .and_then(|object| object.peel_to_tree())
+        {
+            Ok(tree) => tree,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(ClientError::Git(err)),
+        };
+
+        let entry = match tree.get_path(Path::new(path)) {
+            Ok(entry) => entry,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(ClientError::Git(err)),
+        };
+
+        let blob = match repository.find_blob(entry.id()) {
+            Ok(blob) => blob,
+            Err(err) => return Err(ClientError::Git(err)),
+        };
+
+        Ok(Some(RemoteFile {
+            content: blob.content().to_vec(),
+            sha: blob.id().to_string(),
+        }))
+    }
+}
+
+#[cfg(feature = "git")]
+fn git_commit(
+    repository: &git2::Repository,
+    root: &Path,
+    paths: &[&str],
+    options: &CommitOptions,
+) -> Result<git2::Oid, git2::Error> {
+    let mut index = repository.index()?;
+
+    for path in paths {
+        if root.join(path).exists() {
+            index.add_path(Path::new(path))?;
+        } else {
+            index.remove_path(Path::new(path))?;
+        }
+    }
+    index.write()?;
+
+    let tree = repository.find_tree(index.write_tree()?)?;
+
+    let signature = match &options.author {
+        Some(author) => git2::Signature::now(&author.name, &author.email)?,
+        None => match repository.signature() {
+            Ok(signature) => signature,
+            Err(_) => git2::Signature::now("github-db", "github-db@localhost")?,
+        },
+    };
+
+    // the first commit in a fresh repository has no parent
+    let parent = match repository.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(_) => None,
+    };
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+    repository.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &options.message,
+        &tree,
+        &parents,
+    )
+}
+
+#[cfg(feature = "git")]
+fn git_compact(
+    repository: &git2::Repository,
+    options: &CompactOptions,
+) -> Result<git2::Oid, git2::Error> {
+    let head = repository.head()?;
+    let head_commit = head.peel_to_commit()?;
+
+    // the retained commits, newest first, followed by the commit they're squashed into
+    let mut commits = vec![head_commit.clone()];
+    while commits.len() <= options.retain {
+        match commits[commits.len() - 1].parent(0) {
+            Ok(parent) => commits.push(parent),
+            Err(_) => break,
+        }
+    }
+
+    let squashed = match commits.pop() {
+        Some(squashed) if squashed.parent_count() > 0 => squashed,
+        // already compact
+        _ => return Ok(head_commit.id()),
+    };
+
+    let signature = match repository.signature() {
+        Ok(signature) => signature,
+        Err(_) => git2::Signature::now("github-db", "github-db@localhost")?,
+    };
+
+    let mut new_head = repository.commit(
+        None,
+        &signature,
+        &signature,
+        options.message(),
+        &squashed.tree()?,
+        &[],
+    )?;
+
+    for commit in commits.iter().rev() {
+        let parent = repository.find_commit(new_head)?;
+        new_head = repository.commit(
+            None,
+            &commit.author(),
+            &commit.committer(),
+            commit.message().unwrap_or_default(),
+            &commit.tree()?,
+            &[&parent],
+        )?;
+    }
+
+    match head.name() {
+        Some(name) => repository.reference(name, new_head, true, options.message())?,
+        None => return Err(git2::Error::from_str("HEAD is not a branch")),
+    };
+
+    Ok(new_head)
+}
+
+#[cfg(feature = "git")]
+fn git_history(
+    repository: &git2::Repository,
+    path: &str,
+    limit: usize,
+    until: Option<DateTime<Utc>>,
+    reference: Option<&str>,
+) -> Result<Vec<HistoryEntry>, git2::Error> {
+    let mut walk = repository.revwalk()?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    match reference {
+        Some(reference) => walk.push(
+            repository
+                .revparse_single(reference)?
+                .peel_to_commit()?
+                .id(),
+        )?,
+        None => match walk.push_head() {
+            Ok(()) => (),
+            // nothing has been committed yet
+            Err(err) if err.code() == git2::ErrorCode::UnbornBranch => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        },
+    }
+
+    let path = Path::new(path);
+    let blob_at = |commit: &git2::Commit| -> Option<git2::Oid> {
+        let tree = commit.tree().ok()?;
+
+        // the root of the repository is the commit's own tree
+        match path.as_os_str().is_empty() {
+            true => Some(tree.id()),
+            false => tree.get_path(path).ok().map(|entry| entry.id()),
+        }
+    };
+
+    let mut entries = Vec::new();
+
+    for oid in walk {
+        if entries.len() >= limit {
+            break;
+        }
+
+        let commit = repository.find_commit(oid?)?;
+        let timestamp = match Utc.timestamp_opt(commit.time().seconds(), 0).single() {
+            Some(timestamp) => timestamp,
+            None => continue,
+        };
+
+        if until.is_some_and(|until| timestamp > until) {
+            continue;
+        }
+
+        // only commits that changed the file count
+        let blob = blob_at(&commit);
+        let parent_blob = commit.parent(0).ok().and_then(|parent| blob_at(&parent));
+        if blob == parent_blob {
+            continue;
+        }
+
+        entries.push(HistoryEntry {
+            sha: commit.id().to_string(),
+            message: commit.message().unwrap_or_default().to_string(),
+            author: commit.author().name().unwrap_or_default().to_string(),
+            timestamp,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// The files `sha` changed compared to its first parent, with their line counts.
+#[cfg(feature = "git")]
+fn git_changed_files(
+    repository: &git2::Repository,
+    sha: &str,
+) -> Result<Vec<ChangedFile>, git2::Error> {
+    let commit = repository.revparse_single(sha)?.peel_to_commit()?;
+    let parent = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+
+    let diff = repository.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), None)?;
+
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let kind = match delta.status() {
+            git2::Delta::Added => ChangeKind::Added,
+            git2::Delta::Deleted => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        };
+        let path = match delta.new_file().path().or(delta.old_file().path()) {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => continue,
+        };
+
+        let (additions, deletions) = match git2::Patch::from_diff(&diff, index)? {
+            Some(patch) => {
+                let (_, additions, deletions) = patch.line_stats()?;
+                (Some(additions as u64), Some(deletions as u64))
+            }
+            None => (None, None),
+        };
+
+        files.push(ChangedFile {
+            path,
+            kind,
+            additions,
+            deletions,
+        });
+    }
+
+    Ok(files)
+}
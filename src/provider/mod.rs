@@ -0,0 +1,545 @@
+//! The storage backends a [`Client`](crate::Client) can read and write files through.
+//!
+//! Every collection, document, and key-value store is built on the small set
+//! of file operations in [`Provider`]. The built-in providers talk to the
+//! contents APIs of GitHub, GitLab, and Gitea; pick one with
+//! [`ClientBuilder::forge`](crate::ClientBuilder::forge). [`LocalBackend`]
+//! stores files on disk for tests and offline development. Any of these, or
+//! your own, can be used with [`Client::with_provider`](crate::Client::with_provider).
+
+mod gitea;
+mod github;
+mod gitlab;
+#[cfg(not(target_arch = "wasm32"))]
+mod local;
+
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+#[cfg(not(target_arch = "wasm32"))]
+use futures::stream::BoxStream;
+#[cfg(target_arch = "wasm32")]
+use futures::stream::LocalBoxStream;
+use futures::{stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub(crate) use gitea::Gitea;
+pub(crate) use github::GitHub;
+pub(crate) use gitlab::GitLab;
+#[cfg(not(target_arch = "wasm32"))]
+pub use local::LocalBackend;
+
+use crate::{ClientError, HistoryEntry};
+
+/// The kinds of forge the built-in providers can talk to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Forge {
+    #[default]
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl Forge {
+    /// where the forge's public instance lives, used when no host is given
+    pub fn default_host(&self) -> &'static str {
+        match self {
+            Forge::GitHub => "https://api.github.com",
+            Forge::GitLab => "https://gitlab.com",
+            Forge::Gitea => "https://gitea.com",
+        }
+    }
+}
+
+/// File storage with optimistic concurrency, the interface every backend implements.
+///
+/// Files are addressed by their path from the root of the repository. Each
+/// file has a version token (a blob sha on GitHub and Gitea, the last commit
+/// id on GitLab) that must be sent back when updating or deleting it; a
+/// mismatched token means someone else wrote first and fails with
+/// [`ClientError::Conflict`].
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait Provider: Debug + Send + Sync {
+    /// fetch a file, optionally at a branch or commit, returning [`None`] if it doesn't exist
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError>;
+
+    /// fetch several files at the same branch or commit, returning [`None`] for those that don't exist
+    ///
+    /// by default each file is fetched separately, providers that can read
+    /// several files in one request should override this
+    async fn get_files(
+        &self,
+        paths: &[String],
+        reference: Option<&str>,
+    ) -> Result<Vec<Option<RemoteFile>>, ClientError> {
+        get_each(self, paths, reference).await
+    }
+
+    /// fetch a file unless it's still at version `sha`, for polling it for changes
+    ///
+    /// by default the file is fetched and its version compared, providers
+    /// that can ask whether a file changed without downloading it should
+    /// override this
+    async fn poll_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+        sha: &str,
+    ) -> Result<Polled, ClientError> {
+        match self.get_file(path, reference).await? {
+            Some(file) if file.sha == sha => Ok(Polled::Unchanged),
+            file => Ok(Polled::Changed(file)),
+        }
+    }
+
+    /// create a file, or update it if `sha` is given, returning the commit and its new version token
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError>;
+
+    /// delete a file whose version token is `sha`
+    async fn delete_file(
+        &self,
+        path: &str,
+        sha: &str,
+        options: &CommitOptions,
+    ) -> Result<(), ClientError>;
+
+    /// list a directory, returning an empty list if it doesn't exist
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError>;
+
+    /// list a directory a page at a time, fetching the next page as the stream is polled
+    ///
+    /// by default this is a single [`list_dir`](Self::list_dir), providers
+    /// whose listings are paginated should override it
+    fn list_dir_pages<'a>(&'a self, path: &'a str, reference: Option<&'a str>) -> DirEntries<'a> {
+        Box::pin(
+            stream::once(self.list_dir(path, reference))
+                .map_ok(|entries| stream::iter(entries.into_iter().map(Ok)))
+                .try_flatten(),
+        )
+    }
+
+    /// up to `limit` commits touching `path`, newest first, optionally only those before `until`
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let _ = (path, limit, until, reference);
+        Err(ClientError::Unsupported("history"))
+    }
+
+    /// the files the commit `sha` changed, compared to its first parent
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        let _ = sha;
+        Err(ClientError::Unsupported("audit log"))
+    }
+
+    /// write several files in a single commit, returning the commit's sha
+    ///
+    /// fails with [`ClientError::Conflict`] if any file's version token
+    /// doesn't match `expected_sha` or the branch moved while committing
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        let _ = (changes, options);
+        Err(ClientError::Unsupported("transactions"))
+    }
+
+    /// the version token of a file written with `content` by [`commit_files`](Self::commit_files) in the commit `commit`
+    ///
+    /// by default its git blob sha, providers whose version tokens are
+    /// something else should override this
+    fn version_after_commit(&self, content: &[u8], commit: &str) -> String {
+        let _ = commit;
+        blob_sha(content)
+    }
+
+    /// create the repository if it doesn't exist, and `branch` in it if given, returning whether anything was created
+    async fn ensure_repository(
+        &self,
+        options: &RepositoryOptions,
+        branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        let _ = (options, branch);
+        Err(ClientError::Unsupported("repository creation"))
+    }
+
+    /// squash the history of `branch` into a single commit with the same content, returning the new head
+    ///
+    /// the most recent [`CompactOptions::retain`] commits are recreated on
+    /// top of the squashed commit. the branch is force updated, failing with
+    /// [`ClientError::Conflict`] instead if its head moved while compacting.
+    async fn compact(
+        &self,
+        options: &CompactOptions,
+        branch: Option<&str>,
+    ) -> Result<String, ClientError> {
+        let _ = (options, branch);
+        Err(ClientError::Unsupported("compaction"))
+    }
+
+    /// create `branch` at the head of `from`, the default branch if [`None`]
+    async fn create_branch(&self, branch: &str, from: Option<&str>) -> Result<(), ClientError> {
+        let _ = (branch, from);
+        Err(ClientError::Unsupported("branches"))
+    }
+
+    /// open a pull request merging `head` into `base` (the default branch if [`None`]), returning its number
+    async fn open_pull_request(
+        &self,
+        options: &PullRequestOptions,
+        head: &str,
+        base: Option<&str>,
+    ) -> Result<u64, ClientError> {
+        let _ = (options, head, base);
+        Err(ClientError::Unsupported("pull requests"))
+    }
+
+    /// upload `content` to the repository's Git LFS server, where it's addressed by its sha256 `oid`
+    async fn upload_lfs(&self, oid: &str, content: &[u8]) -> Result<(), ClientError> {
+        let _ = (oid, content);
+        Err(ClientError::Unsupported("git lfs"))
+    }
+
+    /// download the object with the sha256 `oid` from the repository's Git LFS server
+    async fn download_lfs(&self, oid: &str, size: u64) -> Result<Vec<u8>, ClientError> {
+        let _ = (oid, size);
+        Err(ClientError::Unsupported("git lfs"))
+    }
+}
+
+/// The entries of a directory, see [`Provider::list_dir_pages`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type DirEntries<'a> = BoxStream<'a, Result<DirEntry, ClientError>>;
+/// The entries of a directory, see [`Provider::list_dir_pages`].
+#[cfg(target_arch = "wasm32")]
+pub type DirEntries<'a> = LocalBoxStream<'a, Result<DirEntry, ClientError>>;
+
+/// A file's decoded content and the version token used to update it.
+#[derive(Clone, Debug)]
+pub struct RemoteFile {
+    pub content: Vec<u8>,
+    pub sha: String,
+}
+
+/// Whether a polled file changed, returned by [`Provider::poll_file`].
+#[derive(Clone, Debug)]
+pub enum Polled {
+    /// the file is still at the version it was polled with
+    Unchanged,
+    /// the file changed, [`None`] if it was deleted
+    Changed(Option<RemoteFile>),
+}
+
+/// What a write committed, returned by [`Provider::put_file`] and the operations built on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommitInfo {
+    /// the sha of the new commit, [`None`] if the provider doesn't make commits
+    pub commit: Option<String>,
+    /// the written file's new version token
+    pub sha: String,
+    /// where the commit can be viewed, if the provider has a web interface
+    pub html_url: Option<String>,
+    /// when the commit was made, if the provider reports it
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl CommitInfo {
+    /// A write that only reports the file's new version token.
+    pub fn new(sha: impl Into<String>) -> Self {
+        Self {
+            sha: sha.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A file changed by a commit, see [`Provider::changed_files`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub path: String,
+    pub kind: ChangeKind,
+    /// lines added, if the provider reports it
+    pub additions: Option<u64>,
+    /// lines removed, if the provider reports it
+    pub deletions: Option<u64>,
+}
+
+/// How a commit changed a [`ChangedFile`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// An entry in a directory listing.
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    pub name: String,
+    pub kind: EntryKind,
+}
+
+/// What a [`DirEntry`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    /// symlinks, submodules, and anything else
+    Other,
+}
+
+/// Describes the commit a write creates.
+#[derive(Clone, Debug, Default)]
+pub struct CommitOptions {
+    pub message: String,
+    /// the branch to commit to, the repository's default branch if [`None`]
+    pub branch: Option<String>,
+    /// who the commit is attributed to, the token's owner if [`None`]
+    pub author: Option<CommitAuthor>,
+}
+
+/// The name and email a commit is attributed to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+/// How a repository is created by [`Provider::ensure_repository`].
+#[derive(Clone, Debug)]
+pub struct RepositoryOptions {
+    /// whether only collaborators can see the repository, true by default
+    pub private: bool,
+    pub description: Option<String>,
+}
+
+impl Default for RepositoryOptions {
+    fn default() -> Self {
+        Self {
+            private: true,
+            description: None,
+        }
+    }
+}
+
+/// How [`Provider::compact`] rewrites history.
+#[derive(Clone, Debug, Default)]
+pub struct CompactOptions {
+    /// how many of the most recent commits to keep, 0 by default
+    pub retain: usize,
+    /// the message of the squashed commit, written by the client's [`MessageFormatter`](crate::MessageFormatter) if [`None`]
+    pub message: Option<String>,
+}
+
+impl CompactOptions {
+    /// the message of the squashed commit, `"Compact History"` if none was set
+    pub fn message(&self) -> &str {
+        self.message.as_deref().unwrap_or("Compact History")
+    }
+}
+
+/// The pull request opened by [`WriteMode::PullRequest`](crate::WriteMode::PullRequest).
+#[derive(Clone, Debug)]
+pub struct PullRequestOptions {
+    pub title: String,
+    pub body: Option<String>,
+    /// labels added to the pull request, which must already exist on gitea
+    pub labels: Vec<String>,
+    /// usernames asked to review the pull request
+    pub reviewers: Vec<String>,
+    /// the branch changes are committed to, a new `github-db/<timestamp>` branch if [`None`]
+    pub branch: Option<String>,
+}
+
+impl Default for PullRequestOptions {
+    fn default() -> Self {
+        Self {
+            title: "Update Database".to_string(),
+            body: None,
+            labels: Vec::new(),
+            reviewers: Vec::new(),
+            branch: None,
+        }
+    }
+}
+
+/// A file written as part of [`Provider::commit_files`].
+#[derive(Clone, Debug)]
+pub struct FileChange {
+    pub path: String,
+    /// the new content, or [`None`] to delete the file
+    pub content: Option<Vec<u8>>,
+    /// the file's version token when the change was staged, [`None`] if it didn't exist
+    pub expected_sha: Option<String>,
+}
+
+/// Fetch files one at a time, what [`Provider::get_files`] does by default.
+pub(crate) async fn get_each<P: Provider + ?Sized>(
+    provider: &P,
+    paths: &[String],
+    reference: Option<&str>,
+) -> Result<Vec<Option<RemoteFile>>, ClientError> {
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        files.push(provider.get_file(path, reference).await?);
+    }
+
+    Ok(files)
+}
+
+/// Decode base64 file content as returned by the contents APIs.
+pub(crate) fn decode_content(value: &Value) -> Result<Vec<u8>, ClientError> {
+    let content = match value.as_str() {
+        Some(content) => content,
+        None => return Err(ClientError::NoContent),
+    };
+
+    // github puts a "\n" into the base64 every 60 characters, skip them while
+    // decoding rather than copying the whole file without them first
+    let mut encoded = SkipNewlines(content.as_bytes());
+    let mut decoder = base64::read::DecoderReader::new(&mut encoded, base64::STANDARD);
+
+    let mut decoded = Vec::with_capacity(content.len() / 4 * 3);
+    match decoder.read_to_end(&mut decoded) {
+        Ok(_) => Ok(decoded),
+        Err(err) => match err
+            .get_ref()
+            .and_then(|err| err.downcast_ref::<base64::DecodeError>())
+        {
+            Some(err) => Err(ClientError::BadEncoding(err.clone())),
+            None => Err(ClientError::Io(err)),
+        },
+    }
+}
+
+/// Reads base64 content, leaving out the line breaks in it.
+struct SkipNewlines<'a>(&'a [u8]);
+
+impl Read for SkipNewlines<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+
+        while read < buf.len() {
+            while let Some((b'\n', rest)) = self.0.split_first() {
+                self.0 = rest;
+            }
+
+            let line = self
+                .0
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .unwrap_or(self.0.len());
+            if line == 0 {
+                break;
+            }
+
+            let len = line.min(buf.len() - read);
+            buf[read..read + len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            read += len;
+        }
+
+        Ok(read)
+    }
+}
+
+/// Parse a commit from the GitHub or Gitea commits API.
+pub(crate) fn parse_history_entry(commit: &Value) -> Result<HistoryEntry, ClientError> {
+    let sha = match commit["sha"].as_str() {
+        Some(sha) => sha.to_string(),
+        None => return Err(ClientError::NoSha),
+    };
+
+    let timestamp = match commit["commit"]["author"]["date"].as_str() {
+        Some(date) => parse_timestamp(date)?,
+        None => return Err(ClientError::NoContent),
+    };
+
+    Ok(HistoryEntry {
+        sha,
+        message: commit["commit"]["message"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        author: commit["commit"]["author"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        timestamp,
+    })
+}
+
+/// A file from the `files` of a github or gitea commit.
+pub(crate) fn parse_changed_file(file: &Value) -> Result<ChangedFile, ClientError> {
+    Ok(ChangedFile {
+        path: json_str(&file["filename"])?,
+        kind: match file["status"].as_str() {
+            Some("added") => ChangeKind::Added,
+            Some("removed") | Some("deleted") => ChangeKind::Deleted,
+            _ => ChangeKind::Modified,
+        },
+        additions: file["additions"].as_u64(),
+        deletions: file["deletions"].as_u64(),
+    })
+}
+
+pub(crate) fn parse_timestamp(date: &str) -> Result<DateTime<Utc>, ClientError> {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(date) => Ok(date.with_timezone(&Utc)),
+        Err(_) => Err(ClientError::NoContent),
+    }
+}
+
+/// The commit a write through the github or gitea contents API made.
+pub(crate) fn contents_commit(response: &Value) -> Result<CommitInfo, ClientError> {
+    let commit = &response["commit"];
+
+    Ok(CommitInfo {
+        commit: commit["sha"].as_str().map(str::to_string),
+        sha: json_str(&response["content"]["sha"])?,
+        html_url: commit["html_url"].as_str().map(str::to_string),
+        timestamp: commit["committer"]["date"]
+            .as_str()
+            .and_then(|date| parse_timestamp(date).ok()),
+    })
+}
+
+pub(crate) fn json_str(value: &Value) -> Result<String, ClientError> {
+    match value.as_str() {
+        Some(string) => Ok(string.to_string()),
+        None => Err(ClientError::NoSha),
+    }
+}
+
+/// The sha git (and github) would give a blob with this content.
+pub(crate) fn blob_sha(content: &[u8]) -> String {
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    hasher.digest().to_string()
+}
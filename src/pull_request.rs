@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::lock::{Mutex, MutexGuard};
+
+use crate::{
+    ChangedFile, Client, ClientError, CommitInfo, CommitOptions, DirEntry, FileChange,
+    HistoryEntry, Polled, Provider, PullRequestOptions, RemoteFile, RepositoryOptions,
+};
+
+/// Where a [`Client`]'s writes are committed, see [`ClientBuilder::write_mode`](crate::ClientBuilder::write_mode).
+#[derive(Clone, Debug, Default)]
+pub enum WriteMode {
+    /// commit straight to the client's branch
+    #[default]
+    Direct,
+    /// commit to a new branch and open a pull request into the client's branch, so changes can be reviewed
+    ///
+    /// the branch is created and the pull request opened by the first
+    /// write, and every later write from the client (or its clones) is
+    /// added to the same pull request. reads see the pull request's changes.
+    PullRequest(PullRequestOptions),
+}
+
+/// The branch and pull request a client in [`WriteMode::PullRequest`] writes to.
+#[derive(Debug, Default)]
+pub(crate) struct PullRequestState {
+    branch: Option<String>,
+    number: Option<u64>,
+}
+
+impl Client {
+    /// A copy of this client that writes according to `mode`.
+    ///
+    /// [`WriteMode::Direct`] returns an unchanged copy.
+    pub fn with_write_mode(&self, mode: WriteMode) -> Client {
+        let options = match mode {
+            WriteMode::Direct => return self.clone(),
+            WriteMode::PullRequest(options) => options,
+        };
+
+        let state = Arc::new(Mutex::new(PullRequestState::default()));
+
+        self.derive(|inner| {
+            inner.provider = Arc::new(PullRequestWriter::new(
+                inner.provider.clone(),
+                inner.branch.clone(),
+                options,
+                state.clone(),
+            ));
+            inner.pull_request = Some(state);
+        })
+    }
+
+    /// the number of the pull request opened by [`WriteMode::PullRequest`], [`None`] until the first write
+    pub async fn pull_request(&self) -> Option<u64> {
+        match &self.inner.pull_request {
+            Some(state) => state.lock().await.number,
+            None => None,
+        }
+    }
+}
+
+/// Commits to a pull request's branch instead of the base branch.
+#[derive(Debug)]
+pub(crate) struct PullRequestWriter {
+    inner: Arc<dyn Provider>,
+    /// the branch the pull request merges into, the default branch if [`None`]
+    base: Option<String>,
+    options: PullRequestOptions,
+    state: Arc<Mutex<PullRequestState>>,
+}
+
+impl PullRequestWriter {
+    pub(crate) fn new(
+        inner: Arc<dyn Provider>,
+        base: Option<String>,
+        options: PullRequestOptions,
+        state: Arc<Mutex<PullRequestState>>,
+    ) -> Self {
+        Self {
+            inner,
+            base,
+            options,
+            state,
+        }
+    }
+
+    /// Lock the state, creating the branch if this is the first write, and point `options` at the branch.
+    async fn start(
+        &self,
+        options: &CommitOptions,
+    ) -> Result<(MutexGuard<'_, PullRequestState>, CommitOptions), ClientError> {
+        // held until the write finishes so the branch and pull request are only created once
+        let mut state = self.state.lock().await;
+
+        let branch = match &state.branch {
+            Some(branch) => branch.clone(),
+            None => {
+                let branch = match &self.options.branch {
+                    Some(branch) => branch.clone(),
+                    None => format!("github-db/{}", Utc::now().format("%Y%m%d%H%M%S%3f")),
+                };
+
+                self.inner
+                    .create_branch(&branch, self.base.as_deref())
+                    .await?;
+                state.branch = Some(branch.clone());
+                branch
+            }
+        };
+
+        let options = CommitOptions {
+            branch: Some(branch),
+            ..options.clone()
+        };
+
+        Ok((state, options))
+    }
+
+    /// Open the pull request after its first commit, since forges won't open one without changes.
+    async fn finish(&self, mut state: MutexGuard<'_, PullRequestState>) -> Result<(), ClientError> {
+        if state.number.is_some() {
+            return Ok(());
+        }
+
+        let branch = match &state.branch {
+            Some(branch) => branch.clone(),
+            None => return Ok(()),
+        };
+
+        let number = self
+            .inner
+            .open_pull_request(&self.options, &branch, self.base.as_deref())
+            .await?;
+        state.number = Some(number);
+
+        Ok(())
+    }
+
+    /// The branch to read from, the pull request's once it exists.
+    async fn reference(&self, reference: Option<&str>) -> Option<String> {
+        if reference == self.base.as_deref() {
+            if let Some(branch) = &self.state.lock().await.branch {
+                return Some(branch.clone());
+            }
+        }
+
+        reference.map(str::to_string)
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Provider for PullRequestWriter {
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        let reference = self.reference(reference).await;
+        self.inner.get_file(path, reference.as_deref()).await
+    }
+
+    async fn poll_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+        sha: &str,
+    ) -> Result<Polled, ClientError> {
+        let reference = self.reference(reference).await;
+        self.inner.poll_file(path, reference.as_deref(), sha).await
+    }
+
+    async fn put_file(
+        &self,
+        path: &str,
+        content: &[u8],
+        sha: Option<&str>,
+        options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError> {
+        let (state, options) = self.start(options).await?;
+        let commit = self.inner.put_file(path, content, sha, &options).await?;
+        self.finish(state).await?;
+
+        Ok(commit)
+    }
+
+    async fn delete_file(
+        &self,
+        path: &str,
+        sha: &str,
+        options: &CommitOptions,
+    ) -> Result<(), ClientError> {
+        let (state, options) = self.start(options).await?;
+        self.inner.delete_file(path, sha, &options).await?;
+        self.finish(state).await
+    }
+
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError> {
+        let reference = self.reference(reference).await;
+        self.inner.list_dir(path, reference.as_deref()).await
+    }
+
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let reference = self.reference(reference).await;
+        self.inner
+            .list_commits(path, limit, until, reference.as_deref())
+            .await
+    }
+
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        self.inner.changed_files(sha).await
+    }
+
+    async fn commit_files(
+        &self,
+        changes: &[FileChange],
+        options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        let (state, options) = self.start(options).await?;
+        let sha = self.inner.commit_files(changes, &options).await?;
+        self.finish(state).await?;
+
+        Ok(sha)
+    }
+
+    fn version_after_commit(&self, content: &[u8], commit: &str) -> String {
+        self.inner.version_after_commit(content, commit)
+    }
+
+    async fn ensure_repository(
+        &self,
+        options: &RepositoryOptions,
+        branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        self.inner.ensure_repository(options, branch).await
+    }
+
+    async fn create_branch(&self, branch: &str, from: Option<&str>) -> Result<(), ClientError> {
+        self.inner.create_branch(branch, from).await
+    }
+
+    async fn open_pull_request(
+        &self,
+        options: &PullRequestOptions,
+        head: &str,
+        base: Option<&str>,
+    ) -> Result<u64, ClientError> {
+        self.inner.open_pull_request(options, head, base).await
+    }
+
+    async fn upload_lfs(&self, oid: &str, content: &[u8]) -> Result<(), ClientError> {
+        self.inner.upload_lfs(oid, content).await
+    }
+
+    async fn download_lfs(&self, oid: &str, size: u64) -> Result<Vec<u8>, ClientError> {
+        self.inner.download_lfs(oid, size).await
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    ChangedFile, Client, ClientError, CommitInfo, CommitOptions, CompactOptions, DirEntries,
+    DirEntry, FileChange, HistoryEntry, Polled, Provider, PullRequestOptions, RemoteFile,
+    RepositoryOptions,
+};
+
+impl Client {
+    /// A copy of this client that can only read, see [`ClientBuilder::read_only`](crate::ClientBuilder::read_only).
+    pub fn read_only(&self) -> Client {
+        self.derive(|inner| inner.provider = Arc::new(ReadOnly(inner.provider.clone())))
+    }
+}
+
+/// Passes reads through to another provider and rejects every write.
+#[derive(Debug)]
+pub(crate) struct ReadOnly(pub(crate) Arc<dyn Provider>);
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl Provider for ReadOnly {
+    async fn get_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<RemoteFile>, ClientError> {
+        self.0.get_file(path, reference).await
+    }
+
+    async fn get_files(
+        &self,
+        paths: &[String],
+        reference: Option<&str>,
+    ) -> Result<Vec<Option<RemoteFile>>, ClientError> {
+        self.0.get_files(paths, reference).await
+    }
+
+    async fn poll_file(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+        sha: &str,
+    ) -> Result<Polled, ClientError> {
+        self.0.poll_file(path, reference, sha).await
+    }
+
+    async fn put_file(
+        &self,
+        _path: &str,
+        _content: &[u8],
+        _sha: Option<&str>,
+        _options: &CommitOptions,
+    ) -> Result<CommitInfo, ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    async fn delete_file(
+        &self,
+        _path: &str,
+        _sha: &str,
+        _options: &CommitOptions,
+    ) -> Result<(), ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    async fn list_dir(
+        &self,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Vec<DirEntry>, ClientError> {
+        self.0.list_dir(path, reference).await
+    }
+
+    fn list_dir_pages<'a>(&'a self, path: &'a str, reference: Option<&'a str>) -> DirEntries<'a> {
+        self.0.list_dir_pages(path, reference)
+    }
+
+    async fn list_commits(
+        &self,
+        path: &str,
+        limit: usize,
+        until: Option<DateTime<Utc>>,
+        reference: Option<&str>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        self.0.list_commits(path, limit, until, reference).await
+    }
+
+    async fn changed_files(&self, sha: &str) -> Result<Vec<ChangedFile>, ClientError> {
+        self.0.changed_files(sha).await
+    }
+
+    async fn commit_files(
+        &self,
+        _changes: &[FileChange],
+        _options: &CommitOptions,
+    ) -> Result<String, ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    fn version_after_commit(&self, content: &[u8], commit: &str) -> String {
+        self.0.version_after_commit(content, commit)
+    }
+
+    async fn ensure_repository(
+        &self,
+        _options: &RepositoryOptions,
+        _branch: Option<&str>,
+    ) -> Result<bool, ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    async fn compact(
+        &self,
+        _options: &CompactOptions,
+        _branch: Option<&str>,
+    ) -> Result<String, ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    async fn create_branch(&self, _branch: &str, _from: Option<&str>) -> Result<(), ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    async fn open_pull_request(
+        &self,
+        _options: &PullRequestOptions,
+        _head: &str,
+        _base: Option<&str>,
+    ) -> Result<u64, ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    async fn upload_lfs(&self, _oid: &str, _content: &[u8]) -> Result<(), ClientError> {
+        Err(ClientError::ReadOnly)
+    }
+
+    async fn download_lfs(&self, oid: &str, size: u64) -> Result<Vec<u8>, ClientError> {
+        self.0.download_lfs(oid, size).await
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    expiry::expiry_key, index::field_value, merge::KeyFn, Client, ClientError, Collection,
+    CommitInfo, Operation,
+};
+
+/// A document type that knows its own primary key and which fields to index.
+///
+/// Usually implemented with `#[derive(Document)]` from the `derive` feature:
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize, Document)]
+/// struct User {
+///     #[id]
+///     id: u64,
+///     #[unique]
+///     email: String,
+///     #[indexed]
+///     country: String,
+/// }
+/// ```
+pub trait Record: Serialize + DeserializeOwned {
+    /// the type of the primary key
+    type Id: PartialEq + Serialize;
+
+    /// fields to maintain an index on
+    const INDEXED: &'static [&'static str];
+    /// fields that are indexed and must not be shared by two documents
+    const UNIQUE: &'static [&'static str];
+
+    /// the primary key of this document
+    fn id(&self) -> &Self::Id;
+}
+
+impl Client {
+    /// Return a reference to a collection of [`Record`]s, creating any indexes the type declares.
+    pub async fn record_collection<T: Record>(
+        &self,
+        name: impl AsRef<str>,
+    ) -> Result<Collection<T>, ClientError> {
+        let key: KeyFn<T> = Arc::new(|document| match serde_json::to_value(document.id()) {
+            Ok(key) => Ok(key),
+            Err(err) => Err(ClientError::Json(err)),
+        });
+        let mut collection = self.collection::<T>(name).await?.keyed_by(key);
+
+        let mut missing = false;
+        for field in T::INDEXED.iter().chain(T::UNIQUE) {
+            if !collection.indexes.iter().any(|index| index == field) {
+                collection.indexes.push(field.to_string());
+                missing = true;
+            }
+        }
+
+        if missing {
+            collection.write_indexes().await?;
+        }
+
+        Ok(collection)
+    }
+}
+
+impl<T: Record> Collection<T> {
+    /// syncs and returns the document with the primary key `id`
+    pub async fn get(&mut self, id: &T::Id) -> Result<Option<&T>, ClientError> {
+        self.update().await?;
+
+        Ok(self.inner.iter().find(|document| document.id() == id))
+    }
+
+    /// replace the document with the same primary key as `data`, or push it if there isn't one
+    ///
+    /// returns [`ClientError::DuplicateKey`] without writing anything if
+    /// another document has the same value in one of the [`Record::UNIQUE`] fields
+    pub async fn save(&mut self, data: T) -> Result<CommitInfo, ClientError> {
+        let message = self.message(&Operation::Save);
+        self.save_with(data, message).await
+    }
+
+    /// like [`save`](Self::save), with `message` as the commit message
+    pub async fn save_with(
+        &mut self,
+        data: T,
+        message: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError> {
+        self.save_inner(data, message.as_ref(), None).await
+    }
+
+    /// Save `data`, setting its expiry if `expires_at` is given.
+    pub(crate) async fn save_inner(
+        &mut self,
+        data: T,
+        message: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<CommitInfo, ClientError> {
+        let expiry = match expires_at {
+            Some(expires_at) => match serde_json::to_value(data.id()) {
+                Ok(key) => Some((expiry_key(&key), expires_at)),
+                Err(err) => return Err(ClientError::Json(err)),
+            },
+            None => None,
+        };
+        let mut data = data;
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+            self.check_unique(&data)?;
+
+            if let Some((key, expires_at)) = &expiry {
+                self.meta.expires.insert(key.clone(), *expires_at);
+            }
+
+            let sha = self.sha.clone();
+            let position = self
+                .inner
+                .iter()
+                .position(|document| document.id() == data.id());
+            let previous = match position {
+                Some(position) => {
+                    Some((position, std::mem::replace(&mut self.inner[position], data)))
+                }
+                None => {
+                    self.inner.push(data);
+                    None
+                }
+            };
+
+            match self.write(&Operation::Save, message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => {
+                    retries += 1;
+
+                    data = match position {
+                        Some(position) => self.inner.swap_remove(position),
+                        None => match self.inner.pop() {
+                            Some(data) => data,
+                            None => return Err(ClientError::Conflict),
+                        },
+                    };
+                }
+                Err(err) => {
+                    // nothing was committed, so put back the document it replaced
+                    if self.sha == sha {
+                        match previous {
+                            Some((position, previous)) => self.inner[position] = previous,
+                            None => {
+                                self.inner.pop();
+                            }
+                        }
+                    }
+                    return Err(err);
+                }
+                Ok(commit) => return Ok(commit),
+            }
+        }
+    }
+
+    /// remove the document with the primary key `id`, returning it if it existed
+    pub async fn remove(&mut self, id: &T::Id) -> Result<Option<T>, ClientError> {
+        let message = self.message(&Operation::Delete);
+        self.remove_with(id, message).await
+    }
+
+    /// like [`remove`](Self::remove), with `message` as the commit message
+    pub async fn remove_with(
+        &mut self,
+        id: &T::Id,
+        message: impl AsRef<str>,
+    ) -> Result<Option<T>, ClientError> {
+        let message = message.as_ref();
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+
+            let position = match self.inner.iter().position(|document| document.id() == id) {
+                Some(position) => position,
+                None => return Ok(None),
+            };
+            let sha = self.sha.clone();
+            let removed = self.inner.remove(position);
+
+            match self.write(&Operation::Delete, message).await {
+                Err(ClientError::Conflict) if self.rebases(retries) => retries += 1,
+                Err(err) => {
+                    // nothing was committed, so put the document back
+                    if self.sha == sha {
+                        self.inner.insert(position, removed);
+                    }
+                    return Err(err);
+                }
+                Ok(_) => return Ok(Some(removed)),
+            }
+        }
+    }
+
+    /// Check that no other document shares a unique field with `data`.
+    fn check_unique(&self, data: &T) -> Result<(), ClientError> {
+        if T::UNIQUE.is_empty() {
+            return Ok(());
+        }
+
+        let new = match serde_json::to_value(data) {
+            Ok(new) => new,
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+
+        for document in self
+            .inner
+            .iter()
+            .filter(|document| document.id() != data.id())
+        {
+            let document = match serde_json::to_value(document) {
+                Ok(document) => document,
+                Err(err) => return Err(ClientError::Json(err)),
+            };
+
+            let duplicate = T::UNIQUE.iter().any(|field| {
+                let value = field_value(&new, field);
+                value.is_some() && value == field_value(&document, field)
+            });
+            if duplicate {
+                return Err(ClientError::DuplicateKey);
+            }
+        }
+
+        Ok(())
+    }
+}
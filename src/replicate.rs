@@ -0,0 +1,207 @@
+//! Copying collections from one database to another.
+//!
+//! A [`Replicator`] keeps a target [`Client`] in sync with a source, which
+//! can be a different repository or a different provider entirely, so a
+//! second repository can be kept as a hot standby. Each [`sync`](Replicator::sync)
+//! reads both databases and commits every difference to the target in a
+//! single commit; [`run`](Replicator::run) syncs on a schedule.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use futures::{stream, Stream};
+use futures_timer::Delay;
+
+use crate::{BundleFile, Client, ClientError, FileChange, Operation};
+
+/// What a [`Replicator`] does with files that were changed on the target since they were last replicated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplicationPolicy {
+    /// overwrite them with the source's version
+    #[default]
+    SourceWins,
+    /// leave them alone until they match the source again
+    TargetWins,
+    /// fail the sync with [`ClientError::Conflict`] without writing anything
+    FailFast,
+}
+
+/// The files a single [`Replicator::sync`] changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replication {
+    /// files written to the target
+    pub updated: Vec<String>,
+    /// files deleted from the target
+    pub deleted: Vec<String>,
+    /// files left alone because they were changed on the target
+    pub skipped: Vec<String>,
+    /// the sha of the commit to the target, [`None`] if it was already in sync
+    pub commit: Option<String>,
+}
+
+/// Copies collections from one [`Client`] to another, see the [module documentation](self).
+pub struct Replicator {
+    source: Client,
+    target: Client,
+    collections: Option<Vec<String>>,
+    policy: ReplicationPolicy,
+    /// the content of every file as it was last replicated
+    replicated: BTreeMap<String, BundleFile>,
+}
+
+impl Replicator {
+    /// Replicate every collection in `source` to `target`.
+    pub fn new(source: Client, target: Client) -> Self {
+        Self {
+            source,
+            target,
+            collections: None,
+            policy: ReplicationPolicy::default(),
+            replicated: BTreeMap::new(),
+        }
+    }
+
+    /// only replicate the named collections, along with their indexes, shards, and trash
+    pub fn collections<I, S>(mut self, collections: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.collections = Some(collections.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// set what happens to files changed on the target, see [`ReplicationPolicy`]
+    pub fn policy(mut self, policy: ReplicationPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// make the target match the source, returning what changed
+    ///
+    /// files only count as changed on the target once they've been
+    /// replicated by this replicator, so the first sync always makes the
+    /// target match the source.
+    pub async fn sync(&mut self) -> Result<Replication, ClientError> {
+        let source = self.source.export().await?.files;
+        let target = self.target.export().await?.files;
+
+        let mut paths: Vec<&String> = source.keys().chain(target.keys()).collect();
+        paths.sort();
+        paths.dedup();
+
+        let mut replication = Replication::default();
+        let mut conflicts = false;
+        let mut changes = Vec::new();
+
+        for path in paths {
+            if !self.includes(path) {
+                continue;
+            }
+
+            let wanted = source.get(path);
+            let current = target.get(path);
+
+            if wanted == current {
+                self.remember(path, wanted);
+                continue;
+            }
+
+            let changed_on_target = match self.replicated.get(path) {
+                Some(replicated) => current != Some(replicated),
+                None => false,
+            };
+
+            if changed_on_target {
+                match self.policy {
+                    ReplicationPolicy::SourceWins => {}
+                    ReplicationPolicy::TargetWins => {
+                        replication.skipped.push(path.clone());
+                        continue;
+                    }
+                    ReplicationPolicy::FailFast => {
+                        conflicts = true;
+                        continue;
+                    }
+                }
+            }
+
+            let full_path = self.target.file_path(path);
+            let expected_sha = self.target.get_file(&full_path).await?.map(|file| file.sha);
+
+            let content = match wanted {
+                Some(file) => {
+                    replication.updated.push(path.clone());
+                    Some(file.content()?)
+                }
+                None => {
+                    replication.deleted.push(path.clone());
+                    None
+                }
+            };
+
+            changes.push(FileChange {
+                path: full_path,
+                content,
+                expected_sha,
+            });
+        }
+
+        if conflicts {
+            return Err(ClientError::Conflict);
+        }
+
+        if !changes.is_empty() {
+            let message = self.target.commit_message(&Operation::Replicate, None);
+            let commit = self
+                .target
+                .inner
+                .provider
+                .commit_files(&changes, &self.target.commit_options(&message))
+                .await?;
+            replication.commit = Some(commit);
+        }
+
+        for path in replication.updated.iter().chain(&replication.deleted) {
+            self.remember(path, source.get(path));
+        }
+
+        Ok(replication)
+    }
+
+    /// sync every `interval`, yielding what each sync changed
+    ///
+    /// errors are yielded without ending the stream, so a failed sync is
+    /// retried at the next interval.
+    pub fn run(self, interval: Duration) -> impl Stream<Item = Result<Replication, ClientError>> {
+        stream::unfold(self, move |mut replicator| async move {
+            Delay::new(interval).await;
+
+            let replication = replicator.sync().await;
+            Some((replication, replicator))
+        })
+    }
+
+    /// Whether a file belongs to one of the replicated collections.
+    fn includes(&self, path: &str) -> bool {
+        let collections = match &self.collections {
+            Some(collections) => collections,
+            None => return true,
+        };
+
+        // `reviews.json`, `reviews.index.json`, and `reviews/000001.json` all belong to `reviews`
+        let name = path
+            .split('/')
+            .next()
+            .and_then(|segment| segment.split('.').next())
+            .unwrap_or_default();
+
+        collections.iter().any(|collection| collection == name)
+    }
+
+    fn remember(&mut self, path: &str, file: Option<&BundleFile>) {
+        match file {
+            Some(file) => self.replicated.insert(path.to_string(), file.clone()),
+            None => self.replicated.remove(path),
+        };
+    }
+}
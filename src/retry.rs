@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode};
+
+/// How requests that fail for transient reasons are retried.
+///
+/// Reads are retried on connection errors, timeouts, and any status in
+/// `retry_on_status`. Writes aren't idempotent (a `PUT` that timed out may
+/// still have been committed) so by default they're only retried when the
+/// connection failed before the request was sent.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// total attempts including the first, `1` disables retrying
+    pub max_attempts: u32,
+    /// delay before the first retry, doubled for every retry after it
+    pub base_delay: Duration,
+    /// the longest delay between two attempts
+    pub max_delay: Duration,
+    /// randomize each delay between half and all of its value so clients don't retry in lockstep
+    pub jitter: bool,
+    /// response statuses that are worth retrying
+    pub retry_on_status: Vec<StatusCode>,
+    /// also retry writes on timeouts and `retry_on_status`, risking applying them twice
+    pub retry_writes: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_on_status: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_writes: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// never retry
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// whether a response with this status should be retried
+    pub(crate) fn retries_status(&self, method: &Method, status: StatusCode) -> bool {
+        (is_idempotent(method) || self.retry_writes) && self.retry_on_status.contains(&status)
+    }
+
+    /// whether a request that failed with this error should be retried
+    pub(crate) fn retries_error(&self, method: &Method, error: &reqwest::Error) -> bool {
+        // a failed connection means the request never reached github
+        is_connect(error) || ((is_idempotent(method) || self.retry_writes) && error.is_timeout())
+    }
+
+    /// how long to wait after the given (1-based) attempt failed
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        if self.jitter {
+            delay.mul_f64(0.5 + fastrand::f64() * 0.5)
+        } else {
+            delay
+        }
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_connect(error: &reqwest::Error) -> bool {
+    error.is_connect()
+}
+
+// the fetch api doesn't tell us why a request failed
+#[cfg(target_arch = "wasm32")]
+fn is_connect(_error: &reqwest::Error) -> bool {
+    false
+}
@@ -0,0 +1,157 @@
+//! JSON Schema enforcement for collections, enabled by the `schema` feature.
+//!
+//! A collection's schema is stored next to it as `<name>.schema.json`, so
+//! other tools editing the repository can check documents against the same
+//! contract. It's loaded when the collection is opened, and every write
+//! from the collection is checked against it before anything is committed.
+
+use std::sync::Arc;
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{Client, ClientError, Collection, Operation, RemoteFile};
+
+/// A collection's schema and its compiled validator.
+#[derive(Clone)]
+pub(crate) struct Schema {
+    value: Value,
+    validator: Arc<jsonschema::Validator>,
+}
+
+impl Schema {
+    fn new(value: Value) -> Result<Self, ClientError> {
+        match jsonschema::validator_for(&value) {
+            Ok(validator) => Ok(Self {
+                value,
+                validator: Arc::new(validator),
+            }),
+            Err(err) => Err(ClientError::InvalidSchema(err.to_string())),
+        }
+    }
+
+    /// Every way the documents don't conform.
+    pub(crate) fn violations<T: Serialize>(
+        &self,
+        documents: &[T],
+    ) -> Result<Vec<SchemaViolation>, ClientError> {
+        let mut violations = Vec::new();
+
+        for (position, document) in documents.iter().enumerate() {
+            let document = match serde_json::to_value(document) {
+                Ok(document) => document,
+                Err(err) => return Err(ClientError::Json(err)),
+            };
+
+            for error in self.validator.iter_errors(&document) {
+                violations.push(SchemaViolation {
+                    document: position,
+                    path: error.instance_path().to_string(),
+                    message: error.to_string(),
+                });
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+/// A way a document doesn't conform to its collection's schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// the position of the document in the collection
+    pub document: usize,
+    /// a JSON pointer to the offending value in the document
+    pub path: String,
+    pub message: String,
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// attach a JSON Schema to the collection, storing it as `<name>.schema.json`
+    ///
+    /// fails with [`ClientError::InvalidSchema`] if `schema` isn't a valid
+    /// schema, or [`ClientError::Validation`] if the documents already in
+    /// the collection don't conform to it.
+    pub async fn set_schema(&mut self, schema: Value) -> Result<(), ClientError> {
+        let schema = Schema::new(schema)?;
+
+        self.update().await?;
+        if let Some(violation) = schema.violations(&self.inner)?.into_iter().next() {
+            return Err(violation.into_error());
+        }
+
+        let content = match serde_json::to_vec_pretty(&schema.value) {
+            Ok(content) => content,
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+
+        let path = schema_path(&self.client, &self.name);
+        let sha = self.client.get_file(&path).await?.map(|file| file.sha);
+        let message = self.message(&Operation::SetSchema);
+        self.client
+            .put_file(&path, &content, sha.as_deref(), &message)
+            .await?;
+
+        self.schema = Some(schema);
+
+        Ok(())
+    }
+
+    /// the collection's JSON Schema, if it has one
+    pub fn schema(&self) -> Option<&Value> {
+        self.schema.as_ref().map(|schema| &schema.value)
+    }
+
+    /// syncs and returns every way the documents don't conform to the schema
+    ///
+    /// documents can stop conforming when the schema or the collection is
+    /// edited outside of this client, like through the github web interface.
+    pub async fn schema_violations(&mut self) -> Result<Vec<SchemaViolation>, ClientError> {
+        self.update().await?;
+
+        match &self.schema {
+            Some(schema) => schema.violations(&self.inner),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Check documents against the schema before they're written.
+    pub(crate) fn validate_schema(&self, documents: &[T]) -> Result<(), ClientError> {
+        match &self.schema {
+            Some(schema) => match schema.violations(documents)?.into_iter().next() {
+                Some(violation) => Err(violation.into_error()),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+}
+
+impl SchemaViolation {
+    fn into_error(self) -> ClientError {
+        ClientError::Validation(match self.path.is_empty() {
+            true => format!("document {}: {}", self.document, self.message),
+            false => format!(
+                "document {} at '{}': {}",
+                self.document, self.path, self.message
+            ),
+        })
+    }
+}
+
+/// Load a collection's schema from its schema file, if it has one.
+pub(crate) fn load(file: Option<&RemoteFile>) -> Result<Option<Schema>, ClientError> {
+    let file = match file {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+
+    match serde_json::from_slice(&file.content) {
+        Ok(schema) => Schema::new(schema).map(Some),
+        Err(err) => Err(ClientError::Json(err)),
+    }
+}
+
+pub(crate) fn schema_path(client: &Client, name: &str) -> String {
+    client.file_path(&format!("{name}.schema.json"))
+}
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    index::{self, field_value},
+    ClientError, Collection,
+};
+
+/// Term to the positions of the documents that contain it, and how many times.
+type Postings = BTreeMap<String, BTreeMap<usize, u32>>;
+
+/// The inverted index stored in the index file, see [`Collection::create_search_index`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SearchIndex {
+    /// the fields that are searched, every string in a document if empty
+    pub(crate) fields: Vec<String>,
+    terms: Postings,
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// syncs and returns the documents matching `query`, best match first
+    ///
+    /// documents match if they contain any of the words in `query`, ignoring
+    /// case, and are ranked by how often they contain them and how rare the
+    /// words are across the collection. without a
+    /// [search index](Self::create_search_index) every string in each
+    /// document is searched.
+    pub async fn search(&mut self, query: impl AsRef<str>) -> Result<Vec<&T>, ClientError> {
+        self.update().await?;
+
+        let terms = tokenize(query.as_ref());
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let stored = match index::read_index_file(&self.client, &self.name).await? {
+            // the positions are only right for the version of the collection it was built from
+            Some(index_file) if index_file.sha == self.sha && !self.has_expired() => {
+                index_file.search
+            }
+            _ => None,
+        };
+        let postings = match stored {
+            Some(search) if Some(&search.fields) == self.search.as_ref() => search.terms,
+            _ => build_postings(&self.inner, self.search.as_deref().unwrap_or_default())?,
+        };
+
+        let mut scores: BTreeMap<usize, f64> = BTreeMap::new();
+        for term in &terms {
+            let documents = match postings.get(term) {
+                Some(documents) => documents,
+                None => continue,
+            };
+
+            // rarer terms say more about a document
+            let idf = (1.0 + self.inner.len() as f64 / documents.len() as f64).ln();
+            for (position, count) in documents {
+                *scores.entry(*position).or_default() += f64::from(*count).sqrt() * idf;
+            }
+        }
+
+        let mut ranked: Vec<(usize, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        Ok(ranked
+            .into_iter()
+            .filter_map(|(position, _)| self.inner.get(position))
+            .collect())
+    }
+
+    /// build and maintain an inverted index over `fields` for [`search`](Self::search)
+    ///
+    /// nested fields are separated by dots, like `"author.name"`, and with no
+    /// fields every string in a document is indexed. the index is stored in
+    /// `<name>.index.json` and rebuilt on every write.
+    pub async fn create_search_index(&mut self, fields: &[&str]) -> Result<(), ClientError> {
+        self.update().await?;
+
+        self.search = Some(fields.iter().map(|field| field.to_string()).collect());
+
+        self.write_indexes().await
+    }
+
+    /// stop maintaining the search index, [`search`](Self::search) still works without it
+    pub async fn drop_search_index(&mut self) -> Result<(), ClientError> {
+        self.search = None;
+
+        self.remove_index().await
+    }
+}
+
+/// Build the inverted index over `fields` of `documents`.
+pub(crate) fn build_search_index<T: Serialize>(
+    documents: &[T],
+    fields: &[String],
+) -> Result<SearchIndex, ClientError> {
+    Ok(SearchIndex {
+        fields: fields.to_vec(),
+        terms: build_postings(documents, fields)?,
+    })
+}
+
+fn build_postings<T: Serialize>(
+    documents: &[T],
+    fields: &[String],
+) -> Result<Postings, ClientError> {
+    let mut postings = Postings::new();
+
+    for (position, document) in documents.iter().enumerate() {
+        let document = match serde_json::to_value(document) {
+            Ok(document) => document,
+            Err(err) => return Err(ClientError::Json(err)),
+        };
+
+        let mut text = Vec::new();
+        match fields.is_empty() {
+            true => strings(&document, &mut text),
+            false => {
+                for field in fields {
+                    if let Some(value) = field_value(&document, field) {
+                        strings(value, &mut text);
+                    }
+                }
+            }
+        }
+
+        for term in text.into_iter().flat_map(tokenize) {
+            *postings
+                .entry(term)
+                .or_default()
+                .entry(position)
+                .or_default() += 1;
+        }
+    }
+
+    Ok(postings)
+}
+
+/// Collect every string in `value`, including in nested arrays and objects.
+fn strings<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(string) => out.push(string),
+        Value::Array(values) => values.iter().for_each(|value| strings(value, out)),
+        Value::Object(fields) => fields.values().for_each(|value| strings(value, out)),
+        _ => {}
+    }
+}
+
+/// Split text into lowercase words.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
@@ -0,0 +1,218 @@
+use std::marker::PhantomData;
+
+use futures::{future, stream, Stream, TryStreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    decode_documents,
+    meta::{decode_collection, encode_collection},
+    Client, ClientError, CollectionMeta, CommitInfo, EntryKind, FileChange, Format, Json,
+    Operation, MAX_CONFLICT_RETRIES,
+};
+
+/// A collection split across several files so it never has to be loaded at once.
+///
+/// Every shard except the last holds exactly `shard_size` documents, which
+/// lets [`page`](Self::page) fetch only the shards covering the requested range.
+/// Shards are stored like any other collection file, with a metadata header.
+pub struct ShardedCollection<T> {
+    pub name: String,
+    client: Client,
+    format: Json,
+    shard_size: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> ShardedCollection<T> {
+    pub(crate) fn new(client: Client, name: String, shard_size: usize) -> Self {
+        Self {
+            name,
+            client,
+            format: Json,
+            shard_size: shard_size.max(1),
+            _marker: PhantomData,
+        }
+    }
+
+    /// stream every document, fetching one shard at a time
+    pub fn iter(&self) -> impl Stream<Item = Result<T, ClientError>> + '_ {
+        stream::once(self.shards())
+            .map_ok(|shards| stream::iter(shards.into_iter().map(Ok)))
+            .try_flatten()
+            .and_then(move |shard| self.read_shard(shard))
+            .map_ok(|documents| stream::iter(documents.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// return `limit` documents starting at `offset`, only fetching the shards they live in
+    pub async fn page(&self, offset: usize, limit: usize) -> Result<Vec<T>, ClientError> {
+        let shards = self.shards().await?;
+
+        let mut documents = Vec::new();
+        let mut skip = offset % self.shard_size;
+
+        for shard in shards.into_iter().skip(offset / self.shard_size) {
+            if documents.len() >= limit {
+                break;
+            }
+
+            let shard_documents = self.read_shard(shard).await?;
+
+            documents.extend(
+                shard_documents
+                    .into_iter()
+                    .skip(skip)
+                    .take(limit - documents.len()),
+            );
+            skip = 0;
+        }
+
+        Ok(documents)
+    }
+
+    /// push a document onto the last shard, starting a new one if it's full
+    pub async fn insert(&self, data: T) -> Result<CommitInfo, ClientError> {
+        let message = self.message(&Operation::Insert);
+        let mut data = Some(data);
+        let mut retries = 0;
+
+        loop {
+            let shards = self.shards().await?;
+
+            let (index, meta, mut documents, sha) = match shards.last() {
+                Some(&index) => {
+                    let path = self.shard_path(index);
+                    let file = match self.client.get_file(&path).await? {
+                        Some(file) => file,
+                        None => return Err(ClientError::NoContent),
+                    };
+                    let (meta, documents) = decode_collection(&self.format, &file.content)?;
+
+                    if documents.len() < self.shard_size {
+                        let meta = meta.unwrap_or_else(|| CollectionMeta::legacy(&self.format));
+                        (index, meta, documents, Some(file.sha))
+                    } else {
+                        (index + 1, self.new_meta(), Vec::new(), None)
+                    }
+                }
+                None => (0, self.new_meta(), Vec::new(), None),
+            };
+
+            documents.extend(data.take());
+
+            let content = encode_collection(&self.format, &meta, &documents)?;
+            let result = self
+                .client
+                .put_file(&self.shard_path(index), &content, sha.as_deref(), &message)
+                .await;
+
+            match result {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => {
+                    retries += 1;
+                    data = documents.pop();
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// overwrite every shard with `value`, removing any shards no longer needed
+    ///
+    /// the shards are written and removed in a single commit.
+    pub async fn set_as(&self, value: Vec<T>) -> Result<(), ClientError> {
+        let old_shards = self.shards().await?;
+
+        let mut changes = Vec::new();
+        for (index, chunk) in value.chunks(self.shard_size).enumerate() {
+            let path = self.shard_path(index);
+            let (meta, sha) = match self.client.get_file(&path).await? {
+                Some(file) => {
+                    let (meta, _) = decode_collection::<Value>(&self.format, &file.content)?;
+                    let meta = meta.unwrap_or_else(|| CollectionMeta::legacy(&self.format));
+                    (meta, Some(file.sha))
+                }
+                None => (self.new_meta(), None),
+            };
+
+            changes.push(FileChange {
+                path,
+                content: Some(encode_collection(&self.format, &meta, chunk)?),
+                expected_sha: sha,
+            });
+        }
+
+        let written = changes.len();
+        for index in old_shards.into_iter().filter(|index| *index >= written) {
+            let path = self.shard_path(index);
+
+            if let Some(file) = self.client.get_file(&path).await? {
+                changes.push(FileChange {
+                    path,
+                    content: None,
+                    expected_sha: Some(file.sha),
+                });
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let message = self.message(&Operation::Overwrite);
+        self.client.commit_files(&changes, &message).await?;
+
+        Ok(())
+    }
+
+    /// the index of every shard in the repository, in order
+    async fn shards(&self) -> Result<Vec<usize>, ClientError> {
+        let path = self.client.file_path(&self.name);
+        let suffix = format!(".{}", self.format.extension());
+
+        let mut shards: Vec<usize> = self
+            .client
+            .list_dir_pages(&path)
+            .try_filter_map(|entry| {
+                let shard = match entry.kind {
+                    EntryKind::File => entry
+                        .name
+                        .strip_suffix(&suffix)
+                        .and_then(|index| index.parse::<usize>().ok()),
+                    _ => None,
+                };
+                future::ready(Ok(shard))
+            })
+            .try_collect()
+            .await?;
+
+        shards.sort_unstable();
+
+        Ok(shards)
+    }
+
+    async fn read_shard(&self, index: usize) -> Result<Vec<T>, ClientError> {
+        match self.client.get_file(&self.shard_path(index)).await? {
+            Some(file) => decode_documents(&self.format, &file.content),
+            None => Err(ClientError::NoContent),
+        }
+    }
+
+    /// The commit message for `operation` on this collection.
+    fn message(&self, operation: &Operation) -> String {
+        self.client.commit_message(operation, Some(&self.name))
+    }
+
+    /// The metadata header for a new shard.
+    fn new_meta(&self) -> CollectionMeta {
+        CollectionMeta::new(&self.format, 0)
+    }
+
+    fn shard_path(&self, index: usize) -> String {
+        self.client.file_path(&format!(
+            "{}/{index:06}.{}",
+            self.name,
+            self.format.extension()
+        ))
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{ClientError, Collection, CommitInfo};
+
+/// A [`Collection`] that can be cloned and used from several tasks at once.
+///
+/// Any number of tasks can [read](Self::read) at the same time, while
+/// writes take turns. Waiting tasks are served first-in first-out, so
+/// mutations are applied in the order they were called, and a read waiting
+/// behind a write sees its result.
+pub struct SharedCollection<T> {
+    inner: Arc<RwLock<Collection<T>>>,
+}
+
+impl<T> Clone for SharedCollection<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// share the collection between tasks, see [`SharedCollection`]
+    pub fn into_shared(self) -> SharedCollection<T> {
+        SharedCollection {
+            inner: Arc::new(RwLock::new(self)),
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> SharedCollection<T> {
+    /// wait for pending writes, then borrow the collection alongside other readers
+    ///
+    /// the collection's `&self` methods, like aggregations and history,
+    /// can be used through the guard.
+    pub async fn read(&self) -> impl Deref<Target = Collection<T>> + '_ {
+        self.inner.read().await
+    }
+
+    /// wait for every earlier read and write, then borrow the collection exclusively
+    pub async fn write(&self) -> impl DerefMut<Target = Collection<T>> + '_ {
+        self.inner.write().await
+    }
+
+    /// the documents as of the last sync, without fetching
+    pub async fn snapshot(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.read().await.inner.clone()
+    }
+
+    /// syncs and returns all documents
+    pub async fn data(&self) -> Result<Vec<T>, ClientError>
+    where
+        T: Clone,
+    {
+        self.write().await.data().await.cloned()
+    }
+
+    /// update the collection to be in line with the database
+    pub async fn update(&self) -> Result<(), ClientError> {
+        self.write().await.update().await
+    }
+
+    /// push document to the database
+    pub async fn insert(&self, data: T) -> Result<CommitInfo, ClientError> {
+        self.write().await.insert(data).await
+    }
+
+    /// push document to the database with `message` as the commit message
+    pub async fn insert_with(
+        &self,
+        data: T,
+        message: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError> {
+        self.write().await.insert_with(data, message).await
+    }
+
+    /// push document to the database unless another document has the same key, see [`Collection::insert_unique`]
+    pub async fn insert_unique<K, F>(&self, data: T, key: F) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        self.write().await.insert_unique(data, key).await
+    }
+
+    /// replace the document with the same key as `data`, or push it if there isn't one
+    pub async fn upsert<K, F>(&self, data: T, key: F) -> Result<CommitInfo, ClientError>
+    where
+        K: PartialEq,
+        F: Fn(&T) -> K,
+    {
+        self.write().await.upsert(data, key).await
+    }
+
+    /// overwrite the entire collection
+    pub async fn set_as(&self, value: Vec<T>) -> Result<CommitInfo, ClientError> {
+        self.write().await.set_as(value).await
+    }
+
+    /// overwrite the entire collection with `message` as the commit message
+    pub async fn set_as_with(
+        &self,
+        value: Vec<T>,
+        message: impl AsRef<str>,
+    ) -> Result<CommitInfo, ClientError> {
+        self.write().await.set_as_with(value, message).await
+    }
+}
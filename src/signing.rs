@@ -0,0 +1,23 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+
+use crate::{ClientError, CommitAuthor};
+
+/// Signs the commits a [`Client`](crate::Client) creates, so github shows them as verified.
+///
+/// With a signer, every write goes through the Git Data API, which accepts
+/// a signature for each commit, instead of the contents API. Signing is
+/// only supported on github. Set with [`ClientBuilder::signer`](crate::ClientBuilder::signer).
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait CommitSigner: Debug + Send + Sync {
+    /// who signed commits are committed by, which must match the key's identity for github to verify them
+    fn committer(&self) -> CommitAuthor;
+
+    /// sign the raw git commit object, returning an ascii armored PGP or SSH signature
+    ///
+    /// this is the same payload `git commit -S` passes to gpg, so it can
+    /// be signed with `gpg --detach-sign --armor` or `ssh-keygen -Y sign -n git`.
+    async fn sign(&self, payload: &[u8]) -> Result<String, ClientError>;
+}
@@ -0,0 +1,57 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    aggregate::compare_values, index::field_value, ClientError, Collection, CommitInfo, Operation,
+};
+
+/// Orders documents before they're written, see [`Collection::sort_by`].
+pub(crate) type OrderFn<T> = Arc<dyn Fn(&T, &T) -> Ordering + Send + Sync>;
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// sort the collection with `compare` and keep it sorted on every write from this collection
+    ///
+    /// the sort is stable, so documents that compare equal keep their
+    /// insertion order and the stored order is deterministic.
+    pub async fn sort_by<F>(&mut self, compare: F) -> Result<CommitInfo, ClientError>
+    where
+        F: Fn(&T, &T) -> Ordering + Send + Sync + 'static,
+    {
+        self.order = Some(Arc::new(compare));
+
+        self.update().await?;
+        let message = self.message(&Operation::Sort);
+        self.write(&Operation::Sort, &message).await
+    }
+
+    /// syncs and returns all documents ordered by `field`, without changing the stored order
+    ///
+    /// numbers, strings, and booleans are compared with others of the same
+    /// type, documents without the field come last
+    pub async fn data_sorted_by(&mut self, field: impl AsRef<str>) -> Result<Vec<&T>, ClientError> {
+        let field = field.as_ref();
+
+        self.update().await?;
+
+        let mut documents = Vec::with_capacity(self.inner.len());
+        for document in &self.inner {
+            match serde_json::to_value(document) {
+                Ok(value) => documents.push((field_value(&value, field).cloned(), document)),
+                Err(err) => return Err(ClientError::Json(err)),
+            }
+        }
+
+        documents.sort_by(|(a, _), (b, _)| match (a, b) {
+            (Some(a), Some(b)) => compare_values(a, b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        });
+
+        Ok(documents
+            .into_iter()
+            .map(|(_, document)| document)
+            .collect())
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{expiry, meta, ClientError, Collection, HistoryEntry, CONTENTS_API_LIMIT};
+
+/// How big a collection is and how often it changes, see [`Collection::stats`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CollectionStats {
+    /// the number of documents stored, leaving out expired ones
+    pub documents: usize,
+    /// the size of the collection's file in bytes, as stored
+    pub size: usize,
+    /// the commit that last changed the collection
+    pub last_commit: Option<HistoryEntry>,
+    /// how many commits have changed the collection
+    pub commits: usize,
+    /// how many more bytes the file can take before reads fall back to the Git Data API, see [`CONTENTS_API_LIMIT`]
+    pub headroom: usize,
+}
+
+impl CollectionStats {
+    /// the share of [`CONTENTS_API_LIMIT`] the collection's file takes, above 1.0 once it's past it
+    pub fn usage(&self) -> f64 {
+        self.size as f64 / CONTENTS_API_LIMIT as f64
+    }
+}
+
+impl<T: DeserializeOwned> Collection<T> {
+    /// how big the collection is and how many commits have changed it, for monitoring growth
+    ///
+    /// like [`data_as`](Self::data_as) it reads the file as stored without
+    /// syncing the collection. counting the commits lists the collection's
+    /// whole history, a request per hundred commits on github.
+    pub async fn stats(&self) -> Result<CollectionStats, ClientError> {
+        let file = match self.client.get_file(&self.path).await? {
+            Some(file) => file,
+            None => return Err(ClientError::NoContent),
+        };
+
+        let (meta, mut documents) =
+            meta::decode_collection::<Value>(self.format.as_ref(), &file.content)?;
+        if let Some(meta) = meta {
+            expiry::retain_unexpired_values(&mut documents, self.key.as_ref(), &meta.expires)?;
+        }
+
+        let history = self
+            .client
+            .inner
+            .provider
+            .list_commits(
+                &self.path,
+                usize::MAX,
+                None,
+                self.client.inner.branch.as_deref(),
+            )
+            .await?;
+
+        Ok(CollectionStats {
+            documents: documents.len(),
+            size: file.content.len(),
+            commits: history.len(),
+            last_commit: history.into_iter().next(),
+            headroom: CONTENTS_API_LIMIT.saturating_sub(file.content.len()),
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Client, ClientError, Collection, Operation};
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// open the collection at `path` under one of this collection's documents, creating it if it doesn't exist
+    ///
+    /// `path` alternates document ids and collection names, like
+    /// `"alice/orders"` for the orders of the document `alice`, or
+    /// `"alice/orders/1001/items"` to go deeper. it's stored in this
+    /// collection's format at `<name>/<path>`, like `users/alice/orders.json`,
+    /// and works like any other collection. ids and names can't be empty or
+    /// contain `.`.
+    pub async fn subcollection<U: Serialize + DeserializeOwned>(
+        &self,
+        path: impl AsRef<str>,
+    ) -> Result<Collection<U>, ClientError> {
+        let path = path.as_ref();
+        if !is_subcollection_path(path) {
+            return Err(ClientError::InvalidKey(path.to_string()));
+        }
+
+        let name = format!("{}/{path}", self.name);
+
+        match self
+            .client
+            .load_collection(name.clone(), self.format.clone())
+            .await
+        {
+            Err(err) => Err(err.in_collection(&name, "open")),
+            collection => collection,
+        }
+    }
+
+    /// the paths of every subcollection under this collection's documents, like `"alice/orders"`, sorted
+    ///
+    /// nested subcollections are included, like `"alice/orders/1001/items"`.
+    pub async fn list_subcollections(&self) -> Result<Vec<String>, ClientError> {
+        let extension = format!(".{}", self.format.extension());
+
+        let mut paths: Vec<String> = self
+            .directory(None)
+            .list_files()
+            .await?
+            .into_iter()
+            .filter_map(|file| Some(file.strip_suffix(&extension)?.to_string()))
+            // skips the index, schema, and attachment files kept alongside them
+            .filter(|path| is_subcollection_path(path))
+            .collect();
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    /// delete every subcollection under `document` in a single commit, returning how many files were deleted
+    ///
+    /// everything stored with them goes too, like their indexes,
+    /// attachments, and subcollections of their own. the document itself
+    /// isn't touched.
+    pub async fn delete_subcollections(
+        &self,
+        document: impl AsRef<str>,
+    ) -> Result<usize, ClientError> {
+        let document = document.as_ref();
+        if !is_segment(document) {
+            return Err(ClientError::InvalidKey(document.to_string()));
+        }
+
+        let message = self.message(&Operation::DeleteSubcollections {
+            document: document.to_string(),
+        });
+        self.client
+            .delete_directory(&self.directory(Some(document)), &message)
+            .await
+    }
+
+    /// A client whose path prefix is this collection's directory, or a document's directory in it.
+    fn directory(&self, document: Option<&str>) -> Client {
+        let path = match document {
+            Some(document) => format!("{}/{document}/", self.name),
+            None => format!("{}/", self.name),
+        };
+        let path_prefix = self.client.file_path(&path);
+
+        self.client
+            .derive(|inner| inner.path_prefix = Some(path_prefix))
+    }
+}
+
+/// Whether `path` is pairs of document ids and collection names.
+fn is_subcollection_path(path: &str) -> bool {
+    let segments: Vec<&str> = path.split('/').collect();
+
+    segments.len().is_multiple_of(2) && segments.iter().all(|segment| is_segment(segment))
+}
+
+/// Whether `segment` can be a document id or collection name in a subcollection path, which keeps them apart from a collection's other files.
+fn is_segment(segment: &str) -> bool {
+    !segment.is_empty() && !segment.contains(['/', '.'])
+}
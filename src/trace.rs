@@ -0,0 +1,97 @@
+//! Spans for the `tracing` feature, which compile to nothing without it.
+
+use std::future::Future;
+
+use reqwest::{Method, Response};
+use url::Url;
+
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, Instrument, Span};
+
+/// Run a collection operation inside a `collection` span.
+#[cfg(feature = "tracing")]
+pub(crate) async fn collection<F: Future>(
+    collection: &str,
+    operation: &str,
+    future: F,
+) -> F::Output {
+    future
+        .instrument(tracing::info_span!("collection", collection, operation))
+        .await
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) async fn collection<F: Future>(
+    _collection: &str,
+    _operation: &str,
+    future: F,
+) -> F::Output {
+    future.await
+}
+
+/// Run an http request, including its retries, inside a `request` span.
+#[cfg(feature = "tracing")]
+pub(crate) async fn request<F: Future>(method: &Method, url: &Url, future: F) -> F::Output {
+    let span = tracing::info_span!(
+        "request",
+        %method,
+        %url,
+        status = Empty,
+        rate_limit_remaining = Empty,
+        retries = 0,
+        error = Empty,
+    );
+
+    future.instrument(span).await
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) async fn request<F: Future>(_method: &Method, _url: &Url, future: F) -> F::Output {
+    future.await
+}
+
+/// Record a response on the current `request` span.
+#[cfg(feature = "tracing")]
+pub(crate) fn response(response: &Response, retries: u32) {
+    let span = Span::current();
+    span.record("status", response.status().as_u16());
+    span.record("retries", retries);
+
+    // github and gitea send `X-RateLimit-Remaining`, gitlab sends `RateLimit-Remaining`
+    if let Some(remaining) = ["x-ratelimit-remaining", "ratelimit-remaining"]
+        .into_iter()
+        .find_map(|header| response.headers().get(header))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+    {
+        span.record("rate_limit_remaining", remaining);
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn response(_response: &Response, _retries: u32) {}
+
+/// Record a request that failed without a response on the current `request` span.
+#[cfg(feature = "tracing")]
+pub(crate) fn error(error: &reqwest::Error, retries: u32) {
+    let span = Span::current();
+    span.record("error", tracing::field::display(error));
+    span.record("retries", retries);
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn error(_error: &reqwest::Error, _retries: u32) {}
+
+/// Warn that a collection's file is close to the largest the contents api returns.
+#[cfg(feature = "tracing")]
+pub(crate) fn approaching_limit(collection: &str, size: usize, limit: usize) {
+    tracing::warn!(
+        collection,
+        size,
+        limit,
+        "collection is approaching the contents api size limit"
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn approaching_limit(_collection: &str, _size: usize, _limit: usize) {}
@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Client, ClientError, Collection, CommitInfo, FileChange, Operation};
+
+/// A set of changes to several collections that are committed together.
+///
+/// Changes are staged locally and then written as a single commit through
+/// [`Provider::commit_files`](crate::Provider::commit_files), so readers never
+/// observe a partially applied change. Each collection is written like any
+/// other overwrite, with its validators, hooks, limits, sort order, and
+/// index file. If any staged collection changed in the repository since it
+/// was last synced, or another commit lands on the branch first,
+/// [`commit`](Self::commit) fails with [`ClientError::Conflict`] and nothing
+/// is written.
+///
+/// Once committed, the staged collections hold the documents they were set to.
+pub struct Transaction<'a> {
+    client: Client,
+    staged: Vec<Box<dyn StagedWrite + 'a>>,
+}
+
+/// A collection staged in a [`Transaction`], with the type of its documents erased.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+trait StagedWrite {
+    fn path(&self) -> &str;
+
+    /// Swap in the staged documents and return the changes that write them, the collection file first.
+    async fn stage(&mut self, message: &str) -> Result<Vec<FileChange>, ClientError>;
+
+    /// Put back the documents the collection had before it was staged.
+    fn abort(&mut self);
+
+    /// Record that the collection file was committed with `commit`.
+    async fn committed(&mut self, message: &str, commit: CommitInfo) -> Result<(), ClientError>;
+}
+
+struct Staged<'a, T> {
+    collection: &'a mut Collection<T>,
+    /// the staged documents, and once they're swapped in the ones they replaced
+    documents: Vec<T>,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl<T: Serialize + DeserializeOwned + Send + Sync> StagedWrite for Staged<'_, T> {
+    fn path(&self) -> &str {
+        &self.collection.path
+    }
+
+    async fn stage(&mut self, message: &str) -> Result<Vec<FileChange>, ClientError> {
+        std::mem::swap(&mut self.collection.inner, &mut self.documents);
+
+        self.collection.stage(&Operation::Overwrite, message).await
+    }
+
+    fn abort(&mut self) {
+        std::mem::swap(&mut self.collection.inner, &mut self.documents);
+    }
+
+    async fn committed(&mut self, message: &str, commit: CommitInfo) -> Result<(), ClientError> {
+        self.collection.committed(commit.sha.clone());
+
+        self.collection
+            .after_write_hooks(&Operation::Overwrite, message, &commit)
+            .await
+    }
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn new(client: Client) -> Self {
+        Self {
+            client,
+            staged: Vec::new(),
+        }
+    }
+
+    /// stage overwriting a collection with `documents`
+    ///
+    /// the collection keeps its documents until the transaction is committed.
+    pub fn set<T: Serialize + DeserializeOwned + Send + Sync>(
+        &mut self,
+        collection: &'a mut Collection<T>,
+        documents: Vec<T>,
+    ) -> Result<&mut Self, ClientError> {
+        collection.validate(&documents)?;
+
+        // staging the same collection twice keeps only the latest documents
+        self.staged
+            .retain(|staged| staged.path() != collection.path);
+        self.staged.push(Box::new(Staged {
+            collection,
+            documents,
+        }));
+
+        Ok(self)
+    }
+
+    /// write every staged change in a single commit, returning the commit's sha
+    pub async fn commit(mut self, message: impl AsRef<str>) -> Result<String, ClientError> {
+        let message = message.as_ref();
+
+        let mut changes = Vec::new();
+        // where each collection's file is in `changes`
+        let mut files = Vec::with_capacity(self.staged.len());
+        // how many collections have their staged documents swapped in
+        let mut swapped = 0;
+        let mut result = Ok(String::new());
+
+        for staged in &mut self.staged {
+            swapped += 1;
+            match staged.stage(message).await {
+                Ok(staged) => {
+                    files.push(changes.len());
+                    changes.extend(staged);
+                }
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        if result.is_ok() {
+            result = self.client.commit_files(&changes, message).await;
+        }
+
+        let commit = match result {
+            Ok(commit) => commit,
+            Err(err) => {
+                for staged in &mut self.staged[..swapped] {
+                    staged.abort();
+                }
+                return Err(err);
+            }
+        };
+
+        for (staged, file) in self.staged.iter_mut().zip(files) {
+            let content = changes[file].content.as_deref().unwrap_or_default();
+            let info = CommitInfo {
+                sha: self
+                    .client
+                    .inner
+                    .provider
+                    .version_after_commit(content, &commit),
+                commit: Some(commit.clone()),
+                ..CommitInfo::default()
+            };
+
+            staged.committed(message, info).await?;
+        }
+
+        Ok(commit)
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ClientError, Collection, FileChange, Operation, Record, RemoteFile, MAX_CONFLICT_RETRIES,
+};
+
+/// A soft deleted document in a collection's trash.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trashed<T> {
+    pub deleted_at: DateTime<Utc>,
+    pub document: T,
+}
+
+impl<T: Record> Collection<T> {
+    /// move the document with the primary key `id` into the collection's trash, returning whether it existed
+    ///
+    /// trashed documents are stored in `<name>.trash.json` (in the
+    /// collection's format) until they're [`restore`](Self::restore)d or
+    /// [`purge`](Self::purge)d. the document is removed and trashed in a single commit.
+    pub async fn soft_delete(&mut self, id: &T::Id) -> Result<bool, ClientError> {
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+            let (file, mut trash) = self.read_trash().await?;
+
+            let position = match self.inner.iter().position(|document| document.id() == id) {
+                Some(position) => position,
+                None => return Ok(false),
+            };
+            let document = self.inner.remove(position);
+            trash.push(Trashed {
+                deleted_at: Utc::now(),
+                document,
+            });
+
+            let operation = Operation::SoftDelete;
+            let message = self.message(&operation);
+            match self
+                .write_with_trash(&operation, file, &trash, &message)
+                .await
+            {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(true),
+            }
+        }
+    }
+
+    /// move the document with the primary key `id` out of the trash and back into the collection
+    ///
+    /// returns whether it was in the trash
+    pub async fn restore(&mut self, id: &T::Id) -> Result<bool, ClientError> {
+        let mut retries = 0;
+
+        loop {
+            self.update().await?;
+            let (file, mut trash) = self.read_trash().await?;
+
+            // restore the most recently deleted version
+            let position = match trash
+                .iter()
+                .rposition(|trashed| trashed.document.id() == id)
+            {
+                Some(position) => position,
+                None => return Ok(false),
+            };
+            if self.inner.iter().any(|document| document.id() == id) {
+                return Err(ClientError::DuplicateKey);
+            }
+            self.inner.push(trash.remove(position).document);
+
+            let operation = Operation::Restore;
+            let message = self.message(&operation);
+            match self
+                .write_with_trash(&operation, file, &trash, &message)
+                .await
+            {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(true),
+            }
+        }
+    }
+
+    /// the documents in the trash, oldest deletion first
+    pub async fn trash(&self) -> Result<Vec<Trashed<T>>, ClientError> {
+        self.read_trash().await.map(|(_, trash)| trash)
+    }
+
+    /// permanently delete documents that have been in the trash for longer than `older_than`
+    ///
+    /// returns how many documents were deleted
+    pub async fn purge(&mut self, older_than: Duration) -> Result<usize, ClientError> {
+        let older_than = match chrono::Duration::from_std(older_than) {
+            Ok(older_than) => older_than,
+            Err(_) => return Ok(0),
+        };
+        let cutoff = Utc::now() - older_than;
+        let mut retries = 0;
+
+        loop {
+            let (file, mut trash) = self.read_trash().await?;
+            let file = match file {
+                Some(file) => file,
+                None => return Ok(0),
+            };
+
+            let before = trash.len();
+            trash.retain(|trashed| trashed.deleted_at > cutoff);
+            let purged = before - trash.len();
+            if purged == 0 {
+                return Ok(0);
+            }
+
+            let message = self.message(&Operation::PurgeTrash);
+            let result = if trash.is_empty() {
+                self.client
+                    .delete_file(&self.trash_path(), &file.sha, &message)
+                    .await
+            } else {
+                let content = self.encode_trash(&trash)?;
+                self.client
+                    .put_file(&self.trash_path(), &content, Some(&file.sha), &message)
+                    .await
+                    .map(|_| ())
+            };
+
+            match result {
+                Err(ClientError::Conflict) if retries < MAX_CONFLICT_RETRIES => retries += 1,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(purged),
+            }
+        }
+    }
+
+    fn trash_path(&self) -> String {
+        let name = format!("{}.trash.{}", self.name, self.format.extension());
+
+        self.client.file_path(&name)
+    }
+
+    async fn read_trash(&self) -> Result<(Option<RemoteFile>, Vec<Trashed<T>>), ClientError> {
+        let file = match self.client.get_file(&self.trash_path()).await? {
+            Some(file) => file,
+            None => return Ok((None, Vec::new())),
+        };
+
+        let value = self.format.deserialize(&file.content)?;
+        match serde_json::from_value(value) {
+            Ok(trash) => Ok((Some(file), trash)),
+            Err(err) => Err(ClientError::Json(err)),
+        }
+    }
+
+    fn encode_trash(&self, trash: &[Trashed<T>]) -> Result<Vec<u8>, ClientError> {
+        match serde_json::to_value(trash) {
+            Ok(value) => self.format.serialize(&value),
+            Err(err) => Err(ClientError::Json(err)),
+        }
+    }
+
+    /// Write the current documents for `operation` and commit `trash` with them.
+    async fn write_with_trash(
+        &mut self,
+        operation: &Operation,
+        trash_file: Option<RemoteFile>,
+        trash: &[Trashed<T>],
+        message: &str,
+    ) -> Result<(), ClientError> {
+        let change = FileChange {
+            path: self.trash_path(),
+            // an empty trash is deleted
+            content: if trash.is_empty() {
+                None
+            } else {
+                Some(self.encode_trash(trash)?)
+            },
+            expected_sha: trash_file.map(|file| file.sha),
+        };
+
+        self.write_with_files(operation, message, vec![change])
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{ClientError, Collection};
+
+type DocumentFn<T> = Arc<dyn Fn(&T) -> Result<(), String> + Send + Sync>;
+type CollectionFn<T> = Arc<dyn Fn(&[T]) -> Result<(), String> + Send + Sync>;
+
+/// A check run on documents before they're written, see [`Collection::with_validator`].
+pub(crate) enum Validator<T> {
+    Document(DocumentFn<T>),
+    Collection(CollectionFn<T>),
+}
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// check every document with `validator` before each write
+    ///
+    /// writes with a document `validator` rejects fail with
+    /// [`ClientError::Validation`] and nothing is committed.
+    pub fn with_validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validators
+            .push(Validator::Document(Arc::new(validator)));
+        self
+    }
+
+    /// check all the documents together with `validator` before each write, for rules like uniqueness across documents
+    pub fn with_collection_validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&[T]) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validators
+            .push(Validator::Collection(Arc::new(validator)));
+        self
+    }
+
+    /// Run every validator, and the schema if there is one, over `documents`.
+    pub(crate) fn validate(&self, documents: &[T]) -> Result<(), ClientError> {
+        #[cfg(feature = "schema")]
+        self.validate_schema(documents)?;
+
+        for validator in &self.validators {
+            match validator {
+                Validator::Document(validator) => {
+                    for (position, document) in documents.iter().enumerate() {
+                        if let Err(err) = validator(document) {
+                            return Err(ClientError::Validation(format!(
+                                "document {position}: {err}"
+                            )));
+                        }
+                    }
+                }
+                Validator::Collection(validator) => {
+                    if let Err(err) = validator(documents) {
+                        return Err(ClientError::Validation(err));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mock::MockClient, ClientError};
+
+    #[tokio::test]
+    async fn rejected_insert_leaves_documents_unchanged() {
+        let mock = MockClient::new();
+        let mut numbers = mock
+            .collection::<u32>("numbers")
+            .await
+            .unwrap()
+            .with_validator(|n| match *n < 10 {
+                true => Ok(()),
+                false => Err("too big".to_string()),
+            });
+
+        numbers.insert(1).await.unwrap();
+
+        let result = numbers.insert(20).await;
+        assert!(matches!(result, Err(ClientError::Validation(_))));
+        assert_eq!(numbers.inner, vec![1]);
+        assert_eq!(numbers.data().await.unwrap(), &vec![1]);
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{expiry, meta, Client, ClientError, Collection, Polled};
+
+impl<T: Serialize + DeserializeOwned> Collection<T> {
+    /// poll the collection every `interval`, yielding a snapshot of its documents whenever it changes
+    ///
+    /// this catches edits made outside the client, like through the
+    /// github web interface. reads are conditional, so polling a collection
+    /// that hasn't changed doesn't count against github's rate limit. errors
+    /// are yielded without ending the stream, so a failed poll is retried
+    /// at the next interval.
+    ///
+    /// if webhooks can reach your service, the `webhook` feature reports
+    /// changes without polling.
+    pub fn watch(&self, interval: Duration) -> impl Stream<Item = Result<Vec<T>, ClientError>> {
+        let client = self.client.clone();
+        let path = self.path.clone();
+        let format = self.format.clone();
+        let key = self.key.clone();
+
+        // only report changes made after the collection was last synced
+        stream::unfold(self.sha.clone(), move |mut sha| {
+            let client = client.clone();
+            let path = path.clone();
+            let format = format.clone();
+            let key = key.clone();
+
+            async move {
+                loop {
+                    Delay::new(interval).await;
+
+                    let file = match client.poll_file(&path, &sha).await {
+                        Ok(Polled::Unchanged) => continue,
+                        Ok(Polled::Changed(Some(file))) => file,
+                        Ok(Polled::Changed(None)) => {
+                            return Some((Err(ClientError::NoContent), sha))
+                        }
+                        Err(err) => return Some((Err(err), sha)),
+                    };
+
+                    sha = file.sha;
+                    let documents = match meta::decode_collection(format.as_ref(), &file.content) {
+                        Ok((Some(meta), mut documents)) => {
+                            expiry::retain_unexpired(&mut documents, key.as_ref(), &meta.expires)
+                                .map(|()| documents)
+                        }
+                        Ok((None, documents)) => Ok(documents),
+                        Err(err) => Err(err),
+                    };
+                    return Some((documents, sha));
+                }
+            }
+        })
+    }
+}
+
+impl Client {
+    /// Fetch a file unless it's still at version `sha`, updating its offline snapshot if it changed.
+    async fn poll_file(&self, path: &str, sha: &str) -> Result<Polled, ClientError> {
+        let polled = self
+            .inner
+            .provider
+            .poll_file(path, self.inner.branch.as_deref(), sha)
+            .await?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let (Polled::Changed(file), Some(cache)) = (&polled, &self.inner.offline) {
+            cache.store(&self.snapshot_path(path), file.as_ref());
+        }
+
+        Ok(polled)
+    }
+}
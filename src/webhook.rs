@@ -0,0 +1,207 @@
+//! Change notifications from github push webhooks, enabled by the `webhook` feature.
+//!
+//! Point a repository webhook (content type `application/json`) at your
+//! service, then pass every delivery's `X-Hub-Signature-256` header and raw
+//! body to [`Webhook::handle`]. The signature is checked against the
+//! webhook's secret, and a [`ChangeEvent`] is sent to every
+//! [`events`](Webhook::events) stream for each collection the push changed,
+//! so caches can be invalidated or [`Collection::update`](crate::Collection::update)
+//! called only when data actually changed.
+//!
+//! Files are mapped to collections by their first path segment (after the
+//! path prefix) up to the first `.`, so `reviews.json`, `reviews.index.json`,
+//! and `reviews/000001.json` all belong to `reviews`.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    Stream,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::ClientError;
+
+/// Collections changed by a single push.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeEvent {
+    /// the name of the collection
+    pub collection: String,
+    /// every file in the collection that was added, modified, or removed
+    pub paths: Vec<String>,
+    /// the sha of the newest commit in the push
+    pub commit: String,
+}
+
+/// Verifies and parses push webhooks, see the [module documentation](self).
+#[derive(Debug)]
+pub struct Webhook {
+    secret: Vec<u8>,
+    path_prefix: Option<String>,
+    branch: Option<String>,
+    subscribers: Mutex<Vec<UnboundedSender<ChangeEvent>>>,
+}
+
+/// The parts of a push payload we care about.
+#[derive(Deserialize)]
+struct PushPayload {
+    #[serde(rename = "ref")]
+    reference: String,
+    after: String,
+    #[serde(default)]
+    commits: Vec<PushCommit>,
+}
+
+#[derive(Deserialize)]
+struct PushCommit {
+    #[serde(default)]
+    added: Vec<String>,
+    #[serde(default)]
+    modified: Vec<String>,
+    #[serde(default)]
+    removed: Vec<String>,
+}
+
+impl Webhook {
+    /// Verify deliveries with the secret the webhook was configured with.
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: secret.into(),
+            path_prefix: None,
+            branch: None,
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// only report files under the client's path prefix, like `"data/"`
+    pub fn path_prefix(mut self, path_prefix: impl Into<String>) -> Self {
+        self.path_prefix = Some(path_prefix.into());
+        self
+    }
+
+    /// only report pushes to `branch`, pushes to every branch are reported by default
+    pub fn branch(mut self, branch: impl Into<String>) -> Self {
+        self.branch = Some(branch.into());
+        self
+    }
+
+    /// a stream of every change reported after this is called
+    pub fn events(&self) -> impl Stream<Item = ChangeEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers().push(sender);
+
+        receiver
+    }
+
+    /// check the `X-Hub-Signature-256` header of a delivery against its raw body
+    pub fn verify(&self, signature: &str, body: &[u8]) -> Result<(), ClientError> {
+        let expected = match signature.strip_prefix("sha256=").and_then(decode_hex) {
+            Some(expected) => expected,
+            None => return Err(ClientError::InvalidSignature),
+        };
+
+        let mut mac = match Hmac::<Sha256>::new_from_slice(&self.secret) {
+            Ok(mac) => mac,
+            Err(_) => return Err(ClientError::InvalidSignature),
+        };
+        mac.update(body);
+
+        // compared in constant time
+        match mac.verify_slice(&expected) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(ClientError::InvalidSignature),
+        }
+    }
+
+    /// verify and parse a push delivery, returning and broadcasting the collections it changed
+    ///
+    /// deliveries for other events, like `ping`, have no commits and
+    /// produce no changes
+    pub fn handle(&self, signature: &str, body: &[u8]) -> Result<Vec<ChangeEvent>, ClientError> {
+        self.verify(signature, body)?;
+
+        let payload: PushPayload = match serde_json::from_slice(body) {
+            Ok(payload) => payload,
+            // not a push
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        if let Some(branch) = &self.branch {
+            if payload.reference != format!("refs/heads/{branch}") {
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut collections: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for commit in &payload.commits {
+            for path in commit
+                .added
+                .iter()
+                .chain(&commit.modified)
+                .chain(&commit.removed)
+            {
+                if let Some(collection) = self.collection_name(path) {
+                    let paths = collections.entry(collection).or_default();
+                    if !paths.contains(path) {
+                        paths.push(path.clone());
+                    }
+                }
+            }
+        }
+
+        let events: Vec<ChangeEvent> = collections
+            .into_iter()
+            .map(|(collection, paths)| ChangeEvent {
+                collection,
+                paths,
+                commit: payload.after.clone(),
+            })
+            .collect();
+
+        // forget subscribers whose stream was dropped
+        self.subscribers().retain(|subscriber| {
+            events
+                .iter()
+                .all(|event| subscriber.unbounded_send(event.clone()).is_ok())
+        });
+
+        Ok(events)
+    }
+
+    /// The collection a changed file belongs to.
+    fn collection_name(&self, path: &str) -> Option<String> {
+        let path = match &self.path_prefix {
+            Some(prefix) => path.strip_prefix(prefix.as_str())?,
+            None => path,
+        };
+
+        let segment = path.split('/').next()?;
+        let name = segment.split('.').next()?;
+
+        if name.is_empty() {
+            None
+        } else {
+            Some(name.to_string())
+        }
+    }
+
+    fn subscribers(&self) -> std::sync::MutexGuard<'_, Vec<UnboundedSender<ChangeEvent>>> {
+        match self.subscribers.lock() {
+            Ok(subscribers) => subscribers,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}